@@ -0,0 +1,471 @@
+use crate::{BTreeMap, BTreeStore};
+use std::borrow::Borrow;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::ops::RangeBounds;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+
+/// A bidirectional b-tree map: a one-to-one relation between left and right values, ordered and
+/// searchable from either side.
+///
+/// It's a map from left to right values and a map from right to left values, so each value is
+/// stored (cloned) in both. The maps are allocated in separate stores, because their nodes have
+/// different key and value types.
+pub struct BiBTreeMap<'store, L, R> {
+    left: BTreeMap<'store, L, R>,
+    right: BTreeMap<'store, R, L>,
+}
+
+impl<'store, L, R> BiBTreeMap<'store, L, R> {
+    /// Creates an empty bidirectional map, whose left-to-right map is in `left_store` and
+    /// right-to-left map is in `right_store`.
+    #[inline]
+    pub const fn new_in(
+        left_store: &'store BTreeStore<L, R>,
+        right_store: &'store BTreeStore<R, L>,
+    ) -> Self {
+        Self {
+            left: BTreeMap::new_in(left_store),
+            right: BTreeMap::new_in(right_store),
+        }
+    }
+
+    /// Returns the number of pairs in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    /// Returns `true` if the map contains no pairs.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+
+    /// Clears the map, removing all pairs.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.left.clear();
+        self.right.clear();
+    }
+
+    /// Returns the right value paired with the left value, if any.
+    #[inline]
+    pub fn get_by_left<Q: Ord + ?Sized>(&self, left: &Q) -> Option<&R>
+    where
+        L: Borrow<Q>,
+    {
+        self.left.get(left)
+    }
+
+    /// Returns the left value paired with the right value, if any.
+    #[inline]
+    pub fn get_by_right<Q: Ord + ?Sized>(&self, right: &Q) -> Option<&L>
+    where
+        R: Borrow<Q>,
+    {
+        self.right.get(right)
+    }
+
+    /// Returns `true` if the left value is in a pair.
+    #[inline]
+    pub fn contains_left<Q: Ord + ?Sized>(&self, left: &Q) -> bool
+    where
+        L: Borrow<Q>,
+    {
+        self.left.contains_key(left)
+    }
+
+    /// Returns `true` if the right value is in a pair.
+    #[inline]
+    pub fn contains_right<Q: Ord + ?Sized>(&self, right: &Q) -> bool
+    where
+        R: Borrow<Q>,
+    {
+        self.right.contains_key(right)
+    }
+
+    /// Pairs the left and right values, removing their existing pairs. Returns the right value
+    /// `left` was paired with and the left value `right` was paired with.
+    ///
+    /// If `L` or `R`'s [Ord] or [Clone] *panic*s, the map is unchanged: existing pairs which were
+    /// already removed are put back. If putting them back *panic*s too, they stay removed.
+    pub fn insert(&mut self, left: L, right: R) -> (Option<R>, Option<L>)
+    where
+        L: Ord + Clone,
+        R: Ord + Clone,
+    {
+        // Clone first, so a *panic*ing `Clone` leaves the map unchanged
+        let (left2, right2, left3) = (left.clone(), right.clone(), left.clone());
+        let old_right = self.remove_by_left(&left).map(|(_, right)| right);
+        let old_left = match catch_unwind(AssertUnwindSafe(|| self.remove_by_right(&right))) {
+            Ok(old) => old.map(|(left, _)| left),
+            Err(err) => {
+                self.restore(Some(left).zip(old_right));
+                resume_unwind(err)
+            }
+        };
+        // Neither value is paired now, so if an insertion *panic*s its direction is unchanged
+        if let Err(err) = catch_unwind(AssertUnwindSafe(|| self.left.insert(left, right))) {
+            self.restore(Some(left3).zip(old_right));
+            self.restore(old_left.zip(Some(right2)));
+            resume_unwind(err)
+        }
+        if let Err(err) = catch_unwind(AssertUnwindSafe(|| self.right.insert(right2, left2))) {
+            // Take the pair back out of the left direction, to pair its values with their old
+            // partners again
+            let len = self.left.len();
+            match catch_unwind(AssertUnwindSafe(|| self.left.remove_key_value(&left3))) {
+                Ok(pair) => {
+                    let (left, right) = pair.expect("directions disagree");
+                    self.restore(Some(left).zip(old_right));
+                    self.restore(old_left.zip(Some(right)));
+                }
+                Err(_) => {
+                    if self.left.len() == len {
+                        self.remove_half_pairs();
+                    }
+                    self.restore(Some(left3).zip(old_right));
+                }
+            }
+            resume_unwind(err)
+        }
+        (old_right, old_left)
+    }
+
+    /// Removes and returns the pair with the left value, if any. If `L` or `R`'s [Ord] or [Clone]
+    /// *panic*s, the map is unchanged, unless dropping a separator *panic*s after the pair was
+    /// removed: then it's removed from both directions.
+    pub fn remove_by_left<Q: Ord + ?Sized>(&mut self, left: &Q) -> Option<(L, R)>
+    where
+        L: Ord + Clone + Borrow<Q>,
+        R: Ord + Clone,
+    {
+        let len = self.left.len();
+        let (left, right) =
+            match catch_unwind(AssertUnwindSafe(|| self.left.remove_key_value(left))) {
+                Ok(pair) => pair?,
+                Err(err) => {
+                    // Dropping a separator *panic*ked after the entry was removed, so we lost the
+                    // right value, and can only find the other direction's entry by its value
+                    if self.left.len() != len {
+                        self.remove_half_pairs();
+                    }
+                    resume_unwind(err)
+                }
+            };
+        let len = self.right.len();
+        match catch_unwind(AssertUnwindSafe(|| self.right.remove(&right))) {
+            Ok(left2) => {
+                left2.expect("directions disagree");
+                Some((left, right))
+            }
+            Err(err) => {
+                // If the pair was removed from both directions before the *panic*, it's consistent
+                if self.right.len() == len {
+                    self.undo(|this| {
+                        this.left.insert(left, right);
+                    });
+                }
+                resume_unwind(err)
+            }
+        }
+    }
+
+    /// Removes and returns the pair with the right value, if any. If `L` or `R`'s [Ord] or
+    /// [Clone] *panic*s, the map is unchanged, unless dropping a separator *panic*s after the pair
+    /// was removed: then it's removed from both directions.
+    pub fn remove_by_right<Q: Ord + ?Sized>(&mut self, right: &Q) -> Option<(L, R)>
+    where
+        L: Ord + Clone,
+        R: Ord + Clone + Borrow<Q>,
+    {
+        let len = self.right.len();
+        let (right, left) =
+            match catch_unwind(AssertUnwindSafe(|| self.right.remove_key_value(right))) {
+                Ok(pair) => pair?,
+                Err(err) => {
+                    if self.right.len() != len {
+                        self.remove_half_pairs();
+                    }
+                    resume_unwind(err)
+                }
+            };
+        let len = self.left.len();
+        match catch_unwind(AssertUnwindSafe(|| self.left.remove(&left))) {
+            Ok(right2) => {
+                right2.expect("directions disagree");
+                Some((left, right))
+            }
+            Err(err) => {
+                if self.left.len() == len {
+                    self.undo(|this| {
+                        this.right.insert(right, left);
+                    });
+                }
+                resume_unwind(err)
+            }
+        }
+    }
+
+    /// Undoes the first half of an update whose second half *panic*ked. If this *panic*s too, the
+    /// pair may only be in one direction, so it's removed from the other.
+    #[inline]
+    fn undo(&mut self, undo: impl FnOnce(&mut Self))
+    where
+        L: Ord + Clone,
+        R: Ord + Clone,
+    {
+        if catch_unwind(AssertUnwindSafe(|| undo(self))).is_err() {
+            self.remove_half_pairs();
+        }
+    }
+
+    /// Pairs values again after an update which removed their pairs *panic*ked. The values aren't
+    /// paired with anything else, so if this *panic*s too, the pair just stays removed.
+    #[inline]
+    fn restore(&mut self, pair: Option<(L, R)>)
+    where
+        L: Ord + Clone,
+        R: Ord + Clone,
+    {
+        if let Some((left, right)) = pair {
+            // The *panic* being handled is the one to resume
+            let _ = catch_unwind(AssertUnwindSafe(|| self.insert(left, right)));
+        }
+    }
+
+    /// Removes the entries whose pair is only in one direction, after an update *panic*ked midway
+    /// and couldn't be undone. We can't find an entry by its value, so this scans both directions,
+    /// but it only runs after a *panic*. If this *panic*s too, some entries may remain.
+    fn remove_half_pairs(&mut self)
+    where
+        L: Ord + Clone,
+        R: Ord + Clone,
+    {
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            let left_only = (self.left.iter())
+                .filter(|(left, right)| self.right.get(*right) != Some(*left))
+                .map(|(left, _)| left.clone())
+                .collect::<Vec<_>>();
+            let right_only = (self.right.iter())
+                .filter(|(right, left)| self.left.get(*left) != Some(*right))
+                .map(|(right, _)| right.clone())
+                .collect::<Vec<_>>();
+            for left in left_only {
+                self.left.remove(&left);
+            }
+            for right in right_only {
+                self.right.remove(&right);
+            }
+        }));
+    }
+
+    /// Iterates over the pairs in order of their left values.
+    #[inline]
+    pub fn iter_by_left(&self) -> ByLeft<'_, L, R> {
+        ByLeft(self.left.iter())
+    }
+
+    /// Iterates over the pairs in order of their right values.
+    #[inline]
+    pub fn iter_by_right(&self) -> ByRight<'_, L, R> {
+        ByRight(self.right.iter())
+    }
+
+    /// Iterates over the pairs whose left values are in the range, in order of their left values.
+    #[inline]
+    pub fn range_by_left<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> RangeByLeft<'_, L, R>
+    where
+        L: Borrow<Q>,
+    {
+        RangeByLeft(self.left.range(bounds))
+    }
+
+    /// Iterates over the pairs whose right values are in the range, in order of their right
+    /// values.
+    #[inline]
+    pub fn range_by_right<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> RangeByRight<'_, L, R>
+    where
+        R: Borrow<Q>,
+    {
+        RangeByRight(self.right.range(bounds))
+    }
+
+    /// Validates the map, *panic*ing if it is invalid. Specifically, we check both b-trees, and
+    /// that every pair is in both directions.
+    pub fn validate(&self)
+    where
+        L: Debug + Ord,
+        R: Debug + Ord,
+    {
+        self.left.validate();
+        self.right.validate();
+        assert_eq!(
+            self.left.len(),
+            self.right.len(),
+            "directions have different lengths"
+        );
+        for (left, right) in self.left.iter() {
+            assert_eq!(
+                self.right.get(right),
+                Some(left),
+                "pair ({:?}, {:?}) is only left-to-right",
+                left,
+                right
+            );
+        }
+    }
+}
+
+// region common trait impls
+impl<'store, L: Debug, R: Debug> Debug for BiBTreeMap<'store, L, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter_by_left()).finish()
+    }
+}
+
+impl<'store, L: PartialEq, R: PartialEq> PartialEq for BiBTreeMap<'store, L, R> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.left == other.left
+    }
+}
+
+impl<'store, L: Eq, R: Eq> Eq for BiBTreeMap<'store, L, R> {}
+
+impl<'store, L: Ord + Clone, R: Ord + Clone> Extend<(L, R)> for BiBTreeMap<'store, L, R> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (L, R)>>(&mut self, iter: I) {
+        for (left, right) in iter {
+            self.insert(left, right);
+        }
+    }
+}
+
+impl<'a, 'store: 'a, L, R> IntoIterator for &'a BiBTreeMap<'store, L, R> {
+    type Item = (&'a L, &'a R);
+    type IntoIter = ByLeft<'a, L, R>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_by_left()
+    }
+}
+// endregion
+
+// region iterators
+/// Iterator over the pairs in order of their left values
+pub struct ByLeft<'a, L, R>(crate::map::Iter<'a, L, R>);
+
+impl<'a, L, R> Iterator for ByLeft<'a, L, R> {
+    type Item = (&'a L, &'a R);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, L, R> DoubleEndedIterator for ByLeft<'a, L, R> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+impl<'a, L, R> ExactSizeIterator for ByLeft<'a, L, R> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, L, R> FusedIterator for ByLeft<'a, L, R> {}
+
+/// Iterator over the pairs in order of their right values
+pub struct ByRight<'a, L, R>(crate::map::Iter<'a, R, L>);
+
+impl<'a, L, R> Iterator for ByRight<'a, L, R> {
+    type Item = (&'a L, &'a R);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(right, left)| (left, right))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, L, R> DoubleEndedIterator for ByRight<'a, L, R> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(right, left)| (left, right))
+    }
+}
+
+impl<'a, L, R> ExactSizeIterator for ByRight<'a, L, R> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, L, R> FusedIterator for ByRight<'a, L, R> {}
+
+/// Iterator over the pairs whose left values are in a range
+pub struct RangeByLeft<'a, L, R>(crate::map::Range<'a, L, R>);
+
+impl<'a, L, R> Iterator for RangeByLeft<'a, L, R> {
+    type Item = (&'a L, &'a R);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<'a, L, R> DoubleEndedIterator for RangeByLeft<'a, L, R> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+impl<'a, L, R> FusedIterator for RangeByLeft<'a, L, R> {}
+
+/// Iterator over the pairs whose right values are in a range
+pub struct RangeByRight<'a, L, R>(crate::map::Range<'a, R, L>);
+
+impl<'a, L, R> Iterator for RangeByRight<'a, L, R> {
+    type Item = (&'a L, &'a R);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(right, left)| (left, right))
+    }
+}
+
+impl<'a, L, R> DoubleEndedIterator for RangeByRight<'a, L, R> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(right, left)| (left, right))
+    }
+}
+
+impl<'a, L, R> FusedIterator for RangeByRight<'a, L, R> {}
+// endregion
@@ -0,0 +1,82 @@
+use std::cmp::Ordering;
+
+use crate::separator::SeparatorKey;
+
+/// Orders a [BTreeMap](crate::BTreeMap)'s keys, so a map can be ordered at runtime (e.g. by a
+/// collation or a case-insensitive order) without wrapping every key.
+///
+/// Like [Ord], the order must be total and consistent. If it isn't (e.g. it changes while the map
+/// has entries), the map will behave incorrectly but memory-safely.
+///
+/// Closures `Fn(&T, &T) -> Ordering` are comparators.
+pub trait Comparator<T: ?Sized> {
+    /// Compares `a` to `b`
+    fn cmp(&self, a: &T, b: &T) -> Ordering;
+
+    /// Returns a key `s` where `prev < s <= next`, which internal nodes store to separate the leaf
+    /// ending with `prev` from the leaf starting with `next`. `None` (the default) copies `next`.
+    ///
+    /// A shorter separator saves memory when keys share long prefixes: see [ShortSeparators].
+    #[inline]
+    fn separator(&self, prev: &T, next: &T) -> Option<T>
+    where
+        T: Sized,
+    {
+        let _ = (prev, next);
+        None
+    }
+}
+
+/// The default comparator, which orders keys by [Ord]. It's zero-sized so maps don't pay for it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct OrdComparator;
+
+impl<T: Ord + ?Sized> Comparator<T> for OrdComparator {
+    #[inline]
+    fn cmp(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Orders keys by [Ord] like [OrdComparator], but internal nodes store the shortest separators
+/// between leaves (see [SeparatorKey]) instead of copies of their first keys.
+///
+/// ```
+/// use btree_plus_store::{compare::ShortSeparators, BTreeMap, BTreeStore};
+///
+/// let store = BTreeStore::new();
+/// let mut map = BTreeMap::with_comparator_in(&store, ShortSeparators);
+/// for i in 0..100 {
+///     map.insert(format!("/usr/share/very/long/path/{}", i), i);
+/// }
+/// assert_eq!(map.get(&String::from("/usr/share/very/long/path/42")), Some(&42));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ShortSeparators;
+
+impl<T: SeparatorKey> Comparator<T> for ShortSeparators {
+    #[inline]
+    fn cmp(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+
+    #[inline]
+    fn separator(&self, prev: &T, next: &T) -> Option<T> {
+        Some(T::shortest_separator(prev, next))
+    }
+}
+
+/// Comparators which order [ByteKey](crate::ByteKey)s by their bytes, like [Ord], so a map can find the keys with
+/// a byte prefix: see [BTreeMap::starts_with](crate::BTreeMap::starts_with).
+pub trait BytewiseComparator {}
+
+impl BytewiseComparator for OrdComparator {}
+
+impl BytewiseComparator for ShortSeparators {}
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Comparator<T> for F {
+    #[inline]
+    fn cmp(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
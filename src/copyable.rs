@@ -1,8 +1,12 @@
+pub use archived::{ArchivedBTreeMap, Pod};
 pub use map::BTreeMap;
 pub use set::BTreeSet;
+pub use snapshot::{Persist, TreeHandle};
 pub use store::{BTree, BTreeStoreExt};
 
+pub mod archived;
 pub mod map;
 pub(crate) mod sealed;
 pub mod set;
+mod snapshot;
 mod store;
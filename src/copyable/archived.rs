@@ -0,0 +1,617 @@
+//! Read-only maps which are searched directly in a byte buffer, e.g. an mmapped file, without
+//! deserializing them into a store.
+//!
+//! Write an archive with [BTreeMap::write_archived](crate::copyable::BTreeMap::write_archived)
+//! and read it with [ArchivedBTreeMap::new]. Nodes keep the same layout as in the store, except
+//! edges are offsets from the start of the buffer instead of pointers. Leaves are contiguous and
+//! in order, so iterating an archived map is a linear scan.
+
+use std::borrow::Borrow;
+use std::fmt::{Debug, Formatter};
+use std::io::{self, Write};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::ops::RangeBounds;
+
+use crate::compare::OrdComparator;
+use crate::copyable::sealed::BTree;
+use crate::node::{NodePtr, M};
+use crate::search::{self, Find, NodeBounds, SearchTree};
+
+/// Identifies the archive format
+const MAGIC: [u8; 8] = *b"BTPSARCH";
+/// Written in native byte order, so archives from a machine with different endianness are
+/// rejected
+const ENDIAN_CHECK: u64 = 0x0102_0304_0506_0708;
+/// Incremented when the archive format changes
+const VERSION: u64 = 1;
+/// Number of `u64` words in the header
+const HEADER_WORDS: usize = 12;
+
+const CORRUPT: &str = "archived b-tree is corrupt";
+
+/// Plain old data: a type which can be archived by copying its bytes, and read back from any
+/// bytes.
+///
+/// # Safety
+/// The type must have no padding, every bit pattern must be a valid value, and it must not
+/// contain pointers or references (they would be meaningless when the archive is read).
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($ty:ty),*) => {
+        $(unsafe impl Pod for $ty {})*
+    };
+}
+
+impl_pod!(
+    (),
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64
+);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// A read-only b-tree map which is searched directly in a byte buffer.
+///
+/// Archives are checked when read: a buffer from a different format, platform, or key and value
+/// types is rejected by [ArchivedBTreeMap::new], and a corrupt buffer makes lookups *panic* or
+/// return wrong results, but never causes undefined behavior.
+pub struct ArchivedBTreeMap<'a, K, V> {
+    bytes: &'a [u8],
+    root: Option<usize>,
+    height: usize,
+    length: usize,
+    /// Byte range of the leaves, which are contiguous and in order
+    leaves_start: usize,
+    leaves_end: usize,
+    layout: Layout,
+    _p: PhantomData<(&'a K, &'a V)>,
+}
+
+/// Byte offsets and sizes of archived nodes
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    /// Alignment of every node, and of the buffer itself
+    align: usize,
+    header_size: usize,
+    keys_offset: usize,
+    vals_offset: usize,
+    edges_offset: usize,
+    leaf_size: usize,
+    internal_size: usize,
+}
+
+impl Layout {
+    #[inline]
+    fn of<K, V>() -> Self {
+        let align = align_of::<u64>().max(align_of::<K>()).max(align_of::<V>());
+        let keys_offset = round_up(size_of::<u64>(), align_of::<K>());
+        let keys_end = keys_offset + M * size_of::<K>();
+        let vals_offset = round_up(keys_end, align_of::<V>());
+        let edges_offset = round_up(keys_end, align_of::<u64>());
+        Self {
+            align,
+            header_size: round_up(HEADER_WORDS * size_of::<u64>(), align),
+            keys_offset,
+            vals_offset,
+            edges_offset,
+            leaf_size: round_up(vals_offset + M * size_of::<V>(), align),
+            internal_size: round_up(edges_offset + (M + 1) * size_of::<u64>(), align),
+        }
+    }
+
+    /// Size of the nodes at `height`
+    #[inline]
+    fn node_size(&self, height: usize) -> usize {
+        if height == 0 {
+            self.leaf_size
+        } else {
+            self.internal_size
+        }
+    }
+}
+
+#[inline]
+fn round_up(n: usize, align: usize) -> usize {
+    n.div_ceil(align) * align
+}
+
+// region writing
+impl<'store, K: Pod, V: Pod> crate::copyable::BTreeMap<'store, K, V> {
+    /// Writes the map in a format which [ArchivedBTreeMap] can search without deserializing.
+    ///
+    /// The archive can only be read on a platform with the same endianness and type layouts.
+    pub fn write_archived(&self, mut writer: impl Write) -> io::Result<()> {
+        let (root, height, length) = self.parts();
+        let layout = Layout::of::<K, V>();
+
+        // Nodes of each level, bottom-up, from left to right
+        let mut levels = Vec::<Vec<NodePtr<K, V>>>::new();
+        if let Some(root) = root {
+            levels.push(vec![root]);
+            for _ in 0..height {
+                let children = levels
+                    .last()
+                    .unwrap()
+                    .iter()
+                    .flat_map(|node| unsafe { node.as_ref().edges() }.iter().copied())
+                    .collect();
+                levels.push(children);
+            }
+            levels.reverse();
+        }
+
+        let mut level_offsets = Vec::with_capacity(levels.len());
+        let mut offset = layout.header_size;
+        for (height, level) in levels.iter().enumerate() {
+            level_offsets.push(offset);
+            offset += level.len() * layout.node_size(height);
+        }
+
+        let header: [u64; HEADER_WORDS] = [
+            u64::from_ne_bytes(MAGIC),
+            ENDIAN_CHECK,
+            VERSION,
+            M as u64,
+            size_of::<K>() as u64,
+            align_of::<K>() as u64,
+            size_of::<V>() as u64,
+            align_of::<V>() as u64,
+            length as u64,
+            height as u64,
+            level_offsets.last().map_or(u64::MAX, |&root| root as u64),
+            levels.first().map_or(0, |leaves| leaves.len() as u64),
+        ];
+        let mut buf = vec![0; layout.header_size];
+        for (idx, word) in header.into_iter().enumerate() {
+            buf[idx * 8..(idx + 1) * 8].copy_from_slice(&word.to_ne_bytes());
+        }
+        writer.write_all(&buf)?;
+
+        for (height, level) in levels.iter().enumerate() {
+            let mut next_child = 0;
+            for node in level {
+                let node = unsafe { node.as_ref() };
+                buf.clear();
+                buf.resize(layout.node_size(height), 0);
+                buf[..8].copy_from_slice(&(node.len as u64).to_ne_bytes());
+                write_pods(&mut buf[layout.keys_offset..], unsafe { node.keys() });
+                if height == 0 {
+                    write_pods(&mut buf[layout.vals_offset..], unsafe { node.vals() });
+                } else {
+                    let edges = (0..=node.len as usize).map(|_| {
+                        let edge =
+                            level_offsets[height - 1] + next_child * layout.node_size(height - 1);
+                        next_child += 1;
+                        edge as u64
+                    });
+                    for (idx, edge) in edges.enumerate() {
+                        let start = layout.edges_offset + idx * 8;
+                        buf[start..start + 8].copy_from_slice(&edge.to_ne_bytes());
+                    }
+                }
+                writer.write_all(&buf)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copies the bytes of `values` to the start of `buf`
+#[inline]
+fn write_pods<T: Pod>(buf: &mut [u8], values: &[T]) {
+    let bytes = unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+    };
+    buf[..bytes.len()].copy_from_slice(bytes);
+}
+// endregion
+
+// region reading
+impl<'a, K: Pod, V: Pod> ArchivedBTreeMap<'a, K, V> {
+    /// Reads an archive written by
+    /// [BTreeMap::write_archived](crate::copyable::BTreeMap::write_archived), without copying it.
+    ///
+    /// The buffer must be aligned to the alignment of `K`, `V` and `u64`. Mmapped files are, but
+    /// a `Vec<u8>` may not be.
+    ///
+    /// Returns an error if the buffer isn't aligned, or isn't an archive of a map with the same
+    /// key and value layouts written on a platform with the same endianness.
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        let layout = Layout::of::<K, V>();
+        if !(bytes.as_ptr() as usize).is_multiple_of(layout.align) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("archive buffer isn't aligned to {} bytes", layout.align),
+            ));
+        }
+        if bytes.len() < layout.header_size {
+            return Err(invalid_data("archive is truncated"));
+        }
+        let header =
+            |idx: usize| u64::from_ne_bytes(bytes[idx * 8..(idx + 1) * 8].try_into().unwrap());
+        if header(0) != u64::from_ne_bytes(MAGIC) {
+            return Err(invalid_data("not a b-tree archive"));
+        }
+        if header(1) != ENDIAN_CHECK {
+            return Err(invalid_data(
+                "archive was written with a different endianness",
+            ));
+        }
+        if header(2) != VERSION {
+            return Err(invalid_data("unsupported archive version"));
+        }
+        if [header(3), header(4), header(5), header(6), header(7)]
+            != [
+                M as u64,
+                size_of::<K>() as u64,
+                align_of::<K>() as u64,
+                size_of::<V>() as u64,
+                align_of::<V>() as u64,
+            ]
+        {
+            return Err(invalid_data(
+                "archive was written with different node, key or value layouts",
+            ));
+        }
+
+        let to_usize = |word: u64| usize::try_from(word).map_err(|_| invalid_data(CORRUPT));
+        let length = to_usize(header(8))?;
+        let height = to_usize(header(9))?;
+        let root = match header(10) {
+            u64::MAX => None,
+            root => Some(to_usize(root)?),
+        };
+        let leaves_start = layout.header_size;
+        let leaves_end = to_usize(header(11))?
+            .checked_mul(layout.leaf_size)
+            .and_then(|size| size.checked_add(leaves_start))
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| invalid_data("archive is truncated"))?;
+        if root.is_none() != (length == 0) || (root.is_none() && leaves_end != leaves_start) {
+            return Err(invalid_data(CORRUPT));
+        }
+        // The root is written last, so this catches most truncated archives
+        if let Some(root) = root {
+            if root
+                .checked_add(layout.node_size(height))
+                .is_none_or(|end| end > bytes.len())
+            {
+                return Err(invalid_data("archive is truncated"));
+            }
+        }
+
+        Ok(Self {
+            bytes,
+            root,
+            height,
+            length,
+            leaves_start,
+            leaves_end,
+            layout,
+            _p: PhantomData,
+        })
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Returns `true` if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    #[inline]
+    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        matches!(search::find(self, &OrdComparator, key), Find::At { .. })
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    #[inline]
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+    {
+        self.get_key_value(key).map(|(_, val)| val)
+    }
+
+    /// Returns the key-value pair corresponding to the supplied key.
+    #[inline]
+    pub fn get_key_value<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&'a K, &'a V)>
+    where
+        K: Borrow<Q>,
+    {
+        match search::find(self, &OrdComparator, key) {
+            Find::At { node, idx } => Some(self.entry(node, idx)),
+            Find::NoRoot | Find::Before { .. } => None,
+        }
+    }
+
+    /// Returns the first key-value pair in the map.
+    #[inline]
+    pub fn first_key_value(&self) -> Option<(&'a K, &'a V)> {
+        self.iter().next()
+    }
+
+    /// Returns the last key-value pair in the map.
+    #[inline]
+    pub fn last_key_value(&self) -> Option<(&'a K, &'a V)> {
+        self.iter().next_back()
+    }
+
+    /// Iterates over the map's entries in order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'a, K, V> {
+        let bounds =
+            search::first_leaf(self)
+                .zip(search::last_leaf(self))
+                .map(|(first_leaf, last_leaf)| NodeBounds {
+                    start_node: first_leaf,
+                    end_node: last_leaf,
+                    start_index: 0,
+                    end_index: self.node_len(last_leaf) - 1,
+                });
+        Iter {
+            range: Range::new(*self, bounds),
+            length: self.length,
+        }
+    }
+
+    /// Iterates over the map's entries within the given bounds, in order.
+    #[inline]
+    pub fn range<Q: Ord + ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Range<'a, K, V>
+    where
+        K: Borrow<Q>,
+    {
+        Range::new(*self, search::node_bounds(self, &OrdComparator, bounds))
+    }
+
+    /// Iterates over the map's keys in order.
+    #[inline]
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &'a K> + ExactSizeIterator {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over the map's values in order of their keys.
+    #[inline]
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &'a V> + ExactSizeIterator {
+        self.iter().map(|(_, val)| val)
+    }
+
+    // region node access
+    /// The bytes of the node at `offset`, *panic*king if they're out of bounds.
+    #[inline]
+    fn node_bytes(&self, offset: usize, size: usize) -> &'a [u8] {
+        assert!(offset.is_multiple_of(self.layout.align), "{}", CORRUPT);
+        offset
+            .checked_add(size)
+            .and_then(|end| self.bytes.get(offset..end))
+            .expect(CORRUPT)
+    }
+
+    #[inline]
+    fn node_len(&self, node: usize) -> u16 {
+        let len = u64::from_ne_bytes(self.node_bytes(node, 8).try_into().unwrap());
+        assert!((1..=M as u64).contains(&len), "{}", CORRUPT);
+        len as u16
+    }
+
+    /// Reads `len` values at `offset` in the node, which was checked to be in bounds and aligned
+    #[inline]
+    fn pods<T: Pod>(&self, node: usize, offset: usize, len: u16) -> &'a [T] {
+        let bytes = self.node_bytes(node, offset + M * size_of::<T>());
+        // SAFETY: the bytes are in bounds and aligned since the buffer and node offset are aligned
+        // to `layout.align` and `offset` is aligned to `T`, and any bytes are a valid `T`
+        unsafe { std::slice::from_raw_parts(bytes.as_ptr().add(offset) as *const T, len as usize) }
+    }
+
+    #[inline]
+    fn node_keys(&self, node: usize) -> &'a [K] {
+        self.pods(node, self.layout.keys_offset, self.node_len(node))
+    }
+
+    #[inline]
+    fn entry(&self, leaf: usize, idx: u16) -> (&'a K, &'a V) {
+        let len = self.node_len(leaf);
+        let keys = self.pods::<K>(leaf, self.layout.keys_offset, len);
+        let vals = self.pods::<V>(leaf, self.layout.vals_offset, len);
+        (&keys[idx as usize], &vals[idx as usize])
+    }
+    // endregion
+}
+
+impl<'a, K: Pod, V: Pod> SearchTree for ArchivedBTreeMap<'a, K, V> {
+    type Key = K;
+    /// Offset from the start of the buffer
+    type Node = usize;
+
+    #[inline]
+    fn root(&self) -> Option<(usize, usize)> {
+        self.root.map(|root| (root, self.height))
+    }
+
+    #[inline]
+    unsafe fn keys(&self, node: usize) -> &[K] {
+        self.node_keys(node)
+    }
+
+    #[inline]
+    unsafe fn edge(&self, node: usize, idx: u16) -> usize {
+        assert!(idx <= self.node_len(node), "{}", CORRUPT);
+        let start = self.layout.edges_offset + idx as usize * 8;
+        let bytes = &self.node_bytes(node, self.layout.internal_size)[start..start + 8];
+        usize::try_from(u64::from_ne_bytes(bytes.try_into().unwrap())).expect(CORRUPT)
+    }
+
+    #[inline]
+    unsafe fn prev(&self, node: usize) -> Option<usize> {
+        node.checked_sub(self.layout.leaf_size)
+            .filter(|&prev| prev >= self.leaves_start)
+    }
+
+    #[inline]
+    unsafe fn next(&self, node: usize) -> Option<usize> {
+        Some(node + self.layout.leaf_size).filter(|&next| next < self.leaves_end)
+    }
+
+    #[inline]
+    unsafe fn len(&self, node: usize) -> u16 {
+        self.node_len(node)
+    }
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+// endregion
+
+// region iterators
+/// Iterator over an [ArchivedBTreeMap]'s entries
+pub struct Iter<'a, K, V> {
+    range: Range<'a, K, V>,
+    length: usize,
+}
+
+/// Iterator over an [ArchivedBTreeMap]'s entries within bounds
+pub struct Range<'a, K, V> {
+    map: ArchivedBTreeMap<'a, K, V>,
+    /// Addresses of the next entries from the front and back, `None` when done
+    bounds: Option<NodeBounds<usize>>,
+}
+
+impl<'a, K: Pod, V: Pod> Range<'a, K, V> {
+    #[inline]
+    fn new(map: ArchivedBTreeMap<'a, K, V>, bounds: Option<NodeBounds<usize>>) -> Self {
+        Self { map, bounds }
+    }
+}
+
+impl<'a, K: Pod, V: Pod> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let bounds = self.bounds.as_mut()?;
+        let (node, idx) = bounds.start();
+        let item = self.map.entry(node, idx);
+        if (node, idx) == bounds.end() {
+            self.bounds = None;
+        } else if idx + 1 < self.map.node_len(node) {
+            bounds.start_index += 1;
+        } else {
+            match unsafe { self.map.next(node) } {
+                None => self.bounds = None,
+                Some(next) => {
+                    bounds.start_node = next;
+                    bounds.start_index = 0;
+                }
+            }
+        }
+        Some(item)
+    }
+}
+
+impl<'a, K: Pod, V: Pod> DoubleEndedIterator for Range<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let bounds = self.bounds.as_mut()?;
+        let (node, idx) = bounds.end();
+        let item = self.map.entry(node, idx);
+        if (node, idx) == bounds.start() {
+            self.bounds = None;
+        } else if idx > 0 {
+            bounds.end_index -= 1;
+        } else {
+            match unsafe { self.map.prev(node) } {
+                None => self.bounds = None,
+                Some(prev) => {
+                    bounds.end_node = prev;
+                    bounds.end_index = self.map.node_len(prev) - 1;
+                }
+            }
+        }
+        Some(item)
+    }
+}
+
+impl<'a, K: Pod, V: Pod> FusedIterator for Range<'a, K, V> {}
+
+impl<'a, K: Pod, V: Pod> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.range.next()?;
+        self.length = self.length.saturating_sub(1);
+        Some(item)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<'a, K: Pod, V: Pod> DoubleEndedIterator for Iter<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let item = self.range.next_back()?;
+        self.length = self.length.saturating_sub(1);
+        Some(item)
+    }
+}
+
+impl<'a, K: Pod, V: Pod> ExactSizeIterator for Iter<'a, K, V> {}
+
+impl<'a, K: Pod, V: Pod> FusedIterator for Iter<'a, K, V> {}
+
+impl<'a, K: Pod, V: Pod> IntoIterator for ArchivedBTreeMap<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+// endregion
+
+// region common trait impls
+impl<'a, K, V> Clone for ArchivedBTreeMap<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, K, V> Copy for ArchivedBTreeMap<'a, K, V> {}
+
+impl<'a, K: Pod + Debug, V: Pod + Debug> Debug for ArchivedBTreeMap<'a, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+// endregion
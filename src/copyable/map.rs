@@ -1,11 +1,15 @@
+use crate::cursor;
+use crate::node::NodePtr;
+use crate::utils::PtrEq;
 use crate::BTreeStore;
 use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
 use std::marker::PhantomData;
 use std::mem::{size_of, transmute, MaybeUninit};
-use std::ops::{Deref, RangeBounds};
+use std::ops::{Bound, Deref, RangeBounds};
 
 /// A copyable, immutable b-tree map, which doesn't drop its contents.
 pub struct BTreeMap<'store, K, V> {
@@ -17,6 +21,17 @@ pub type Keys<'a, K, V> = crate::map::Keys<'a, K, V>;
 pub type Values<'a, K, V> = crate::map::Values<'a, K, V>;
 pub type Range<'a, K, V> = crate::map::Range<'a, K, V>;
 
+/// An entry which differs between two maps. See [BTreeMap::diff].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiffItem<K, V> {
+    /// The entry is in the new map but not the old one
+    Added(K, V),
+    /// The entry is in the old map but not the new one
+    Removed(K, V),
+    /// The key is in both maps, but with a different value
+    Changed { key: K, old: V, new: V },
+}
+
 impl<'store, K, V> From<crate::BTreeMap<'store, K, V>> for BTreeMap<'store, K, V> {
     /// Creates a copyable map from a non-copyable map. Afterwards, the map is no longer mutable and
     /// will no longer drop its contents.
@@ -43,6 +58,15 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         Self::from(map)
     }
 
+    /// Replaces the stamp recorded at creation, for a map whose nodes were created in an earlier
+    /// generation (e.g. a restored tree handle).
+    #[cfg(feature = "checked")]
+    #[inline]
+    pub(crate) fn with_stamp(mut self, stamp: crate::store::Stamp) -> Self {
+        self.inner.stamp = stamp;
+        self
+    }
+
     // region length
     /// Returns the number of elements in the map.
     #[inline]
@@ -187,6 +211,80 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     {
         self.inner.range_values(bounds)
     }
+
+    /// Iterates over the map's key-value pairs in parallel, splitting the work by subtrees.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_iter(&self) -> crate::par::ParIter<'_, K, V>
+    where
+        K: Sync,
+        V: Sync,
+    {
+        self.inner.par_iter()
+    }
+
+    /// Iterates over the map's key-value pairs within the given range in parallel, splitting the
+    /// work by subtrees.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_range<Q: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> crate::par::ParIter<'_, K, V>
+    where
+        K: Sync + Borrow<Q>,
+        V: Sync,
+    {
+        self.inner.par_range(bounds)
+    }
+
+    /// Iterates over the entries which differ between `self` (the old map) and `other` (the new
+    /// map), in key order.
+    ///
+    /// If `other` is a copy of `self`, they share their root, so this is `O(1)` without comparing
+    /// any entries. Otherwise it's an `O(n + m)` merge-walk over both maps' entries.
+    ///
+    /// This doesn't skip subtrees the two maps share, because copyable maps never share only
+    /// part of their nodes: each node has one parent and linked leaves, so a changed version is
+    /// built from scratch rather than by copying the path to the change. Maps built separately
+    /// never share nodes, even when their entries are equal, so don't expect sublinear diffs.
+    #[inline]
+    pub fn diff<'a>(&'a self, other: &'a BTreeMap<'store, K, V>) -> Diff<'a, K, V>
+    where
+        K: Ord,
+        V: PartialEq,
+    {
+        Diff::new(&self.inner, &other.inner)
+    }
+    // endregion
+
+    // region cursors
+    /// Returns a cursor at the first entry above `bound`, or a detached cursor if there is none.
+    ///
+    /// `Bound::Unbounded` gives a cursor at the first entry.
+    #[inline]
+    pub fn lower_bound<Q: Ord + ?Sized>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V>
+    where
+        K: Borrow<Q>,
+    {
+        let bounds = self.inner.node_bounds((bound, Bound::Unbounded));
+        // SAFETY: Copyable maps never mutate their nodes, which the borrow keeps alive
+        unsafe { Cursor::at(bounds.map(|bounds| bounds.start())) }
+    }
+
+    /// Returns a cursor at the last entry below `bound`, or a detached cursor if there is none.
+    ///
+    /// `Bound::Unbounded` gives a cursor at the last entry.
+    #[inline]
+    pub fn upper_bound<Q: Ord + ?Sized>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V>
+    where
+        K: Borrow<Q>,
+    {
+        let bounds = self.inner.node_bounds((Bound::Unbounded, bound));
+        // SAFETY: Same as `lower_bound`
+        unsafe { Cursor::at(bounds.map(|bounds| bounds.end())) }
+    }
+    // endregion
 }
 
 // region common trait impls
@@ -255,6 +353,8 @@ struct RawBTreeMap<'store, K, V> {
     // But fortunately [crate::BTreeMap]'s size doesn't depend on its generics, because everything
     // is under an indirect pointer, and `K` and `V` are [Sized]
     data: [MaybeUninit<u8>; size_of::<crate::BTreeMap<'static, (), ()>>()],
+    #[cfg(feature = "checked")]
+    stamp: crate::store::Stamp,
     _p: PhantomData<(&'store K, &'store V)>,
 }
 
@@ -262,6 +362,8 @@ impl<'store, K, V> From<crate::BTreeMap<'store, K, V>> for RawBTreeMap<'store, K
     #[inline]
     fn from(inner: crate::BTreeMap<'store, K, V>) -> Self {
         Self {
+            #[cfg(feature = "checked")]
+            stamp: inner.stamp(),
             data: unsafe { transmute(inner) },
             _p: PhantomData,
         }
@@ -273,7 +375,10 @@ impl<'store, K, V> Deref for RawBTreeMap<'store, K, V> {
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        unsafe { &*(self.data.as_ptr() as *const crate::BTreeMap<'store, K, V>) }
+        let inner = unsafe { &*(self.data.as_ptr() as *const crate::BTreeMap<'store, K, V>) };
+        #[cfg(feature = "checked")]
+        inner.assert_alive(self.stamp);
+        inner
     }
 }
 
@@ -282,6 +387,8 @@ impl<'store, K, V> Clone for RawBTreeMap<'store, K, V> {
     fn clone(&self) -> Self {
         Self {
             data: self.data,
+            #[cfg(feature = "checked")]
+            stamp: self.stamp,
             _p: PhantomData,
         }
     }
@@ -301,6 +408,164 @@ impl<'store: 'a, 'a, K, V> IntoIterator for &'a BTreeMap<'store, K, V> {
         self.iter()
     }
 }
+
+impl<'store, K, V> IntoIterator for BTreeMap<'store, K, V> {
+    type Item = (&'store K, &'store V);
+    type IntoIter = Iter<'store, K, V>;
+
+    /// Iterates over the map's key-value pairs in order. Since the map is [Copy] and doesn't own
+    /// its contents, this yields references which live as long as the store.
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        // SAFETY: The nodes stay alive until they are garbage-collected, and `tracing_gc` requires
+        // that this map isn't used afterward. Copyable maps never mutate their nodes.
+        unsafe { Iter::new_detached(&self.inner) }
+    }
+}
+// endregion
+
+// region Cursor
+/// A position in a map, returned by [BTreeMap::lower_bound] and [BTreeMap::upper_bound], which
+/// moves between entries in either direction. Once it moves past either end, it's detached and
+/// stays detached.
+pub struct Cursor<'a, K, V>(cursor::Cursor<'a, K, V>);
+
+impl<'a, K, V> Cursor<'a, K, V> {
+    /// # Safety
+    /// The leaf at the address and the leaves linked to it must be alive for `'a`.
+    #[inline]
+    pub(crate) unsafe fn at(address: Option<(NodePtr<K, V>, u16)>) -> Self {
+        Self(match address {
+            None => cursor::Cursor::new_detached(),
+            Some((node, idx)) => cursor::Cursor::new(Some(node), idx),
+        })
+    }
+
+    /// The current key and value, or `None` if detached
+    #[inline]
+    pub fn key_value(&self) -> Option<(&'a K, &'a V)> {
+        self.0.key_value()
+    }
+
+    /// The current key, or `None` if detached
+    #[inline]
+    pub fn key(&self) -> Option<&'a K> {
+        self.0.key_value().map(|(k, _)| k)
+    }
+
+    /// The current value, or `None` if detached
+    #[inline]
+    pub fn value(&self) -> Option<&'a V> {
+        self.0.key_value().map(|(_, v)| v)
+    }
+
+    /// Whether the cursor is at an entry
+    #[inline]
+    pub fn is_attached(&self) -> bool {
+        self.0.is_attached()
+    }
+
+    /// Moves to the next entry, detaching if this is the last. Does nothing if detached.
+    #[inline]
+    pub fn move_next(&mut self) {
+        if self.0.is_attached() {
+            self.0.advance()
+        }
+    }
+
+    /// Moves to the previous entry, detaching if this is the first. Does nothing if detached.
+    #[inline]
+    pub fn move_prev(&mut self) {
+        if self.0.is_attached() {
+            self.0.advance_back()
+        }
+    }
+}
+
+impl<'a, K, V> Clone for Cursor<'a, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<'a, K: Debug, V: Debug> Debug for Cursor<'a, K, V> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Cursor").field(&self.key_value()).finish()
+    }
+}
+// endregion
+
+// region Diff
+/// Iterator returned by [BTreeMap::diff].
+pub struct Diff<'a, K, V> {
+    old: cursor::Cursor<'a, K, V>,
+    new: cursor::Cursor<'a, K, V>,
+}
+
+impl<'a, K, V> Diff<'a, K, V> {
+    #[inline]
+    fn new(old: &'a crate::BTreeMap<K, V>, new: &'a crate::BTreeMap<K, V>) -> Self {
+        if old.ptr_eq(new) {
+            return Self {
+                old: cursor::Cursor::new_detached(),
+                new: cursor::Cursor::new_detached(),
+            };
+        }
+        // SAFETY: Copyable maps never mutate their nodes, which stay alive for `'a`
+        unsafe {
+            Self {
+                old: cursor::Cursor::new(old.first_leaf(), 0),
+                new: cursor::Cursor::new(new.first_leaf(), 0),
+            }
+        }
+    }
+}
+
+impl<'a, K: Ord, V: PartialEq> Iterator for Diff<'a, K, V> {
+    type Item = DiffItem<&'a K, &'a V>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match (self.old.key_value(), self.new.key_value()) {
+                (None, None) => return None,
+                (Some((key, value)), None) => {
+                    self.old.advance();
+                    return Some(DiffItem::Removed(key, value));
+                }
+                (None, Some((key, value))) => {
+                    self.new.advance();
+                    return Some(DiffItem::Added(key, value));
+                }
+                (Some((old_key, old)), Some((new_key, new))) => match old_key.cmp(new_key) {
+                    Ordering::Less => {
+                        self.old.advance();
+                        return Some(DiffItem::Removed(old_key, old));
+                    }
+                    Ordering::Greater => {
+                        self.new.advance();
+                        return Some(DiffItem::Added(new_key, new));
+                    }
+                    Ordering::Equal => {
+                        self.old.advance();
+                        self.new.advance();
+                        if old != new {
+                            return Some(DiffItem::Changed {
+                                key: old_key,
+                                old,
+                                new,
+                            });
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<'a, K: Ord, V: PartialEq> FusedIterator for Diff<'a, K, V> {}
 // endregion
 
 impl<'store, K, V> crate::copyable::sealed::BTree<'store, K, V> for BTreeMap<'store, K, V> {
@@ -313,4 +578,15 @@ impl<'store, K, V> crate::copyable::sealed::BTree<'store, K, V> for BTreeMap<'st
     fn nodes(&self) -> crate::copyable::sealed::NodeIter<'store, K, V> {
         self.inner.nodes()
     }
+
+    #[inline]
+    fn parts(&self) -> (Option<crate::node::NodePtr<K, V>>, usize, usize) {
+        self.inner.parts()
+    }
+
+    #[cfg(feature = "checked")]
+    #[inline]
+    fn survivor_key(&self) -> Option<(crate::node::NodePtr<K, V>, crate::store::Stamp)> {
+        self.inner.survivor_key(self.inner.stamp)
+    }
 }
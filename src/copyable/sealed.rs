@@ -2,12 +2,42 @@ use std::cmp::Ordering;
 use std::marker::PhantomData;
 
 use crate::node::{Node, NodePtr};
+#[cfg(feature = "checked")]
+use crate::store::Stamp;
 use crate::BTreeStore;
 
 #[doc(hidden)]
 pub trait BTree<'store, K, V> {
     fn assert_store(&self, store: &BTreeStore<K, V>);
     fn nodes(&self) -> NodeIter<'store, K, V>;
+    /// The root node, height, and length
+    fn parts(&self) -> (Option<NodePtr<K, V>>, usize, usize);
+    /// Identifies a copyable b-tree which survives `tracing_gc`, `None` for mutable b-trees.
+    #[cfg(feature = "checked")]
+    fn survivor_key(&self) -> Option<(NodePtr<K, V>, Stamp)>;
+}
+
+impl<'store, K, V, T: BTree<'store, K, V>> BTree<'store, K, V> for &T {
+    #[inline]
+    fn assert_store(&self, store: &BTreeStore<K, V>) {
+        (**self).assert_store(store)
+    }
+
+    #[inline]
+    fn nodes(&self) -> NodeIter<'store, K, V> {
+        (**self).nodes()
+    }
+
+    #[inline]
+    fn parts(&self) -> (Option<NodePtr<K, V>>, usize, usize) {
+        (**self).parts()
+    }
+
+    #[cfg(feature = "checked")]
+    #[inline]
+    fn survivor_key(&self) -> Option<(NodePtr<K, V>, Stamp)> {
+        (**self).survivor_key()
+    }
 }
 
 /// Does a pre-order traversal of all nodes (*not* entries) in the tree.
@@ -59,11 +89,12 @@ impl<'store, K, V> Iterator for NodeIter<'store, K, V> {
                 match self.current_height.cmp(&self.max_height) {
                     Ordering::Less => {
                         self.current_height += 1;
-                        node = unsafe { node.as_ref().parent.unwrap() };
                         let index = unsafe { node.as_ref().parent_idx.assume_init() };
+                        node = unsafe { node.as_ref().parent.unwrap() };
                         if index < unsafe { node.as_ref() }.len {
                             // Remember: we've already traversed this node and its children at `index`s
                             // going down. But we haven't traversed its next child at `index + 1`...
+                            self.current_height -= 1;
                             break Some(unsafe { node.as_ref().edge(index + 1) });
                         }
                     }
@@ -5,7 +5,7 @@ use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use std::mem::{size_of, transmute, MaybeUninit};
-use std::ops::{Deref, RangeBounds};
+use std::ops::{Bound, Deref, RangeBounds};
 
 /// A copyable, immutable b-tree set, which doesn't drop its contents.
 pub struct BTreeSet<'store, T> {
@@ -14,6 +14,10 @@ pub struct BTreeSet<'store, T> {
 
 pub type Iter<'a, T> = crate::set::Iter<'a, T>;
 pub type Range<'a, T> = crate::set::Range<'a, T>;
+pub type Difference<'a, T> = crate::set::Difference<'a, T>;
+pub type SymmetricDifference<'a, T> = crate::set::SymmetricDifference<'a, T>;
+pub type Intersection<'a, T> = crate::set::Intersection<'a, T>;
+pub type Union<'a, T> = crate::set::Union<'a, T>;
 
 impl<'store, T> From<crate::BTreeSet<'store, T>> for BTreeSet<'store, T> {
     /// Creates a copyable set from a non-copyable set. Afterwards, the set is no longer mutable and
@@ -41,6 +45,15 @@ impl<'store, T> BTreeSet<'store, T> {
         Self::from(set)
     }
 
+    /// Replaces the stamp recorded at creation, for a set whose nodes were created in an earlier
+    /// generation (e.g. a restored tree handle).
+    #[cfg(feature = "checked")]
+    #[inline]
+    pub(crate) fn with_stamp(mut self, stamp: crate::store::Stamp) -> Self {
+        self.inner.stamp = stamp;
+        self
+    }
+
     /// Returns the number of elements in the set.
     #[inline]
     pub fn len(&self) -> usize {
@@ -115,14 +128,175 @@ impl<'store, T> BTreeSet<'store, T> {
 
     /// Returns an iterator over the set within the given bounds
     #[inline]
-    pub fn range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> Range<T>
+    pub fn range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> Range<'_, T>
     where
         T: Borrow<U>,
     {
         self.inner.range(bounds)
     }
+
+    /// Returns a parallel iterator over the set, which splits the work by subtrees.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_iter(&self) -> crate::par::ParSetIter<'_, T>
+    where
+        T: Sync,
+    {
+        self.inner.par_iter()
+    }
+
+    /// Returns a parallel iterator over the set within the given bounds
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_range<U: Ord + ?Sized>(
+        &self,
+        bounds: impl RangeBounds<U>,
+    ) -> crate::par::ParSetIter<'_, T>
+    where
+        T: Sync + Borrow<U>,
+    {
+        self.inner.par_range(bounds)
+    }
+
+    /// Returns an iterator over the values in `self` but not in `other`, in order.
+    #[inline]
+    pub fn difference<'a>(&'a self, other: &'a BTreeSet<'_, T>) -> Difference<'a, T>
+    where
+        T: Ord,
+    {
+        self.inner.difference(&other.inner)
+    }
+
+    /// Returns an iterator over the values in `self` or `other` but not both, in order.
+    #[inline]
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a BTreeSet<'_, T>,
+    ) -> SymmetricDifference<'a, T>
+    where
+        T: Ord,
+    {
+        self.inner.symmetric_difference(&other.inner)
+    }
+
+    /// Returns an iterator over the values in both `self` and `other`, in order.
+    #[inline]
+    pub fn intersection<'a>(&'a self, other: &'a BTreeSet<'_, T>) -> Intersection<'a, T>
+    where
+        T: Ord,
+    {
+        self.inner.intersection(&other.inner)
+    }
+
+    /// Returns an iterator over the values in `self` or `other`, in order and without duplicates.
+    #[inline]
+    pub fn union<'a>(&'a self, other: &'a BTreeSet<'_, T>) -> Union<'a, T>
+    where
+        T: Ord,
+    {
+        self.inner.union(&other.inner)
+    }
+
+    /// Returns `true` if `self` and `other` have no values in common.
+    #[inline]
+    pub fn is_disjoint(&self, other: &BTreeSet<'_, T>) -> bool
+    where
+        T: Ord,
+    {
+        self.inner.is_disjoint(&other.inner)
+    }
+
+    /// Returns `true` if every value in `self` is also in `other`.
+    #[inline]
+    pub fn is_subset(&self, other: &BTreeSet<'_, T>) -> bool
+    where
+        T: Ord,
+    {
+        self.inner.is_subset(&other.inner)
+    }
+
+    /// Returns `true` if every value in `other` is also in `self`.
+    #[inline]
+    pub fn is_superset(&self, other: &BTreeSet<'_, T>) -> bool
+    where
+        T: Ord,
+    {
+        self.inner.is_superset(&other.inner)
+    }
+
+    /// Returns a cursor at the first value above `bound`, or a detached cursor if there is none.
+    ///
+    /// `Bound::Unbounded` gives a cursor at the first value.
+    #[inline]
+    pub fn lower_bound<U: Ord + ?Sized>(&self, bound: Bound<&U>) -> Cursor<'_, T>
+    where
+        T: Borrow<U>,
+    {
+        let bounds = self.inner.0.node_bounds((bound, Bound::Unbounded));
+        // SAFETY: Copyable sets never mutate their nodes, which the borrow keeps alive
+        Cursor(unsafe { crate::copyable::map::Cursor::at(bounds.map(|bounds| bounds.start())) })
+    }
+
+    /// Returns a cursor at the last value below `bound`, or a detached cursor if there is none.
+    ///
+    /// `Bound::Unbounded` gives a cursor at the last value.
+    #[inline]
+    pub fn upper_bound<U: Ord + ?Sized>(&self, bound: Bound<&U>) -> Cursor<'_, T>
+    where
+        T: Borrow<U>,
+    {
+        let bounds = self.inner.0.node_bounds((Bound::Unbounded, bound));
+        // SAFETY: Same as `lower_bound`
+        Cursor(unsafe { crate::copyable::map::Cursor::at(bounds.map(|bounds| bounds.end())) })
+    }
 }
 
+// region Cursor
+/// A position in a set, returned by [BTreeSet::lower_bound] and [BTreeSet::upper_bound]. See
+/// [the map's](crate::copyable::map::Cursor).
+pub struct Cursor<'a, T>(crate::copyable::map::Cursor<'a, T, ()>);
+
+impl<'a, T> Cursor<'a, T> {
+    /// The current value, or `None` if detached
+    #[inline]
+    pub fn value(&self) -> Option<&'a T> {
+        self.0.key()
+    }
+
+    /// Whether the cursor is at a value
+    #[inline]
+    pub fn is_attached(&self) -> bool {
+        self.0.is_attached()
+    }
+
+    /// Moves to the next value, detaching if this is the last. Does nothing if detached.
+    #[inline]
+    pub fn move_next(&mut self) {
+        self.0.move_next()
+    }
+
+    /// Moves to the previous value, detaching if this is the first. Does nothing if detached.
+    #[inline]
+    pub fn move_prev(&mut self) {
+        self.0.move_prev()
+    }
+}
+
+impl<'a, T> Clone for Cursor<'a, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<'a, T: Debug> Debug for Cursor<'a, T> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Cursor").field(&self.value()).finish()
+    }
+}
+// endregion
+
 // region common trait impls
 impl<'store, T: Debug> Debug for BTreeSet<'store, T> {
     #[inline]
@@ -189,6 +363,8 @@ struct RawBTreeSet<'store, T> {
     // But fortunately [crate::BTreeSet]'s size doesn't depend on its generics, because everything
     // is under an indirect pointer, and `T` is [Sized]
     data: [MaybeUninit<u8>; size_of::<crate::BTreeSet<'static, ()>>()],
+    #[cfg(feature = "checked")]
+    stamp: crate::store::Stamp,
     _p: PhantomData<&'store T>,
 }
 
@@ -196,6 +372,8 @@ impl<'store, T> From<crate::BTreeSet<'store, T>> for RawBTreeSet<'store, T> {
     #[inline]
     fn from(inner: crate::BTreeSet<'store, T>) -> Self {
         Self {
+            #[cfg(feature = "checked")]
+            stamp: inner.stamp(),
             data: unsafe { transmute(inner) },
             _p: PhantomData,
         }
@@ -207,7 +385,10 @@ impl<'store, T> Deref for RawBTreeSet<'store, T> {
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        unsafe { &*(self.data.as_ptr() as *const crate::BTreeSet<'store, T>) }
+        let inner = unsafe { &*(self.data.as_ptr() as *const crate::BTreeSet<'store, T>) };
+        #[cfg(feature = "checked")]
+        inner.assert_alive(self.stamp);
+        inner
     }
 }
 
@@ -216,6 +397,8 @@ impl<'store, T> Clone for RawBTreeSet<'store, T> {
     fn clone(&self) -> Self {
         Self {
             data: self.data,
+            #[cfg(feature = "checked")]
+            stamp: self.stamp,
             _p: PhantomData,
         }
     }
@@ -235,6 +418,20 @@ impl<'a, 'store: 'a, T> IntoIterator for &'a BTreeSet<'store, T> {
     }
 }
 
+impl<'store, T> IntoIterator for BTreeSet<'store, T> {
+    type Item = &'store T;
+    type IntoIter = Iter<'store, T>;
+
+    /// Iterates over the set's values in order. Since the set is [Copy] and doesn't own its
+    /// contents, this yields references which live as long as the store.
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        // SAFETY: The nodes stay alive until they are garbage-collected, and `tracing_gc` requires
+        // that this set isn't used afterward. Copyable sets never mutate their nodes.
+        unsafe { Iter::new_detached(&self.inner) }
+    }
+}
+
 impl<'store, T> crate::copyable::sealed::BTree<'store, T, ()> for BTreeSet<'store, T> {
     #[inline]
     fn assert_store(&self, store: &BTreeStore<T, ()>) {
@@ -245,4 +442,15 @@ impl<'store, T> crate::copyable::sealed::BTree<'store, T, ()> for BTreeSet<'stor
     fn nodes(&self) -> crate::copyable::sealed::NodeIter<'store, T, ()> {
         self.inner.nodes()
     }
+
+    #[inline]
+    fn parts(&self) -> (Option<crate::node::NodePtr<T, ()>>, usize, usize) {
+        self.inner.parts()
+    }
+
+    #[cfg(feature = "checked")]
+    #[inline]
+    fn survivor_key(&self) -> Option<(crate::node::NodePtr<T, ()>, crate::store::Stamp)> {
+        self.inner.survivor_key(self.inner.stamp)
+    }
 }
@@ -0,0 +1,537 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::ptr::NonNull;
+
+use crate::copyable::BTree;
+use crate::node::{Node, NodePtr, M};
+use crate::BTreeStore;
+
+/// Identifies the snapshot format
+const MAGIC: [u8; 4] = *b"BTPS";
+/// Incremented when the snapshot format changes
+const VERSION: u8 = 1;
+
+const LEAF_TAG: u8 = 0;
+const INTERNAL_TAG: u8 = 1;
+
+/// A type which can be written to and read from a [BTreeStore::snapshot].
+///
+/// This is a simple, compact binary encoding: integers are little-endian, and variable-sized
+/// values are prefixed by their length.
+pub trait Persist: Sized {
+    /// Writes the value
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// Reads a value written by [Persist::write_to]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self>;
+}
+
+/// A tree restored by [BTreeStore::restore]. Convert it into a copyable map or set in the
+/// restored store with [TreeHandle::to_map] or [TreeHandle::to_set].
+///
+/// Like copyable maps, handles don't keep their nodes alive: for
+/// [tracing_gc](crate::copyable::BTreeStoreExt::tracing_gc)'s safety requirement, every handle
+/// you use afterward counts as a b-tree in the store, so convert it and pass the result.
+#[derive(Debug, Clone, Copy)]
+pub struct TreeHandle {
+    store_id: u64,
+    /// Type-erased pointer to the root node, since the handle isn't generic. The store id ensures
+    /// the node type is correct.
+    root: Option<NonNull<()>>,
+    height: usize,
+    length: usize,
+    /// The stamp of the restored store when the handle was created, so converting the handle after
+    /// `tracing_gc` freed its nodes panics like using the tree would.
+    #[cfg(feature = "checked")]
+    stamp: crate::store::Stamp,
+}
+
+impl<K, V> BTreeStore<K, V> {
+    /// Writes the given b-trees to `writer`, so they can be recreated in a new store via
+    /// [BTreeStore::restore]. Nodes shared between trees (e.g. copies of the same copyable map)
+    /// are only written once, and will still be shared when restored.
+    ///
+    /// *Panic*s if any of the trees aren't from this store.
+    pub fn snapshot<'a>(
+        &self,
+        b_trees: impl IntoIterator<Item = impl BTree<'a, K, V>>,
+        mut writer: impl Write,
+    ) -> io::Result<()>
+    where
+        K: Persist + 'a,
+        V: Persist + 'a,
+    {
+        let b_trees = b_trees
+            .into_iter()
+            .map(|b_tree| {
+                b_tree.assert_store(self);
+                b_tree.parts()
+            })
+            .collect::<Vec<_>>();
+
+        // Nodes are written children-first, so restore can link each node to its children as soon
+        // as it's read
+        let mut node_idxs = HashMap::new();
+        let mut node_order = Vec::new();
+        for (root, height, _) in &b_trees {
+            if let Some(root) = root {
+                unsafe { post_order(*root, *height, &mut node_idxs, &mut node_order) };
+            }
+        }
+
+        writer.write_all(&MAGIC)?;
+        VERSION.write_to(&mut writer)?;
+        node_order.len().write_to(&mut writer)?;
+        for (node, height) in &node_order {
+            let node = unsafe { node.as_ref() };
+            if *height == 0 {
+                LEAF_TAG.write_to(&mut writer)?;
+            } else {
+                INTERNAL_TAG.write_to(&mut writer)?;
+            }
+            node.len.write_to(&mut writer)?;
+            if *height == 0 {
+                for (key, val) in unsafe { node.keys().iter().zip(node.vals()) } {
+                    key.write_to(&mut writer)?;
+                    val.write_to(&mut writer)?;
+                }
+            } else {
+                for key in unsafe { node.keys() } {
+                    key.write_to(&mut writer)?;
+                }
+                for edge in unsafe { node.edges() } {
+                    node_idxs[edge].write_to(&mut writer)?;
+                }
+            }
+        }
+        b_trees.len().write_to(&mut writer)?;
+        for (root, height, _) in &b_trees {
+            root.map(|root| node_idxs[&root]).write_to(&mut writer)?;
+            height.write_to(&mut writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Reads b-trees written by [BTreeStore::snapshot] into a new store. Returns the store and a
+    /// handle to each tree, in the order they were passed to `snapshot`.
+    ///
+    /// Invalid or truncated input returns an error; it never creates a tree which could cause
+    /// undefined behavior. However, input with out-of-order keys will create trees whose
+    /// lookups return the wrong results (like a malformed [Ord] implementation).
+    pub fn restore(mut reader: impl Read) -> io::Result<(Self, Vec<TreeHandle>)>
+    where
+        K: Persist,
+        V: Persist,
+    {
+        let store = Self::new();
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC || u8::read_from(&mut reader)? != VERSION {
+            return Err(invalid_data(
+                "not a b-tree snapshot, or an unsupported version",
+            ));
+        }
+
+        let num_nodes = usize::read_from(&mut reader)?;
+        let mut nodes = Vec::new();
+        // Height, number of entries, and whether each node has a parent
+        let mut node_info = Vec::<(usize, usize, bool)>::new();
+        for _ in 0..num_nodes {
+            let tag = u8::read_from(&mut reader)?;
+            let len = u16::read_from(&mut reader)?;
+            if len == 0 || len as usize > M {
+                return Err(invalid_data("node has an invalid number of entries"));
+            }
+            match tag {
+                LEAF_TAG => {
+                    // On error the entries already read are leaked, since nodes don't drop them
+                    let mut node = Node::leaf();
+                    for idx in 0..len {
+                        let key = K::read_from(&mut reader)?;
+                        let val = V::read_from(&mut reader)?;
+                        unsafe { node.insert_val(idx, key, val) };
+                    }
+                    nodes.push(store.alloc(node));
+                    node_info.push((0, len as usize, false));
+                }
+                INTERNAL_TAG => {
+                    let mut keys = Vec::with_capacity(len as usize);
+                    for _ in 0..len {
+                        keys.push(K::read_from(&mut reader)?);
+                    }
+                    let mut edges = Vec::with_capacity(len as usize + 1);
+                    let mut height = None;
+                    let mut length = 0;
+                    for _ in 0..len + 1 {
+                        let edge_idx = usize::read_from(&mut reader)?;
+                        let Some((edge_height, edge_length, has_parent)) =
+                            node_info.get_mut(edge_idx)
+                        else {
+                            return Err(invalid_data("node has an edge to an unknown node"));
+                        };
+                        if *has_parent || height.is_some_and(|height| height != *edge_height) {
+                            return Err(invalid_data("node has an edge to an invalid node"));
+                        }
+                        *has_parent = true;
+                        height = Some(*edge_height);
+                        length += *edge_length;
+                        edges.push(nodes[edge_idx]);
+                    }
+
+                    let mut node = store.alloc(Node::internal(true));
+                    let mut edges = edges.into_iter();
+                    let mut first_edge = edges.next().unwrap();
+                    unsafe {
+                        first_edge.as_mut().set_parent(node, 0);
+                        node.as_mut().set_last_edge(first_edge);
+                        for (idx, (key, mut edge)) in keys.into_iter().zip(edges).enumerate() {
+                            edge.as_mut().set_parent(node, idx as u16 + 1);
+                            node.as_mut().insert_edge(idx as u16, true, key, edge);
+                        }
+                    }
+                    nodes.push(node);
+                    node_info.push((height.unwrap() + 1, length, false));
+                }
+                _ => return Err(invalid_data("node has an invalid tag")),
+            }
+        }
+
+        let num_trees = usize::read_from(&mut reader)?;
+        let mut handles = Vec::new();
+        let mut linked_roots = HashSet::new();
+        for _ in 0..num_trees {
+            let root = Option::<usize>::read_from(&mut reader)?;
+            let height = usize::read_from(&mut reader)?;
+            let length = match root {
+                None => 0,
+                Some(root) => match node_info.get(root) {
+                    Some((root_height, length, false)) if *root_height == height => *length,
+                    _ => return Err(invalid_data("tree has an invalid root")),
+                },
+            };
+            let root = root.map(|root| nodes[root]);
+            if let Some(root) = root {
+                if linked_roots.insert(root) {
+                    unsafe { link_leaves(root, height) };
+                }
+            }
+            handles.push(TreeHandle {
+                store_id: store.id,
+                root: root.map(|root| unsafe { root.as_ptr() }.cast()),
+                height,
+                length,
+                #[cfg(feature = "checked")]
+                stamp: store.stamp(),
+            });
+        }
+
+        Ok((store, handles))
+    }
+}
+
+impl TreeHandle {
+    /// Returns the restored map.
+    ///
+    /// *Panic*s if `store` isn't the store this handle was restored with.
+    /// Under the `checked` feature, also *panic*s if `tracing_gc` freed the tree's nodes.
+    #[inline]
+    pub fn to_map<K, V>(self, store: &BTreeStore<K, V>) -> crate::copyable::BTreeMap<'_, K, V> {
+        let map = crate::copyable::BTreeMap::from(self.to_mutable(store));
+        #[cfg(feature = "checked")]
+        let map = map.with_stamp(self.stamp);
+        map
+    }
+
+    /// Returns the restored set.
+    ///
+    /// *Panic*s if `store` isn't the store this handle was restored with.
+    /// Under the `checked` feature, also *panic*s if `tracing_gc` freed the tree's nodes.
+    #[inline]
+    pub fn to_set<T>(self, store: &BTreeStore<T, ()>) -> crate::copyable::BTreeSet<'_, T> {
+        let set = crate::copyable::BTreeSet::from(crate::BTreeSet(self.to_mutable(store)));
+        #[cfg(feature = "checked")]
+        let set = set.with_stamp(self.stamp);
+        set
+    }
+
+    /// The restored tree as a mutable map, which must be converted into a copyable map or set
+    /// because other handles may share its nodes.
+    fn to_mutable<K, V>(self, store: &BTreeStore<K, V>) -> crate::BTreeMap<'_, K, V> {
+        assert_eq!(
+            self.store_id, store.id,
+            "tree handle is not from this store"
+        );
+        // SAFETY: The handle is from this store, which has nodes of type `Node<K, V>`, and the
+        // caller ensures they weren't garbage-collected
+        let root = self
+            .root
+            .map(|root| unsafe { NodePtr::from_ref(root.cast::<Node<K, V>>().as_ref()) });
+        #[cfg(feature = "checked")]
+        store.assert_alive(self.stamp, root);
+        // SAFETY: `restore` validated the tree's shape
+        unsafe { crate::BTreeMap::from_parts(store, root, self.height, self.length) }
+    }
+
+    /// Number of entries in the tree
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Whether the tree is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+/// Assigns an index to every node in the tree not already in `node_idxs`, children before parents.
+unsafe fn post_order<K, V>(
+    node: NodePtr<K, V>,
+    height: usize,
+    node_idxs: &mut HashMap<NodePtr<K, V>, usize>,
+    node_order: &mut Vec<(NodePtr<K, V>, usize)>,
+) {
+    if node_idxs.contains_key(&node) {
+        // Shared, so the entire subtree was already written
+        return;
+    }
+    if height > 0 {
+        for edge in node.as_ref().edges() {
+            post_order(*edge, height - 1, node_idxs, node_order);
+        }
+    }
+    node_idxs.insert(node, node_order.len());
+    node_order.push((node, height));
+}
+
+/// Sets `prev` and `next` for all leaves in the tree
+unsafe fn link_leaves<K, V>(root: NodePtr<K, V>, height: usize) {
+    unsafe fn link<K, V>(node: NodePtr<K, V>, height: usize, prev: &mut Option<NodePtr<K, V>>) {
+        if height == 0 {
+            let mut node = node;
+            node.as_mut().set_prev(*prev);
+            node.as_mut().set_next(None);
+            if let Some(mut prev) = *prev {
+                prev.as_mut().set_next(Some(node));
+            }
+            *prev = Some(node);
+        } else {
+            for edge in node.as_ref().edges() {
+                link(*edge, height - 1, prev);
+            }
+        }
+    }
+    link(root, height, &mut None)
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// region Persist impls
+macro_rules! impl_persist_num {
+    ($($ty:ty),*) => {
+        $(impl Persist for $ty {
+            #[inline]
+            fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_le_bytes())
+            }
+
+            #[inline]
+            fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+                let mut bytes = [0; std::mem::size_of::<$ty>()];
+                reader.read_exact(&mut bytes)?;
+                Ok(<$ty>::from_le_bytes(bytes))
+            }
+        })*
+    };
+}
+
+impl_persist_num!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// Written as a `u64` so snapshots are portable
+impl Persist for usize {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u64).write_to(writer)
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        usize::try_from(u64::read_from(reader)?).map_err(|_| invalid_data("usize out of range"))
+    }
+}
+
+/// Written as an `i64` so snapshots are portable
+impl Persist for isize {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as i64).write_to(writer)
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        isize::try_from(i64::read_from(reader)?).map_err(|_| invalid_data("isize out of range"))
+    }
+}
+
+impl Persist for bool {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u8).write_to(writer)
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        match u8::read_from(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid bool")),
+        }
+    }
+}
+
+impl Persist for char {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u32).write_to(writer)
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        char::from_u32(u32::read_from(reader)?).ok_or_else(|| invalid_data("invalid char"))
+    }
+}
+
+impl Persist for () {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(_reader: &mut R) -> io::Result<Self> {
+        Ok(())
+    }
+}
+
+impl Persist for String {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.len().write_to(writer)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        let len = usize::read_from(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+}
+
+impl Persist for Box<str> {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.len().write_to(writer)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        String::read_from(reader).map(String::into_boxed_str)
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.len().write_to(writer)?;
+        for elem in self {
+            elem.write_to(writer)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        let len = usize::read_from(reader)?;
+        // Don't trust `len` for the capacity, since the input may be malformed
+        let mut vec = Vec::with_capacity(len.min(4096));
+        for _ in 0..len {
+            vec.push(T::read_from(reader)?);
+        }
+        Ok(vec)
+    }
+}
+
+impl<T: Persist> Persist for Box<[T]> {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.len().write_to(writer)?;
+        for elem in self.iter() {
+            elem.write_to(writer)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        Vec::read_from(reader).map(Vec::into_boxed_slice)
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    #[inline]
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            None => false.write_to(writer),
+            Some(value) => {
+                true.write_to(writer)?;
+                value.write_to(writer)
+            }
+        }
+    }
+
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+        match bool::read_from(reader)? {
+            false => Ok(None),
+            true => T::read_from(reader).map(Some),
+        }
+    }
+}
+
+macro_rules! impl_persist_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Persist),+> Persist for ($($name,)+) {
+            #[inline]
+            #[allow(non_snake_case)]
+            fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+                let ($($name,)+) = self;
+                $($name.write_to(writer)?;)+
+                Ok(())
+            }
+
+            #[inline]
+            fn read_from<R: Read + ?Sized>(reader: &mut R) -> io::Result<Self> {
+                Ok(($($name::read_from(reader)?,)+))
+            }
+        }
+    };
+}
+
+impl_persist_tuple!(A);
+impl_persist_tuple!(A, B);
+impl_persist_tuple!(A, B, C);
+impl_persist_tuple!(A, B, C, D);
+impl_persist_tuple!(A, B, C, D, E);
+impl_persist_tuple!(A, B, C, D, E, F);
+// endregion
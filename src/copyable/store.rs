@@ -24,6 +24,8 @@ pub trait BTreeStoreExt<K, V> {
 /// This trait is [sealed](https://rust-lang.github.io/api-guidelines/future-proofing.html#sealed-traits-protect-against-downstream-implementations-c-sealed)
 pub trait BTree<'store, K, V>: crate::copyable::sealed::BTree<'store, K, V> {}
 
+impl<'store, K, V, T: crate::copyable::sealed::BTree<'store, K, V>> BTree<'store, K, V> for T {}
+
 impl<K, V> BTreeStoreExt<K, V> for BTreeStore<K, V> {
     #[inline]
     unsafe fn tracing_gc<'a>(&self, b_trees: impl IntoIterator<Item = impl BTree<'a, K, V>>)
@@ -31,13 +33,19 @@ impl<K, V> BTreeStoreExt<K, V> for BTreeStore<K, V> {
         K: 'a,
         V: 'a,
     {
+        #[cfg(feature = "checked")]
+        let mut survivors = HashSet::new();
         let nodes = b_trees
             .into_iter()
             .flat_map(|b_tree| {
                 b_tree.assert_store(self);
+                #[cfg(feature = "checked")]
+                survivors.extend(b_tree.survivor_key());
                 b_tree.nodes()
             })
             .collect::<HashSet<_>>();
         self.retain_shared(|node| nodes.contains(&NodePtr::from_ref(node)));
+        #[cfg(feature = "checked")]
+        self.end_gc(survivors);
     }
 }
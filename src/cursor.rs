@@ -2,16 +2,16 @@ use crate::node::{Node, NodePtr};
 use std::marker::PhantomData;
 
 /// Iterates a node's keys and values forwards or backwards.
-pub struct Cursor<'a, K, V> {
+pub struct Cursor<'a, K, V, S = ()> {
     /// Current node
-    node: Option<NodePtr<K, V>>,
+    node: Option<NodePtr<K, V, S>>,
     /// Current index in the node, not counting child nodes.
     index: u16,
     /// Phantom data
-    _p: PhantomData<(&'a K, &'a V)>,
+    _p: PhantomData<(&'a K, &'a V, &'a S)>,
 }
 
-impl<'a, K, V> Cursor<'a, K, V> {
+impl<'a, K, V, S> Cursor<'a, K, V, S> {
     #[inline]
     pub fn new_detached() -> Self {
         Self {
@@ -24,7 +24,7 @@ impl<'a, K, V> Cursor<'a, K, V> {
     /// # Safety
     /// Node and connected pointers must be alive for `'a`, and the node must be a leaf.
     #[inline]
-    pub unsafe fn new(node: Option<NodePtr<K, V>>, index: u16) -> Self {
+    pub unsafe fn new(node: Option<NodePtr<K, V, S>>, index: u16) -> Self {
         let cursor = Self {
             node,
             index,
@@ -37,7 +37,7 @@ impl<'a, K, V> Cursor<'a, K, V> {
     /// # Safety
     /// Node and connected pointers must be alive for `'a`, and the node must be a leaf.
     #[inline]
-    pub unsafe fn new_at_end(node: Option<NodePtr<K, V>>) -> Self {
+    pub unsafe fn new_at_end(node: Option<NodePtr<K, V, S>>) -> Self {
         let idx = match node {
             None => 0,
             Some(node) => node.as_ref().len - 1,
@@ -89,7 +89,7 @@ impl<'a, K, V> Cursor<'a, K, V> {
     }
 
     #[inline]
-    pub fn address(&self) -> Option<(NodePtr<K, V>, u16)> {
+    pub fn address(&self) -> Option<(NodePtr<K, V, S>, u16)> {
         let node = self.node?;
         Some((node, self.index))
     }
@@ -117,14 +117,14 @@ impl<'a, K, V> Cursor<'a, K, V> {
     }
 
     #[inline]
-    fn node(&self) -> Option<&'a Node<K, V>> {
+    fn node(&self) -> Option<&'a Node<K, V, S>> {
         self.node.as_ref().map(|node| unsafe { node.as_ref() })
     }
 
     /// # Safety
     /// Must have exclusive access to the current node
     #[inline]
-    unsafe fn node_mut(&mut self) -> Option<&'a mut Node<K, V>> {
+    unsafe fn node_mut(&mut self) -> Option<&'a mut Node<K, V, S>> {
         self.node.as_mut().map(|node| node.as_mut())
     }
 
@@ -137,3 +137,14 @@ impl<'a, K, V> Cursor<'a, K, V> {
         );
     }
 }
+
+impl<'a, K, V, S> Clone for Cursor<'a, K, V, S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            index: self.index,
+            _p: PhantomData,
+        }
+    }
+}
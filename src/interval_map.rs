@@ -0,0 +1,326 @@
+use crate::{BTreeMap, BTreeStore};
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::mem::replace;
+use std::ops::Range;
+
+/// A b-tree map from non-overlapping ranges to values. Inserting a range splits the ranges it
+/// overlaps, and adjacent ranges with equal values are coalesced.
+///
+/// Ranges are half-open (`start..end`) and empty ranges are ignored. Each range is stored as an
+/// entry from its start to its end and value.
+pub struct IntervalMap<'store, K, V> {
+    map: BTreeMap<'store, K, (K, V)>,
+}
+
+impl<'store, K, V> IntervalMap<'store, K, V> {
+    /// Creates an empty interval map.
+    #[inline]
+    pub fn new_in(store: &'store BTreeStore<K, (K, V)>) -> Self {
+        Self {
+            map: BTreeMap::new_in(store),
+        }
+    }
+
+    /// Returns the number of (coalesced) ranges in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no ranges.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Clears the map, removing all ranges.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    /// Returns the value of the range containing the point, if any.
+    #[inline]
+    pub fn get(&self, point: &K) -> Option<&V>
+    where
+        K: Ord,
+    {
+        self.get_range_value(point).map(|(_, v)| v)
+    }
+
+    /// Returns the range containing the point and its value, if any.
+    #[inline]
+    pub fn get_range_value(&self, point: &K) -> Option<(Range<&K>, &V)>
+    where
+        K: Ord,
+    {
+        let (start, (end, value)) = self.map.range(..=point).next_back()?;
+        (point < end).then_some((start..end, value))
+    }
+
+    /// Returns `true` if a range contains the point.
+    #[inline]
+    pub fn contains(&self, point: &K) -> bool
+    where
+        K: Ord,
+    {
+        self.get_range_value(point).is_some()
+    }
+
+    /// Maps every point in the range to the value, replacing the values of any overlapping ranges
+    /// there.
+    pub fn insert_range(&mut self, range: Range<K>, value: V)
+    where
+        K: Ord + Clone,
+        V: Clone + PartialEq,
+    {
+        if range.is_empty() {
+            return;
+        }
+        self.remove_range(range.clone());
+        let Range { start, mut end } = range;
+
+        // Coalesce with the next range
+        if matches!(self.map.get(&end), Some((_, next_value)) if *next_value == value) {
+            end = self.map.remove(&end).unwrap().0;
+        }
+        // Coalesce with the previous range
+        if let Some((_, (prev_end, prev_value))) = self.map.range_mut(..&start).next_back() {
+            if *prev_end == start && *prev_value == value {
+                *prev_end = end;
+                return;
+            }
+        }
+        self.map.insert(start, (end, value));
+    }
+
+    /// Unmaps every point in the range, splitting the ranges which overlap its ends.
+    pub fn remove_range(&mut self, range: Range<K>)
+    where
+        K: Ord + Clone,
+        V: Clone,
+    {
+        if range.is_empty() {
+            return;
+        }
+
+        // Truncate the range which overlaps the start, and split it if it also overlaps the end
+        if let Some((_, (prev_end, prev_value))) = self.map.range_mut(..&range.start).next_back() {
+            if *prev_end > range.start {
+                let old_end = replace(prev_end, range.start.clone());
+                if old_end > range.end {
+                    let value = prev_value.clone();
+                    self.map.insert(range.end, (old_end, value));
+                    return;
+                }
+            }
+        }
+
+        // Remove the ranges which start inside, and keep the part of the last one after the end
+        while let Some(start) = self
+            .map
+            .range(&range.start..&range.end)
+            .next()
+            .map(|(start, _)| start.clone())
+        {
+            let (end, value) = self.map.remove(&start).unwrap();
+            if end > range.end {
+                self.map.insert(range.end, (end, value));
+                return;
+            }
+        }
+    }
+
+    /// Iterates over the ranges and values which overlap the range, in order.
+    #[inline]
+    pub fn overlapping<'a>(&'a self, range: &'a Range<K>) -> Overlapping<'a, K, V>
+    where
+        K: Ord,
+    {
+        if range.is_empty() {
+            return Overlapping(self.map.range(&range.start..&range.start));
+        }
+        // The previous range may overlap the start
+        let start = match self.map.range(..&range.start).next_back() {
+            Some((prev_start, (prev_end, _))) if *prev_end > range.start => prev_start,
+            _ => &range.start,
+        };
+        Overlapping(self.map.range(start..&range.end))
+    }
+
+    /// Iterates over the sub-ranges of the range which aren't in the map, in order.
+    #[inline]
+    pub fn gaps<'a>(&'a self, range: &'a Range<K>) -> Gaps<'a, K, V>
+    where
+        K: Ord,
+    {
+        Gaps {
+            overlapping: self.overlapping(range),
+            start: Some(&range.start),
+            end: &range.end,
+        }
+    }
+
+    /// Validates the map, *panic*ing if it is invalid. Specifically, we check the b-tree, and
+    /// that the ranges are non-empty, don't overlap, and are coalesced.
+    pub fn validate(&self)
+    where
+        K: Debug + Ord,
+        V: Debug + PartialEq,
+    {
+        self.map.validate();
+        let mut prev: Option<(&K, &V)> = None;
+        for (start, (end, value)) in self.map.iter() {
+            assert!(start < end, "range {:?}..{:?} is empty", start, end);
+            if let Some((prev_end, prev_value)) = prev {
+                assert!(
+                    prev_end <= start,
+                    "range {:?}.. overlaps the previous",
+                    start
+                );
+                assert!(
+                    prev_end != start || prev_value != value,
+                    "range {:?}.. isn't coalesced with the previous",
+                    start
+                );
+            }
+            prev = Some((end, value));
+        }
+    }
+
+    /// Iterates over the ranges and values, in order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.map.iter())
+    }
+}
+
+// region common trait impls
+impl<'store, K: Debug, V: Debug> Debug for IntervalMap<'store, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'store, K: PartialEq, V: PartialEq> PartialEq for IntervalMap<'store, K, V> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<'store, K: Eq, V: Eq> Eq for IntervalMap<'store, K, V> {}
+
+impl<'store, K: Ord + Clone, V: Clone + PartialEq> Extend<(Range<K>, V)>
+    for IntervalMap<'store, K, V>
+{
+    #[inline]
+    fn extend<I: IntoIterator<Item = (Range<K>, V)>>(&mut self, iter: I) {
+        for (range, value) in iter {
+            self.insert_range(range, value);
+        }
+    }
+}
+
+impl<'a, 'store: 'a, K, V> IntoIterator for &'a IntervalMap<'store, K, V> {
+    type Item = (Range<&'a K>, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+// endregion
+
+// region iterators
+/// Iterator over all ranges and values
+pub struct Iter<'a, K, V>(crate::map::Iter<'a, K, (K, V)>);
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (Range<&'a K>, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(start, (end, v))| (start..end, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(start, (end, v))| (start..end, v))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, K, V> FusedIterator for Iter<'a, K, V> {}
+
+/// Iterator over the ranges and values which overlap a range
+pub struct Overlapping<'a, K, V>(crate::map::Range<'a, K, (K, V)>);
+
+impl<'a, K, V> Iterator for Overlapping<'a, K, V> {
+    type Item = (Range<&'a K>, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(start, (end, v))| (start..end, v))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Overlapping<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(start, (end, v))| (start..end, v))
+    }
+}
+
+impl<'a, K, V> FusedIterator for Overlapping<'a, K, V> {}
+
+/// Iterator over the sub-ranges of a range which aren't in the map
+pub struct Gaps<'a, K, V> {
+    overlapping: Overlapping<'a, K, V>,
+    /// Start of the next gap, or `None` when done
+    start: Option<&'a K>,
+    end: &'a K,
+}
+
+impl<'a, K: Ord, V> Iterator for Gaps<'a, K, V> {
+    type Item = Range<&'a K>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.start?;
+            match self.overlapping.next() {
+                Some((range, _)) => {
+                    self.start = Some(range.end);
+                    if start < range.start {
+                        return Some(start..range.start);
+                    }
+                }
+                None => {
+                    self.start = None;
+                    if start < self.end {
+                        return Some(start..self.end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K: Ord, V> FusedIterator for Gaps<'a, K, V> {}
+// endregion
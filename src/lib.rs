@@ -1,18 +1,51 @@
 #![doc = include_str!("../README.md")]
 
+pub use bimap::BiBTreeMap;
+pub use compare::{Comparator, OrdComparator, ShortSeparators};
+pub use interval_map::IntervalMap;
 pub use map::BTreeMap;
+pub use multimap::BTreeMultiMap;
+pub use multiset::BTreeMultiSet;
+pub use prefix::{ByteKey, PrefixKey};
+pub use priority_queue::BTreePriorityQueue;
+pub use range_set::RangeSet;
+pub use separator::{Separator, SeparatorKey};
 pub use set::BTreeSet;
-pub use store::BTreeStore;
+pub use store::{BTreeStore, CapacityError};
+pub use summary::{Count, Summary};
+pub use vec::BTreeVec;
 
+pub mod bimap;
+/// Custom key orders
+pub mod compare;
 /// Immutable map and set which implement [Copy] but don't drop or deallocate its contents; instead,
 /// the store has a new helper which performs a special variant of
 /// [tracing garbage collection](https://en.wikipedia.org/wiki/Tracing_garbage_collection)
 #[cfg(feature = "copyable")]
 pub mod copyable;
 mod cursor;
+pub mod interval_map;
 pub mod map;
+pub mod multimap;
+pub mod multiset;
 mod node;
+/// Rayon parallel iterators over maps and sets
+#[cfg(feature = "rayon")]
+pub mod par;
+/// Prefix scans over composite and byte-string keys
+mod prefix;
+pub mod priority_queue;
+pub mod range_set;
+/// Lookup logic shared by b-trees in a store and archived b-trees
+mod search;
+/// How keys are copied into internal nodes
+mod separator;
+#[cfg(feature = "serde")]
+mod serde_impls;
 pub mod set;
 mod store;
+/// Cached aggregates of subtrees
+mod summary;
 /// Misc utility functions
 mod utils;
+pub mod vec;
@@ -1,61 +1,59 @@
+use std::any::Any;
 use std::borrow::Borrow;
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::Bound;
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
 use std::iter::FusedIterator;
 use std::marker::PhantomData;
-use std::mem::{forget, MaybeUninit};
-use std::ops::RangeBounds;
+use std::mem::{self, forget, needs_drop, size_of, ManuallyDrop};
+use std::ops::{Bound, RangeBounds};
 use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
-use std::ptr::{drop_in_place, NonNull};
-use std::thread::panicking;
+use std::ptr::{self, drop_in_place, NonNull};
 
 use crate::cursor::Cursor;
-use crate::node::{address_after, address_before, normalize_address, Node, NodePtr, M};
+use crate::compare::{BytewiseComparator, Comparator, OrdComparator};
+use crate::node::{Node, NodePtr, M};
+use crate::prefix::{cmp_byte_prefix, ByteKey, PrefixKey};
+use crate::search::{self, Find, NodeBounds, SearchTree};
+use crate::separator::{drop_separator, Separator};
+use crate::summary::{Count, Summary};
+#[cfg(feature = "serde")]
+pub use crate::serde_impls::BTreeMapSeed;
+#[cfg(feature = "checked")]
+use crate::store::Stamp;
 use crate::utils::PtrEq;
-use crate::BTreeStore;
+use crate::{BTreeStore, CapacityError};
 
 /// A b-tree map.
 ///
+/// Keys are ordered by `C`, which is [Ord] by default. See [BTreeMap::with_comparator_in].
+///
 /// See [std::collections::BTreeMap] for more info.
+///
+/// # Unwind safety
+///
+/// If user code *panic*s during a mutating method (the comparator or [Ord], [Clone] when creating
+/// separators, or a key or value's destructor), the map stays valid: it passes
+/// [validate](Self::validate), can still be used, and when dropped frees its nodes back to the
+/// store. Each method documents which entries it may have changed. A *panic*ing [Summary] can
+/// leave cached summaries stale.
 // TODO: impl Clone
-pub struct BTreeMap<'store, K, V> {
-    store: &'store BTreeStore<K, V>,
-    root: Option<NodePtr<K, V>>,
+pub struct BTreeMap<'store, K, V, C = OrdComparator, S = ()> {
+    store: &'store BTreeStore<K, V, S>,
+    root: Option<NodePtr<K, V, S>>,
     length: usize,
     height: usize,
+    /// Orders the keys
+    cmp: C,
+    /// Whether internal nodes' keys are owned clones which we drop, rather than bitwise copies.
+    /// See [Separator].
+    owns_separators: bool,
     /// For dropck; the `Box` avoids making the `Unpin` impl more strict than before
     _p: PhantomData<Box<(K, V)>>,
 }
 
-/// The result of looking up an address to retrieve or insert an entry
-enum Find<K, V> {
-    /// The tree is empty
-    NoRoot,
-    /// The entry would be before this address
-    Before { node: NodePtr<K, V>, idx: u16 },
-    /// The entry is at this address
-    At { node: NodePtr<K, V>, idx: u16 },
-}
-
-/// Pointer and index to the start and end entry for a range within a tree.
-///
-/// These bounds are always inclusive. Use `Option<NodeBounds<'a, K, V>>` to represent a
-/// potentially-empty range.
-struct NodeBounds<K, V> {
-    /// Start node (inclusive)
-    start_node: NodePtr<K, V>,
-    /// End node (inclusive)
-    end_node: NodePtr<K, V>,
-    /// Index in start node (inclusive)
-    start_index: u16,
-    /// Index in end node (inclusive)
-    end_index: u16,
-}
-
-impl<'store, K, V> BTreeMap<'store, K, V> {
+impl<'store, K, V, S> BTreeMap<'store, K, V, OrdComparator, S> {
     /// Creates an empty `BTreeMap`.
     ///
     /// # Examples
@@ -66,16 +64,203 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     /// let mut map = BTreeMap::new_in(&store);
     /// ```
     #[inline]
-    pub const fn new_in(store: &'store BTreeStore<K, V>) -> Self {
+    pub const fn new_in(store: &'store BTreeStore<K, V, S>) -> Self {
+        Self {
+            store,
+            root: None,
+            length: 0,
+            height: 0,
+            cmp: OrdComparator,
+            owns_separators: false,
+            _p: PhantomData,
+        }
+    }
+
+    /// Creates a map from key-value pairs in strictly ascending key order. This builds the nodes
+    /// bottom-up, which is faster than inserting each pair, and leaves them mostly full.
+    ///
+    /// *Panic*s if the keys aren't strictly ascending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let map = BTreeMap::from_sorted_iter_in(&store, (0..100).map(|i| (i, i * 2)));
+    /// assert_eq!(map.get(&50), Some(&100));
+    /// ```
+    pub fn from_sorted_iter_in(
+        store: &'store BTreeStore<K, V, S>,
+        iter: impl IntoIterator<Item = (K, V)>,
+    ) -> Self
+    where
+        K: Ord + Separator,
+        S: Summary<K, V>,
+    {
+        let entries = iter.into_iter().collect::<Vec<_>>();
+        assert!(
+            entries.windows(2).all(|w| w[0].0 < w[1].0),
+            "BTreeMap::from_sorted_iter_in keys aren't strictly ascending"
+        );
+        Self::from_sorted_vec_in(store, entries)
+    }
+
+    /// Creates a map from existing nodes.
+    ///
+    /// # Safety
+    /// `root` must be a valid tree of `height` and `length` in `store`, which no other mutable map
+    /// owns.
+    #[cfg(feature = "copyable")]
+    #[inline]
+    pub(crate) unsafe fn from_parts(
+        store: &'store BTreeStore<K, V, S>,
+        root: Option<NodePtr<K, V, S>>,
+        height: usize,
+        length: usize,
+    ) -> Self {
+        Self {
+            store,
+            root,
+            length,
+            height,
+            cmp: OrdComparator,
+            owns_separators: false,
+            _p: PhantomData,
+        }
+    }
+
+    /// [Self::from_sorted_iter_in] without checking that the keys are strictly ascending. If they
+    /// aren't, the map will be invalid (but memory-safe).
+    pub(crate) fn from_sorted_vec_in(store: &'store BTreeStore<K, V, S>, entries: Vec<(K, V)>) -> Self
+    where
+        K: Separator,
+        S: Summary<K, V>,
+    {
+        let mut map = Self::new_in(store);
+        let length = entries.len();
+        if length == 0 {
+            return map;
+        }
+
+        // Distribute entries evenly between leaves (and children evenly between parents) so that
+        // every node has at least M / 2 entries
+        let mut entries = entries.into_iter();
+        let level = alloc_leaves(store, length.div_ceil(M));
+        for (i, mut leaf) in level.iter().copied().enumerate() {
+            for idx in 0..even_chunk(length, level.len(), i).len() as u16 {
+                let (key, val) = entries.next().unwrap();
+                unsafe { leaf.as_mut().insert_val(idx, key, val) };
+            }
+        }
+        map.stitch_leaves(level, length);
+        map
+    }
+
+    /// Builds the internal levels of a new, empty map on top of its filled leaves (see
+    /// [alloc_leaves]), which contain `length` entries in ascending order.
+    pub(crate) fn stitch_leaves(&mut self, mut level: Vec<NodePtr<K, V, S>>, length: usize)
+    where
+        K: Separator,
+        S: Summary<K, V>,
+    {
+        let store = self.store;
+        let mut height = 0;
+        while level.len() > 1 {
+            let num_children = level.len();
+            let num_parents = num_children.div_ceil(M + 1);
+
+            // The separators are the first keys in each child's subtree, except parents' first
+            // children. Create them first, so if `Clone` *panic*s we drop the level's subtrees
+            // instead of leaking them.
+            let separators = catch_unwind(AssertUnwindSafe(|| {
+                (0..num_parents)
+                    .flat_map(|i| {
+                        let children = even_chunk(num_children, num_parents, i);
+                        &level[children.start + 1..children.end]
+                    })
+                    .map(|&child| unsafe {
+                        let mut first_leaf = child;
+                        for _ in 0..height {
+                            first_leaf = first_leaf.as_ref().edge(0);
+                        }
+                        first_leaf.as_ref().key(0).separator()
+                    })
+                    .collect::<Vec<_>>()
+            }));
+            let mut separators = match separators {
+                Ok(separators) => separators.into_iter(),
+                Err(err) => {
+                    for node in level {
+                        unsafe {
+                            drop_node_ptr(node, height, !K::ALIASED, &mut |n| store.dealloc(n))
+                        };
+                    }
+                    resume_unwind(err);
+                }
+            };
+
+            let mut children = level.into_iter();
+            level = Vec::with_capacity(num_parents);
+            for i in 0..num_parents {
+                let mut parent = store.alloc(Node::internal(!K::ALIASED));
+                for idx in 0..even_chunk(num_children, num_parents, i).len() as u16 {
+                    let mut child = children.next().unwrap();
+                    unsafe {
+                        child.as_mut().set_parent(parent, idx);
+                        match idx.checked_sub(1) {
+                            None => parent.as_mut().set_last_edge(child),
+                            Some(key_idx) => {
+                                let key = separators.next().unwrap();
+                                parent.as_mut().insert_edge(key_idx, true, key, child)
+                            }
+                        }
+                    }
+                }
+                unsafe { resummarize(parent, height + 1) };
+                level.push(parent);
+            }
+            height += 1;
+        }
+
+        self.root = level.pop();
+        self.height = height;
+        self.length = length;
+        self.owns_separators = !K::ALIASED;
+    }
+}
+
+impl<'store, K, V, C, S> BTreeMap<'store, K, V, C, S> {
+    /// Creates an empty `BTreeMap` whose keys are ordered by `cmp` instead of [Ord].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let mut map = BTreeMap::with_comparator_in(&store, |a: &i32, b: &i32| b.cmp(a));
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    /// assert_eq!(map.first_key_value(), Some((&2, &"b")));
+    /// ```
+    #[inline]
+    pub const fn with_comparator_in(store: &'store BTreeStore<K, V, S>, cmp: C) -> Self {
         Self {
             store,
             root: None,
             length: 0,
             height: 0,
+            cmp,
+            owns_separators: false,
             _p: PhantomData,
         }
     }
 
+    /// The comparator which orders the keys
+    #[inline]
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
     // region length
     /// Returns the number of elements in the map.
     #[inline]
@@ -93,18 +278,20 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     // region retrieval
     /// Whether the map contains the key
     #[inline]
-    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
         matches!(self.find(key), Find::At { .. })
     }
 
     /// Returns a reference to the value corresponding to the key.
     #[inline]
-    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V>
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
         match self.find(key) {
             Find::At { node, idx } => unsafe { Some(node.as_ref().val(idx)) },
@@ -112,25 +299,14 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         }
     }
 
-    /// Returns a mutable reference to the value corresponding to the key.
-    #[inline]
-    pub fn get_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
-    where
-        K: Borrow<Q>,
-    {
-        match self.find(key) {
-            Find::At { mut node, idx } => unsafe { Some(node.as_mut().val_mut(idx)) },
-            _ => None,
-        }
-    }
-
     /// Returns a reference to the equivalent key
     ///
     /// This is (only) useful when `Q` is a different type than `K`.
     #[inline]
-    pub fn get_key<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&K>
+    pub fn get_key<Q: ?Sized>(&self, key: &Q) -> Option<&K>
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
         match self.find(key) {
             Find::At { node, idx } => unsafe { Some(node.as_ref().key(idx)) },
@@ -142,9 +318,10 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     ///
     /// This is (only) useful when `Q` is a different type than `K`.
     #[inline]
-    pub fn get_key_value<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+    pub fn get_key_value<Q: ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
         match self.find(key) {
             Find::At { node, idx } => unsafe { Some(node.as_ref().key_val(idx)) },
@@ -152,20 +329,6 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         }
     }
 
-    /// Returns a reference to the equivalent key and mutable associated value
-    ///
-    /// This is (only) useful when `Q` is a different type than `K`.
-    #[inline]
-    pub fn get_key_value_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<(&K, &mut V)>
-    where
-        K: Borrow<Q>,
-    {
-        match self.find(key) {
-            Find::At { mut node, idx } => unsafe { Some(node.as_mut().key_val_mut(idx)) },
-            _ => None,
-        }
-    }
-
     /// Returns the first key and value
     #[inline]
     pub fn first_key_value(&self) -> Option<(&K, &V)> {
@@ -173,13 +336,6 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
             .map(|node| unsafe { node.as_ref().first_key_value() })
     }
 
-    /// Returns the first key and mutable value
-    #[inline]
-    pub fn first_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
-        self.first_leaf()
-            .map(|mut node| unsafe { node.as_mut().first_key_value_mut() })
-    }
-
     /// Returns the last key and value
     #[inline]
     pub fn last_key_value(&self) -> Option<(&K, &V)> {
@@ -187,116 +343,207 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
             .map(|node| unsafe { node.as_ref().last_key_value() })
     }
 
-    /// Returns the last key and mutable value
+    // endregion
+
+    // region summaries
+    /// Returns the summary of all entries, see [Summary].
     #[inline]
-    pub fn last_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
-        self.last_leaf()
-            .map(|mut node| unsafe { node.as_mut().last_key_value_mut() })
+    pub fn summary(&self) -> S
+    where
+        S: Summary<K, V>,
+    {
+        match self.root {
+            None => S::empty(),
+            Some(root) => unsafe { total(root, self.height) },
+        }
+    }
+
+    /// Returns the summary of the entries within the given range in `O(log n)`, by combining the
+    /// cached summaries of the subtrees between its ends. See [Summary].
+    pub fn aggregate<Q: ?Sized>(&self, bounds: impl RangeBounds<Q>) -> S
+    where
+        K: Borrow<Q>,
+        C: Comparator<Q>,
+        S: Summary<K, V>,
+    {
+        let Some(bounds) = self.node_bounds(bounds) else {
+            return S::empty();
+        };
+        let (mut left, left_idx) = bounds.start();
+        let (mut right, right_idx) = bounds.end();
+        unsafe {
+            if left.ptr_eq(&right) {
+                return match left_idx <= right_idx {
+                    true => fold_entries(left.as_ref(), left_idx..right_idx + 1),
+                    false => S::empty(),
+                };
+            }
+
+            // Climb from both ends until the paths meet, adding the subtrees right of the left path
+            // and left of the right path
+            let mut left_sum = fold_entries(left.as_ref(), left_idx..left.as_ref().len);
+            let mut right_sum = fold_entries(right.as_ref(), 0..right_idx + 1);
+            loop {
+                let (left_parent, left_idx) = left.as_ref().parent().unwrap();
+                let (right_parent, right_idx) = right.as_ref().parent().unwrap();
+                if left_parent.ptr_eq(&right_parent) {
+                    if left_idx > right_idx {
+                        // The range is inverted
+                        return S::empty();
+                    }
+                    let middle = fold_summaries(left_parent.as_ref(), left_idx + 1..right_idx);
+                    return S::combine(S::combine(left_sum, middle), right_sum);
+                }
+                let left_parent_len = left_parent.as_ref().len;
+                left_sum = S::combine(
+                    left_sum,
+                    fold_summaries(left_parent.as_ref(), left_idx + 1..left_parent_len + 1),
+                );
+                right_sum = S::combine(fold_summaries(right_parent.as_ref(), 0..right_idx), right_sum);
+                left = left_parent;
+                right = right_parent;
+            }
+        }
     }
     // endregion
 
     // region insertion and removal
     /// Inserts a key-value pair into the map.
+    ///
+    /// If the comparator or `Clone` *panic*s, the map is unchanged. If the store is at its
+    /// [limit](BTreeStore::with_limit), *panic*s with the map unchanged; see
+    /// [try_insert](Self::try_insert).
     #[inline]
     pub fn insert(&mut self, key: K, val: V) -> Option<V>
     where
-        K: Clone + Ord,
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
+    {
+        or_panic(self.try_insert(key, val))
+    }
+
+    /// Inserts a key-value pair into the map, unless the store is at its
+    /// [limit](BTreeStore::with_limit) and can't allocate the nodes it needs: then returns the
+    /// entry in a [CapacityError] and the map is unchanged.
+    ///
+    /// Replacing an existing key's value never allocates, so it always succeeds.
+    #[inline]
+    pub fn try_insert(&mut self, key: K, val: V) -> Result<Option<V>, CapacityError<K, V>>
+    where
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
     {
         match self.find(&key) {
             Find::NoRoot => {
-                self.insert_root(key, val);
-                None
+                self.insert_root(key, val)?;
+                Ok(None)
             }
             Find::Before { node, idx } => unsafe {
-                self.insert_before(key, val, node, idx);
-                None
+                self.insert_before(key, val, node, idx)?;
+                Ok(None)
+            },
+            Find::At { mut node, idx } => unsafe {
+                let val = node.as_mut().replace_val(idx, val);
+                resummarize_up(node, 0);
+                Ok(Some(val))
             },
-            Find::At { mut node, idx } => unsafe { Some(node.as_mut().replace_val(idx, val)) },
         }
     }
 
-    /// Get a reference to the value at the given key, or insert a new value if the key is not
-    /// present.
+    /// Inserts each entry like [insert](Self::insert) until one doesn't fit in the store's
+    /// [limit](BTreeStore::with_limit): then returns it in a [CapacityError]. The entries before
+    /// it stay inserted, and the ones after it aren't taken from the iterator.
     #[inline]
-    pub fn get_or_insert(&mut self, key: K, val: V) -> &mut V
+    pub fn try_extend(
+        &mut self,
+        iter: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<(), CapacityError<K, V>>
     where
-        K: Clone + Ord,
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
     {
-        match self.find(&key) {
-            Find::NoRoot => unsafe {
-                self.insert_root(key, val);
-                self.root.unwrap().as_mut().val_mut(0)
-            },
-            Find::Before { node, idx } => unsafe {
-                // Maybe could optimize into a single lookup...
-                self.insert_before(key.clone(), val, node, idx);
-                self.get_mut(&key).unwrap()
-            },
-            Find::At { mut node, idx } => unsafe { node.as_mut().val_mut(idx) },
+        for (key, val) in iter {
+            self.try_insert(key, val)?;
         }
+        Ok(())
     }
 
     /// Removes the equivalent key and returns the actual key and value, if present.
+    ///
+    /// If the comparator or `Clone` *panic*s, the map is unchanged. If dropping a separator
+    /// *panic*s, the entry is already removed, and is dropped while unwinding.
     #[inline]
-    pub fn remove_key_value<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+    pub fn remove_key_value<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
     where
-        K: Clone + Borrow<Q>,
+        K: Separator + Borrow<Q>,
+        C: Comparator<K> + Comparator<Q>,
+        S: Summary<K, V>,
     {
         match self.find(key) {
             Find::NoRoot | Find::Before { .. } => None,
-            Find::At { mut node, idx } => unsafe {
-                let (key, val) = node.as_mut().remove_val(idx);
-                self.post_removal(node);
-                Some((key, val))
-            },
+            Find::At { node, idx } => unsafe { Some(self.remove_at(node, idx)) },
         }
     }
 
     /// Removes the equivalent key and returns the value if present.
+    ///
+    /// *Panic*s leave the map like [remove_key_value](Self::remove_key_value).
     #[inline]
-    pub fn remove<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
     where
-        K: Clone + Borrow<Q>,
+        K: Separator + Borrow<Q>,
+        C: Comparator<K> + Comparator<Q>,
+        S: Summary<K, V>,
     {
         self.remove_key_value(key).map(|(_, val)| val)
     }
 
     /// Removes the first key and value as long as the map isn't empty
+    ///
+    /// *Panic*s leave the map like [remove_key_value](Self::remove_key_value).
     #[inline]
     pub fn pop_first(&mut self) -> Option<(K, V)>
     where
-        K: Clone,
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
     {
-        self.first_leaf().map(|mut node| unsafe {
-            let (key, val) = node.as_mut().remove_val(0);
-            self.post_removal(node);
-            (key, val)
-        })
+        self.first_leaf()
+            .map(|node| unsafe { self.remove_at(node, 0) })
     }
 
     /// Removes the last key and value as long as the map isn't empty
+    ///
+    /// *Panic*s leave the map like [remove_key_value](Self::remove_key_value).
     #[inline]
     pub fn pop_last(&mut self) -> Option<(K, V)>
     where
-        K: Clone,
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
     {
-        self.last_leaf().map(|mut node| unsafe {
-            let idx = node.as_ref().len - 1;
-            let (key, val) = node.as_mut().remove_val(idx);
-            self.post_removal(node);
-            (key, val)
-        })
+        self.last_leaf()
+            .map(|node| unsafe { self.remove_at(node, node.as_ref().len - 1) })
     }
 
     /// Clears the map, removing all key-value pairs.
+    ///
+    /// If a destructor *panic*s, the rest of the entries are still dropped, and the map is empty.
     #[inline]
     pub fn clear(&mut self) {
+        // Empty the map first, in case a key or value's destructor *panic*s
+        let height = mem::take(&mut self.height);
+        self.length = 0;
         if let Some(root) = self.root.take() {
             unsafe {
-                drop_node_ptr(root, self.height, &mut |n| self.store.dealloc(n));
+                drop_node_ptr(root, height, self.owns_separators, &mut |n| {
+                    self.store.dealloc(n)
+                });
             }
         }
-        self.length = 0;
     }
     // endregion
 
@@ -306,7 +553,8 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     ///
     /// Also, if the function `panic`s we always remove the key, so this is effectively a
     /// special-case of [`replace_with`](https://docs.rs/replace_with/latest/replace_with/) for the
-    /// map.
+    /// map. Other *panic*s leave the map like [insert](Self::insert) or
+    /// [remove_key_value](Self::remove_key_value).
     #[inline]
     pub fn update_and_return<R>(
         &mut self,
@@ -314,35 +562,42 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         update: impl FnOnce(Option<V>) -> (Option<V>, R),
     ) -> R
     where
-        K: Clone + Ord,
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
     {
         match self.find(&key) {
             Find::NoRoot => match update(None) {
                 (None, r) => r,
                 (Some(val), r) => {
-                    self.insert_root(key, val);
+                    or_panic(self.insert_root(key, val));
                     r
                 }
             },
             Find::At { mut node, idx } => unsafe {
+                // Create the separator before moving the value out, so if `Clone` or the comparator
+                // *panic*s, the entry is still in the map with its value
+                let separator = self.redistribution_separator(node);
                 match catch_unwind(AssertUnwindSafe(|| {
                     let val = node.as_mut().read_val(idx);
                     update(Some(val))
                 })) {
                     Err(err) => {
-                        let (_key, value) = node.as_mut().remove_val(idx);
+                        let (_key, value) = self.remove_at_with(node, idx, separator);
                         forget(value);
-                        self.post_removal(node);
                         resume_unwind(err);
                     }
                     Ok((None, r)) => {
-                        let (_key, value) = node.as_mut().remove_val(idx);
+                        let (_key, value) = self.remove_at_with(node, idx, separator);
                         forget(value);
-                        self.post_removal(node);
                         r
                     }
                     Ok((Some(val), r)) => {
                         node.as_mut().write_val(idx, val);
+                        resummarize_up(node, 0);
+                        if let Some(separator) = separator {
+                            drop_separator(separator);
+                        }
                         r
                     }
                 }
@@ -350,7 +605,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
             Find::Before { node, idx } => match update(None) {
                 (None, r) => r,
                 (Some(val), r) => unsafe {
-                    self.insert_before(key, val, node, idx);
+                    or_panic(self.insert_before(key, val, node, idx));
                     r
                 },
             },
@@ -360,36 +615,56 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     /// Transforms the value at the given key, inserting if we go from `None` to `Some` and removing
     /// if we go from `Some` to `None`.
     ///
-    /// Also, if the function `panic`s we always remove the key, so this is effectively a
-    /// special-case of [`replace_with`](https://docs.rs/replace_with/latest/replace_with/) for the
-    /// map.
+    /// Also, if the function `panic`s we always remove the key, like
+    /// [update_and_return](Self::update_and_return).
     #[inline]
     pub fn update(&mut self, key: K, update: impl FnOnce(Option<V>) -> Option<V>)
     where
-        K: Clone + Ord,
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
     {
         self.update_and_return(key, |val| (update(val), ()))
     }
 
     /// Validates the map, *panic*ing if it is invalid. Specifically, we check that the number of
-    /// entries in each node is within the b-tree invariant bounds, and that the keys are in order.
+    /// entries in each node is within the b-tree invariant bounds, that the keys are in order, and
+    /// that the cached summaries are up-to-date.
     ///
     /// Ideally, this should always be a no-op.
     #[inline]
     pub fn validate(&self)
     where
-        K: Debug + Ord,
+        K: Debug,
+        V: Debug,
+        C: Comparator<K>,
+        S: Summary<K, V> + PartialEq,
+    {
+        self.validate_by(Some(&self.cmp))
+    }
+
+    /// [BTreeMap::validate], but only checks that the keys are in order if given a comparator (e.g.
+    /// positional trees' keys are all `()`)
+    pub(crate) fn validate_by(&self, cmp: Option<&impl Comparator<K>>)
+    where
+        K: Debug,
         V: Debug,
+        S: Summary<K, V> + PartialEq,
     {
-        unsafe fn validate_node<K: Debug + Ord, V: Debug>(
+        /// Last key and leaf before a node, if any
+        type Prev<K, V, S> = (Option<NonNull<K>>, Option<NodePtr<K, V, S>>);
+        /// Last key and leaf in a node
+        type Last<K, V, S> = (NonNull<K>, NodePtr<K, V, S>);
+        unsafe fn validate_node<K: Debug, V: Debug, S: Summary<K, V> + PartialEq>(
+            cmp: Option<&impl Comparator<K>>,
             errors: &mut Vec<String>,
-            node: NodePtr<K, V>,
-            parent: Option<(NodePtr<K, V>, u16)>,
+            node: NodePtr<K, V, S>,
+            parent: Option<(NodePtr<K, V, S>, u16)>,
             height: usize,
-            (mut prev_key, mut prev_leaf): (Option<NonNull<K>>, Option<NodePtr<K, V>>),
-        ) -> (usize, (NonNull<K>, NodePtr<K, V>)) {
+            (mut prev_key, mut prev_leaf): Prev<K, V, S>,
+        ) -> (usize, Last<K, V, S>) {
             let errors = RefCell::new(errors);
-            let assert2 = |node: NodePtr<K, V>, cond: bool, msg: &str| {
+            let assert2 = |node: NodePtr<K, V, S>, cond: bool, msg: &str| {
                 if !cond {
                     (*errors.borrow_mut()).push(format!("{:X?} {}", node.as_ptr(), msg))
                 }
@@ -426,12 +701,12 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                 for i in 0..node.len {
                     let key = node.key(i);
 
-                    if let Some(prev_key) = prev_key {
+                    if let (Some(cmp), Some(prev_key)) = (cmp, prev_key) {
                         let prev_key = prev_key.as_ref();
                         assert(
                             match i {
-                                0 => key >= prev_key,
-                                _ => key > prev_key,
+                                0 => cmp.cmp(key, prev_key) != Ordering::Less,
+                                _ => cmp.cmp(key, prev_key) == Ordering::Greater,
                             },
                             &format!("key {} is out of order", i),
                         );
@@ -447,9 +722,12 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                     if let Some(ki) = i.checked_sub(1) {
                         let key = node.key(ki);
 
-                        if let Some(prev_key) = prev_key {
+                        if let (Some(cmp), Some(prev_key)) = (cmp, prev_key) {
                             let prev_key = prev_key.as_ref();
-                            assert(key > prev_key, &format!("key {} is out of order", i));
+                            assert(
+                                cmp.cmp(key, prev_key) == Ordering::Greater,
+                                &format!("key {} is out of order", i),
+                            );
                         }
 
                         prev_key = Some(NonNull::from(key));
@@ -474,6 +752,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                     }
 
                     let (child_len, (last_key, last_leaf)) = validate_node(
+                        cmp,
                         *errors.borrow_mut(),
                         child,
                         Some((node_ptr, i)),
@@ -483,6 +762,13 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                     len += child_len;
                     prev_key = Some(last_key);
                     prev_leaf = Some(last_leaf);
+
+                    if size_of::<S>() != 0 {
+                        assert(
+                            node.summary(i) == total(child, height - 1),
+                            &format!("summary {} is stale", i),
+                        );
+                    }
                 }
                 (len, (prev_key.unwrap(), prev_leaf.unwrap()))
             }
@@ -490,7 +776,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         let mut errors = Vec::new();
         if let Some(root) = self.root {
             let (len, (_last_key, last_leaf)) =
-                unsafe { validate_node(&mut errors, root, None, self.height, (None, None)) };
+                unsafe { validate_node(cmp, &mut errors, root, None, self.height, (None, None)) };
             if len != self.length {
                 errors.push(String::from("tree length isn't correct"))
             };
@@ -512,9 +798,9 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
         K: Debug,
         V: Debug,
     {
-        unsafe fn print_node<K: Debug, V: Debug>(
+        unsafe fn print_node<K: Debug, V: Debug, S>(
             f: &mut Formatter<'_>,
-            node: NodePtr<K, V>,
+            node: NodePtr<K, V, S>,
             max_height: usize,
             height: usize,
         ) -> std::fmt::Result {
@@ -573,88 +859,58 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
     // region iteration
     /// Iterates over the map's key-value pairs in order.
     #[inline]
-    pub fn iter(&self) -> Iter<'_, K, V> {
+    pub fn iter(&self) -> Iter<'_, K, V, S> {
         Iter::new(self)
     }
 
-    /// Iterates over the map's key-value pairs in order. Values are mutable
-    #[inline]
-    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
-        IterMut::new(self)
-    }
-
     /// Iterates over the map's keys in order.
     #[inline]
-    pub fn keys(&self) -> Keys<'_, K, V> {
+    pub fn keys(&self) -> Keys<'_, K, V, S> {
         Keys(self.iter())
     }
 
     /// Iterates over the map's values in order.
     #[inline]
-    pub fn values(&self) -> Values<'_, K, V> {
+    pub fn values(&self) -> Values<'_, K, V, S> {
         Values(self.iter())
     }
 
-    /// Iterates over the map's values in order. Values are mutable
-    #[inline]
-    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
-        ValuesMut(self.iter_mut())
-    }
-
     /// Iterates over the map's key-value pairs in order, within the given range.
     #[inline]
-    pub fn range<Q: Ord + ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Range<'_, K, V>
+    pub fn range<Q: ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Range<'_, K, V, S>
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
         Range::new(self, bounds)
     }
 
-    /// Iterates over the map's key-value pairs in order, within the given range.. Values are mutable
-    #[inline]
-    pub fn range_mut<Q: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> RangeMut<'_, K, V>
-    where
-        K: Borrow<Q>,
-    {
-        RangeMut::new(self, bounds)
-    }
-
     /// Iterates over the map's keys in order, within the given range.
     #[inline]
-    pub fn range_keys<Q: Ord + ?Sized>(
+    pub fn range_keys<Q: ?Sized>(
         &self,
         bounds: impl RangeBounds<Q>,
     ) -> impl Iterator<Item = &K> + '_
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
         self.range(bounds).map(|(k, _)| k)
     }
 
     /// Iterates over the map's values in order, within the given range.
     #[inline]
-    pub fn range_values<Q: Ord + ?Sized>(
+    pub fn range_values<Q: ?Sized>(
         &self,
         bounds: impl RangeBounds<Q>,
     ) -> impl Iterator<Item = &V> + '_
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
         self.range(bounds).map(|(_, v)| v)
     }
 
-    /// Iterates over the map's values in order, within the given range. Values are mutable
-    #[inline]
-    pub fn range_values_mut<Q: Ord + ?Sized>(
-        &mut self,
-        bounds: impl RangeBounds<Q>,
-    ) -> impl Iterator<Item = &mut V> + '_
-    where
-        K: Borrow<Q>,
-    {
-        self.range_mut(bounds).map(|(_, v)| v)
-    }
-
     // /// Drains elements.
     // #[inline]
     // pub fn drain(&mut self) -> Drain<'_, K, V> {
@@ -675,7 +931,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
 
     // /// Drains elements within the given range
     // #[inline]
-    // pub fn drain_range<Q: Ord + ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> DrainRange<'_, K, V> where K: Borrow<Q> {
+    // pub fn drain_range<Q: ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> DrainRange<'_, K, V> where K: Borrow<Q> {
     //     DrainRange::new(self, bounds)
     // }
 
@@ -694,194 +950,346 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
 
     // region b-tree misc
     #[inline]
-    fn first_leaf(&self) -> Option<NodePtr<K, V>> {
-        let mut node = self.root?;
-        for _ in 0..self.height {
-            node = unsafe { node.as_ref().edge(0) };
-        }
-        Some(node)
+    pub(crate) fn first_leaf(&self) -> Option<NodePtr<K, V, S>> {
+        search::first_leaf(self)
     }
 
     #[inline]
-    fn last_leaf(&self) -> Option<NodePtr<K, V>> {
-        let mut node = self.root?;
-        for _ in 0..self.height {
-            node = unsafe { node.as_ref().edge(node.as_ref().len) };
-        }
-        Some(node)
+    pub(crate) fn last_leaf(&self) -> Option<NodePtr<K, V, S>> {
+        search::last_leaf(self)
     }
 
     #[inline]
-    fn find<Q: Ord + ?Sized>(&self, key: &Q) -> Find<K, V>
+    fn find<Q: ?Sized>(&self, key: &Q) -> Find<NodePtr<K, V, S>>
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
-        let Some(mut node) = self.root else {
-            return Find::NoRoot
-        };
-        let mut height = self.height;
-        loop {
-            match unsafe { node.as_ref().keys() }.binary_search_by(|k| k.borrow().cmp(key)) {
-                Ok(idx) => {
-                    let idx = idx as u16;
-                    if height == 0 {
-                        break Find::At { node, idx };
-                    }
-                    height -= 1;
-                    node = unsafe { node.as_ref().edge(idx + 1) }
-                }
-                Err(idx) => {
-                    let idx = idx as u16;
-                    if height == 0 {
-                        break Find::Before { node, idx };
-                    }
-                    height -= 1;
-                    node = unsafe { node.as_ref().edge(idx) }
-                }
-            }
-        }
+        search::find(self, &self.cmp, key)
     }
 
     #[inline]
-    fn node_bounds<Q: Ord + ?Sized>(&self, bounds: impl RangeBounds<Q>) -> Option<NodeBounds<K, V>>
+    pub(crate) fn node_bounds<Q: ?Sized>(
+        &self,
+        bounds: impl RangeBounds<Q>,
+    ) -> Option<NodeBounds<NodePtr<K, V, S>>>
     where
         K: Borrow<Q>,
+        C: Comparator<Q>,
     {
-        let (start_node, start_index) = match bounds.start_bound() {
-            Bound::Included(bound) => match self.find(bound) {
-                Find::NoRoot => return None,
-                Find::Before { node, idx } => unsafe { normalize_address(node, idx) }?,
-                Find::At { node, idx } => (node, idx),
-            },
-            Bound::Excluded(bound) => match self.find(bound) {
-                Find::NoRoot => return None,
-                // normalize_address handles if idx == len, which means we are past this node and
-                // may be at the end.
-                Find::Before { node, idx } => unsafe { normalize_address(node, idx) }?,
-                Find::At { node, idx } => unsafe { address_after(node, idx) }?,
-            },
-            Bound::Unbounded => (self.first_leaf()?, 0),
-        };
-        let (end_node, end_index) = match bounds.end_bound() {
-            Bound::Included(bound) => match self.find(bound) {
-                Find::NoRoot => return None,
-                Find::Before { node, idx } => unsafe { address_before(node, idx) }?,
-                Find::At { node, idx } => (node, idx),
-            },
-            Bound::Excluded(bound) => match self.find(bound) {
-                Find::NoRoot => return None,
-                Find::Before { node, idx } | Find::At { node, idx } => {
-                    unsafe { address_before(node, idx) }?
-                }
-            },
-            Bound::Unbounded => self
-                .last_leaf()
-                .map(|leaf| unsafe { (leaf, leaf.as_ref().len - 1) })?,
-        };
+        search::node_bounds(self, &self.cmp, bounds)
+    }
 
-        // Check for overlap (only need to check if address_after(start) == end)
-        if (start_node.ptr_eq(&end_node) && start_index == end_index + 1)
-            || (start_index == 0 && unsafe { start_node.as_ref().prev() }.ptr_eq(&Some(end_node)))
-        {
-            return None;
-        }
+    /// Iterates the entries between bounds which order keys relative to them (see
+    /// [search::find_by]), e.g. all entries in a group of equivalent keys.
+    #[inline]
+    pub(crate) fn range_by(
+        &self,
+        start: Bound<impl FnMut(&K) -> Ordering>,
+        end: Bound<impl FnMut(&K) -> Ordering>,
+    ) -> Range<'_, K, V, S> {
+        Range::from_bounds(search::node_bounds_by(self, start, end))
+    }
 
-        // Actually create
-        Some(NodeBounds {
-            start_node,
-            end_node,
-            start_index,
-            end_index,
-        })
+    /// Removes the entry whose key `cmp` returns [Ordering::Equal] for, if present.
+    #[inline]
+    pub(crate) fn remove_by(&mut self, cmp: impl FnMut(&K) -> Ordering) -> Option<(K, V)>
+    where
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
+    {
+        match search::find_by(self, cmp) {
+            Find::NoRoot | Find::Before { .. } => None,
+            Find::At { node, idx } => unsafe { Some(self.remove_at(node, idx)) },
+        }
     }
 
     #[inline]
-    fn insert_root(&mut self, key: K, val: V) {
+    fn insert_root(&mut self, key: K, val: V) -> Result<(), CapacityError<K, V>> {
         debug_assert_eq!(self.length, 0);
+        if !self.store.has_room(1) {
+            return Err(CapacityError { key, value: val });
+        }
         let mut root = Node::leaf();
         unsafe {
             root.insert_val(0, key, val);
         }
         self.root = Some(self.store.alloc(root));
         self.length += 1;
+        Ok(())
     }
 
+    /// Inserts before `idx` in the leaf `node`, splitting it and its ancestors if they overflow,
+    /// and returns the leaf and index the entry ended up at. If the store can't fit the new nodes,
+    /// returns the entry and the map is unchanged.
     #[inline]
-    unsafe fn insert_before(&mut self, mut key: K, val: V, mut node: NodePtr<K, V>, idx: u16)
+    unsafe fn insert_before(
+        &mut self,
+        key: K,
+        val: V,
+        mut node: NodePtr<K, V, S>,
+        idx: u16,
+    ) -> Result<Address<K, V, S>, CapacityError<K, V>>
     where
-        K: Clone,
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
     {
-        if (node.as_ref().len as usize) < M {
+        let address = if (node.as_ref().len as usize) < M {
             node.as_mut().insert_val(idx, key, val);
+            resummarize_up(node, 0);
+            (node, idx)
         } else {
+            if !self.store.has_room(nodes_to_split(node)) {
+                return Err(CapacityError { key, value: val });
+            }
             // Rebalance (overflow)
+            self.owns_separators = !K::ALIASED;
 
-            // First split
-            // `key` gets replaced with the "split" (median) key, and `node` gets replaced with the
-            // left node
-            let mut right = self
-                .store
-                .alloc(node.as_mut().split_leaf(idx, &mut key, val));
+            // Create the separator before splitting, so if `Clone` or the comparator *panic*s,
+            // the map is unchanged
+            let (prev, next) = node.as_ref().split_leaf_keys(idx, &key);
+            let separator = self.separator(prev, next);
+
+            // Then split, where `node` becomes the left node
+            let mut right = self.store.alloc(node.as_mut().split_leaf(idx, key, val));
             node.as_mut().set_next(Some(right));
             right.as_mut().set_prev(Some(node));
             if let Some(mut right_next) = right.as_ref().next() {
                 right_next.as_mut().set_prev(Some(right));
             }
+            self.insert_split(separator, node, right, 0);
 
-            loop {
-                let Some((mut parent, idx)) = node.as_ref().parent() else {
-                    // At root: create a new root with the split key, left, and right nodes
-                    self.height += 1;
-                    let mut left = node;
-                    let mut root = self.store.alloc(Node::internal());
-                    left.as_mut().set_parent(root, 0);
-                    // Has to be before insert_edge, otherwise we try to modify a deallocated edge,
-                    // because the tree has 0 edges but insert_edge always expects at least 1.
-                    // Furthermore, we need correct parent_idx, which is why we set both to 0.
-                    right.as_mut().set_parent(root, 0);
-                    root.as_mut().set_last_edge(right);
-                    root.as_mut().insert_edge(0, false, key, left);
-                    self.root = Some(root);
-                    break
-                };
-
-                // Insert split key and right into parent. left is already in parent at idx, so
-                // insert key at idx and right at idx + 1. We must handle the case where the parent
-                // overflows too...
-                right.as_mut().set_parent(parent, idx + 1);
-                if (parent.as_ref().len as usize) < M {
-                    // The parent won't overflow, actually insert into parent
-                    parent.as_mut().insert_edge(idx, true, key, right);
-                    break;
-                }
-                // The parent will overflow too, so we split the parent when inserting idx/key/right
-                // split_internal will replace key with the split key and node with the left node,
-                // and we re-assign right to the right node (we don't just pass as a &mut like we do
-                // with key because it must be allocated). Then insert the new internal parent-right
-                // node in its parent, and so on, until we either find a suitable parent or reach
-                // the root.
-                node = parent;
-                right = self
-                    .store
-                    .alloc(node.as_mut().split_internal(idx, &mut key, right));
-                for right_child in right.as_mut().edges_mut() {
-                    right_child.as_mut().parent = Some(right);
-                }
+            // `split_leaf` keeps the first half of the entries, including the new one, on the left
+            let median = M as u16 / 2;
+            if idx < median {
+                (node, idx)
+            } else {
+                (right, idx - median)
             }
-        }
+        };
         self.length += 1;
+        Ok(address)
+    }
+
+    /// Inserts `right` after `node` in `node`'s parent with the separator `key`, splitting the
+    /// parent and its ancestors while they overflow. Both nodes are at `height` and their summaries
+    /// are up-to-date.
+    #[inline]
+    unsafe fn insert_split(
+        &mut self,
+        mut key: K,
+        mut node: NodePtr<K, V, S>,
+        mut right: NodePtr<K, V, S>,
+        mut height: usize,
+    ) where
+        S: Summary<K, V>,
+    {
+        loop {
+            let Some((mut parent, idx)) = node.as_ref().parent() else {
+                // At root: create a new root with the split key, left, and right nodes
+                self.height += 1;
+                let mut left = node;
+                let mut root = self.store.alloc(Node::internal(self.owns_separators));
+                left.as_mut().set_parent(root, 0);
+                // Has to be before insert_edge, otherwise we try to modify a deallocated edge,
+                // because the tree has 0 edges but insert_edge always expects at least 1.
+                // Furthermore, we need correct parent_idx, which is why we set both to 0.
+                right.as_mut().set_parent(root, 0);
+                root.as_mut().set_last_edge(right);
+                root.as_mut().insert_edge(0, false, key, left);
+                resummarize(root, self.height);
+                self.root = Some(root);
+                break
+            };
+
+            // Insert split key and right into parent. left is already in parent at idx, so
+            // insert key at idx and right at idx + 1. We must handle the case where the parent
+            // overflows too...
+            right.as_mut().set_parent(parent, idx + 1);
+            if (parent.as_ref().len as usize) < M {
+                // The parent won't overflow, actually insert into parent
+                parent.as_mut().insert_edge(idx, true, key, right);
+                resummarize(parent, height + 1);
+                resummarize_up(parent, height + 1);
+                break;
+            }
+            // The parent will overflow too, so we split the parent when inserting idx/key/right
+            // split_internal will replace key with the split key and node with the left node,
+            // and we re-assign right to the right node (we don't just pass as a &mut like we do
+            // with key because it must be allocated). Then insert the new internal parent-right
+            // node in its parent, and so on, until we either find a suitable parent or reach
+            // the root.
+            node = parent;
+            right = self
+                .store
+                .alloc(node.as_mut().split_internal(idx, &mut key, right));
+            for right_child in right.as_mut().edges_mut() {
+                right_child.as_mut().parent = Some(right);
+            }
+            height += 1;
+            resummarize(node, height);
+            resummarize(right, height);
+        }
+    }
+
+    /// Creates the separator between adjacent leaves, where `prev` is the left leaf's last key and
+    /// `next` is the right leaf's first key
+    #[inline]
+    unsafe fn separator(&self, prev: &K, next: &K) -> K
+    where
+        K: Separator,
+        C: Comparator<K>,
+    {
+        self.short_separator(prev, next)
+            .unwrap_or_else(|| next.separator())
     }
 
+    /// The comparator's separator between adjacent leaves, if it shortens them
     #[inline]
-    unsafe fn post_removal(&mut self, mut node: NodePtr<K, V>)
+    fn short_separator(&self, prev: &K, next: &K) -> Option<K>
     where
-        K: Clone,
+        K: Separator,
+        C: Comparator<K>,
     {
+        // Bitwise copies must be exactly the first key, see `remove_at`
+        match K::ALIASED {
+            false => self.cmp.separator(prev, next),
+            true => None,
+        }
+    }
+
+    /// Removes the entry at the address and rebalances
+    #[inline]
+    unsafe fn remove_at(&mut self, node: NodePtr<K, V, S>, idx: u16) -> (K, V)
+    where
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
+    {
+        let separator = self.redistribution_separator(node);
+        self.remove_at_with(node, idx, separator)
+    }
+
+    /// [Self::remove_at] with the [redistribution separator](Self::redistribution_separator), if
+    /// it was created in advance
+    #[inline]
+    unsafe fn remove_at_with(
+        &mut self,
+        mut node: NodePtr<K, V, S>,
+        idx: u16,
+        separator: Option<K>,
+    ) -> (K, V)
+    where
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
+    {
+        let (key, val) = node.as_mut().remove_val(idx);
+        if K::ALIASED && idx == 0 && node.as_ref().len > 0 {
+            // The separator which copies the removed key would dangle, so replace it with a copy of
+            // the new first key in its subtree
+            let mut child = node;
+            while let Some((mut parent, parent_idx)) = child.as_ref().parent() {
+                if parent_idx > 0 {
+                    let separator = node.as_ref().key(0).separator();
+                    drop_separator(parent.as_mut().replace_key(parent_idx - 1, separator));
+                    break;
+                }
+                child = parent;
+            }
+        }
         self.length -= 1;
+        self.rebalance_with(node, 0, separator);
+        (key, val)
+    }
 
-        // Rebalance (underflow)
-        let mut is_leaf = true;
+    /// The separator [Self::rebalance] needs if removing an entry from the leaf makes it underflow
+    /// and it's redistributed with a sibling. This is created before removing, so if `Clone` or the
+    /// comparator *panic*s, the map is unchanged.
+    #[inline]
+    unsafe fn redistribution_separator(&self, leaf: NodePtr<K, V, S>) -> Option<K>
+    where
+        K: Separator,
+        C: Comparator<K>,
+    {
+        if leaf.as_ref().len as usize > M / 2 {
+            return None;
+        }
+        let (parent, idx) = leaf.as_ref().parent()?;
+        if idx > 0 {
+            let prev = parent.as_ref().edge(idx - 1);
+            let prev_len = prev.as_ref().len;
+            if (prev_len as usize) > M / 2 {
+                let moved = prev.as_ref().key(prev_len - 1);
+                return Some(self.separator(prev.as_ref().key(prev_len - 2), moved));
+            }
+        }
+        if idx < parent.as_ref().len {
+            let next = parent.as_ref().edge(idx + 1);
+            if (next.as_ref().len as usize) > M / 2 {
+                return Some(self.separator(next.as_ref().key(0), next.as_ref().key(1)));
+            }
+        }
+        None
+    }
+
+    /// Redistributes or merges `node` with a sibling if it underflows (by at most 1 entry), and so
+    /// on for its ancestors. `node`'s own summaries are up-to-date, but its ancestors' aren't.
+    #[inline]
+    unsafe fn rebalance(&mut self, node: NodePtr<K, V, S>, height: usize)
+    where
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
+    {
+        self.rebalance_with(node, height, None)
+    }
+
+    /// [Self::rebalance] with the [redistribution separator](Self::redistribution_separator), if
+    /// it was created in advance
+    #[inline]
+    unsafe fn rebalance_with(&mut self, node: NodePtr<K, V, S>, height: usize, separator: Option<K>)
+    where
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
+    {
+        let mut separator = separator.map(ManuallyDrop::new);
+        let removed_separator = self.rebalance_nodes(node, height, &mut separator);
+        // Separators are dropped last, so if their destructor *panic*s the map is valid. Both are
+        // dropped even if the first *panic*s
+        if K::ALIASED {
+            return;
+        }
+        let mut panic = None;
+        for mut separator in [removed_separator, separator].into_iter().flatten() {
+            drop_catching(&mut *separator, &mut panic);
+        }
+        if let Some(panic) = panic {
+            resume_unwind(panic);
+        }
+    }
+
+    /// [Self::rebalance], taking the redistribution separator if it's used, and returning the
+    /// separator which was replaced or removed, if any
+    #[inline]
+    unsafe fn rebalance_nodes(
+        &mut self,
+        mut node: NodePtr<K, V, S>,
+        mut height: usize,
+        separator: &mut Option<ManuallyDrop<K>>,
+    ) -> Option<ManuallyDrop<K>>
+    where
+        K: Separator,
+        C: Comparator<K>,
+        S: Summary<K, V>,
+    {
+        // Only leaves' separators are replaced or removed; internal nodes' move
+        let mut removed_separator = None;
         while (node.as_ref().len as usize) < M / 2 {
+            let is_leaf = height == 0;
             let Some((mut parent, idx)) = node.as_ref().parent() else {
                 // Node is root. Root node can have less than M < 2 children
                 if is_leaf {
@@ -889,6 +1297,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                     // is empty.
                     if node.as_ref().len == 0 {
                         self.root = None;
+                        self.store.dealloc(node);
                     }
                 } else if node.as_ref().len < 1 {
                     // If the root is internal, it can have min 1 child (= 2 edges). Otherwise, the
@@ -898,7 +1307,7 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                     self.store.dealloc(node);
                     self.root.as_mut().unwrap().as_mut().clear_parent();
                 }
-                break
+                return removed_separator;
             };
 
             // Try to redistribute with prev sibling
@@ -906,133 +1315,863 @@ impl<'store, K, V> BTreeMap<'store, K, V> {
                 let mut prev = parent.as_ref().edge(idx - 1);
                 if (prev.as_ref().len as usize) > M / 2 {
                     if is_leaf {
-                        let (key, val) = prev.as_mut().remove_val(prev.as_ref().len - 1);
-                        node.as_mut().insert_val(0, key.clone(), val);
-                        parent.as_mut().replace_key(idx - 1, key);
+                        // The separator is between prev's last 2 keys, which it will split
+                        let prev_len = prev.as_ref().len;
+                        let separator = match separator.take() {
+                            Some(separator) => ManuallyDrop::into_inner(separator),
+                            None => {
+                                let moved = prev.as_ref().key(prev_len - 1);
+                                self.separator(prev.as_ref().key(prev_len - 2), moved)
+                            }
+                        };
+                        let (key, val) = prev.as_mut().remove_val(prev_len - 1);
+                        node.as_mut().insert_val(0, key, val);
+                        let separator = parent.as_mut().replace_key(idx - 1, separator);
+                        removed_separator = Some(ManuallyDrop::new(separator));
                     } else {
                         let (key, mut edge) = prev.as_mut().remove_last_edge();
                         let key = parent.as_mut().replace_key(idx - 1, key);
                         edge.as_mut().set_parent(node, 0);
                         node.as_mut().insert_edge(0, false, key, edge);
+                        resummarize(prev, height);
+                        resummarize(node, height);
+                    }
+                    node = parent;
+                    height += 1;
+                    resummarize(node, height);
+                    break;
+                }
+            }
+
+            // Try to redistribute with next sibling
+            if idx < parent.as_ref().len {
+                let mut next = parent.as_ref().edge(idx + 1);
+                if (next.as_ref().len as usize) > M / 2 {
+                    if is_leaf {
+                        // The separator is between next's first 2 keys, which it will split
+                        let separator = match separator.take() {
+                            Some(separator) => ManuallyDrop::into_inner(separator),
+                            None => self.separator(next.as_ref().key(0), next.as_ref().key(1)),
+                        };
+                        let (key, val) = next.as_mut().remove_val(0);
+                        node.as_mut().insert_val(node.as_ref().len, key, val);
+                        let separator = parent.as_mut().replace_key(idx, separator);
+                        removed_separator = Some(ManuallyDrop::new(separator));
+                    } else {
+                        let (key, mut edge) = next.as_mut().remove_edge(0, false);
+                        let key = parent.as_mut().replace_key(idx, key);
+                        let len = node.as_ref().len;
+                        edge.as_mut().set_parent(node, len + 1);
+                        node.as_mut().insert_edge(len, true, key, edge);
+                        resummarize(next, height);
+                        resummarize(node, height);
+                    }
+                    node = parent;
+                    height += 1;
+                    resummarize(node, height);
+                    break;
+                }
+            }
+
+            // Merge with prev sibling or next sibling. We prioritize prev just because, but
+            // must choose next if idx == 0
+            if idx > 0 {
+                let mut prev = parent.as_mut().edge(idx - 1);
+                if is_leaf {
+                    node.as_mut().merge_prev_leaf(prev.as_mut());
+                    if let Some(mut new_prev) = node.as_ref().prev() {
+                        new_prev.as_mut().set_next(Some(node));
+                    }
+                } else {
+                    // Moved from the parent, which forgets it below
+                    let key = ptr::read(parent.as_ref().key(idx - 1));
+                    for child in prev.as_mut().edges_mut() {
+                        child.as_mut().parent = Some(node);
+                    }
+                    node.as_mut().merge_prev_internal(key, prev.as_mut());
+                    resummarize(node, height);
+                }
+
+                // Dealloc and remove absorbed (empty) node and fix indices of the nodes
+                // after
+                let (key, edge) = parent.as_mut().remove_edge(idx - 1, false);
+                if is_leaf {
+                    removed_separator = Some(ManuallyDrop::new(key));
+                } else {
+                    forget(key);
+                }
+                debug_assert!(edge.ptr_eq(&prev));
+                self.store.dealloc(prev);
+            } else {
+                let mut next = parent.as_mut().edge(idx + 1);
+                if is_leaf {
+                    node.as_mut().merge_next_leaf(next.as_mut());
+                    if let Some(mut new_next) = node.as_ref().next() {
+                        new_next.as_mut().set_prev(Some(node));
+                    }
+                } else {
+                    // Moved from the parent, which forgets it below
+                    let key = ptr::read(parent.as_ref().key(idx));
+                    for child in next.as_mut().edges_mut() {
+                        child.as_mut().parent = Some(node);
+                    }
+                    node.as_mut().merge_next_internal(key, next.as_mut());
+                    resummarize(node, height);
+                }
+
+                // Dealloc and remove absorbed (empty) node and fix indices of the nodes
+                // after
+                let (key, edge) = parent.as_mut().remove_edge(idx, true);
+                if is_leaf {
+                    removed_separator = Some(ManuallyDrop::new(key));
+                } else {
+                    forget(key);
+                }
+                debug_assert!(edge.ptr_eq(&next));
+                self.store.dealloc(next);
+            }
+
+            // Since we merged, we may now have to redistribute or merge the parent since it
+            // has 1 less child
+            node = parent;
+            height += 1;
+            resummarize(node, height);
+        }
+        resummarize_up(node, height);
+        removed_separator
+    }
+    // endregion
+}
+
+/// Prefix scans over composite keys, which must be ordered lexicographically by [Ord]. See
+/// [PrefixKey].
+impl<'store, K, V, S> BTreeMap<'store, K, V, OrdComparator, S> {
+    /// Iterates over the entries whose keys start with the prefix, in order.
+    #[inline]
+    pub fn prefix<P: ?Sized>(&self, prefix: &P) -> Range<'_, K, V, S>
+    where
+        K: PrefixKey<P>,
+    {
+        self.range_by(
+            Bound::Included(|k: &K| k.cmp_prefix(prefix).then(Ordering::Greater)),
+            Bound::Included(|k: &K| k.cmp_prefix(prefix).then(Ordering::Less)),
+        )
+    }
+
+    /// Iterates over the entries whose keys start with the prefix and whose next component is in
+    /// the range, in order.
+    #[inline]
+    pub fn range_prefix<P: ?Sized>(
+        &self,
+        prefix: &P,
+        bounds: impl RangeBounds<K::Next>,
+    ) -> Range<'_, K, V, S>
+    where
+        K: PrefixKey<P>,
+    {
+        if is_inverted(&bounds) {
+            return Range::from_bounds(None);
+        }
+        // Neither function returns `Equal`, so each finds the partition point
+        let start = bounds.start_bound();
+        let end = bounds.end_bound();
+        self.range_by(
+            Bound::Included(|k: &K| {
+                k.cmp_prefix(prefix).then_with(|| match start {
+                    Bound::Included(start) => k.next_component().cmp(start).then(Ordering::Greater),
+                    Bound::Excluded(start) => k.next_component().cmp(start).then(Ordering::Less),
+                    Bound::Unbounded => Ordering::Greater,
+                })
+            }),
+            Bound::Included(|k: &K| {
+                k.cmp_prefix(prefix).then_with(|| match end {
+                    Bound::Included(end) => k.next_component().cmp(end).then(Ordering::Less),
+                    Bound::Excluded(end) => k.next_component().cmp(end).then(Ordering::Greater),
+                    Bound::Unbounded => Ordering::Less,
+                })
+            }),
+        )
+    }
+}
+
+/// Prefix searches over string and byte-string keys. See [ByteKey].
+impl<'store, K: ByteKey, V, C: BytewiseComparator, S> BTreeMap<'store, K, V, C, S> {
+    /// Iterates over the entries whose keys start with the prefix, in order.
+    #[inline]
+    pub fn starts_with(&self, prefix: impl AsRef<[u8]>) -> Range<'_, K, V, S> {
+        let prefix = prefix.as_ref();
+        self.range_by(
+            Bound::Included(|k: &K| cmp_byte_prefix(k.key_bytes(), prefix).then(Ordering::Greater)),
+            Bound::Included(|k: &K| cmp_byte_prefix(k.key_bytes(), prefix).then(Ordering::Less)),
+        )
+    }
+
+    /// Returns the entry with the longest key which is a prefix of `query`, if any, e.g. the most
+    /// specific route for a path.
+    ///
+    /// Each descent finds the greatest key before `query`. If that key isn't a prefix, only
+    /// shorter prefixes than the bytes they share can match, so we search again for those.
+    pub fn longest_prefix_match(&self, query: impl AsRef<[u8]>) -> Option<(&K, &V)> {
+        let mut query = query.as_ref();
+        loop {
+            let (key, val) = self
+                .range_by(
+                    Bound::<fn(&K) -> Ordering>::Unbounded,
+                    Bound::Included(|k: &K| k.key_bytes().cmp(query).then(Ordering::Less)),
+                )
+                .next_back()?;
+            let key_bytes = key.key_bytes();
+            if query.starts_with(key_bytes) {
+                return Some((key, val));
+            }
+            let common = key_bytes.iter().zip(query).take_while(|(a, b)| a == b).count();
+            query = &query[..common];
+        }
+    }
+}
+
+/// Whether the bounds contain nothing because they're inverted, or are both the same value and one
+/// is excluded. These would confuse partition points
+#[inline]
+fn is_inverted<T: Ord + ?Sized>(bounds: &impl RangeBounds<T>) -> bool {
+    match (bounds.start_bound(), bounds.end_bound()) {
+        (Bound::Included(start), Bound::Included(end)) => start > end,
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) => start >= end,
+        _ => false,
+    }
+}
+
+/// Number of nodes inserting into `leaf` allocates: one per node which splits, plus a new root if
+/// the root splits
+#[inline]
+unsafe fn nodes_to_split<K, V, S>(leaf: NodePtr<K, V, S>) -> usize {
+    let mut node = leaf;
+    let mut count = 0;
+    while node.as_ref().len as usize == M {
+        count += 1;
+        match node.as_ref().parent() {
+            Some((parent, _)) => node = parent,
+            None => return count + 1,
+        }
+    }
+    count
+}
+
+/// A leaf and the index of an entry in it
+type Address<K, V, S> = (NodePtr<K, V, S>, u16);
+
+/// Unwraps the result of an insertion in a method which can't return it, *panic*king if the store
+/// was at its limit
+#[inline]
+fn or_panic<T, K, V>(result: Result<T, CapacityError<K, V>>) -> T {
+    result.unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Methods which mutate values in place, so they're only available without cached [Summary]s
+impl<'store, K, V, C> BTreeMap<'store, K, V, C> {
+    /// Returns a mutable reference to the value corresponding to the key.
+    #[inline]
+    pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        C: Comparator<Q>,
+    {
+        match self.find(key) {
+            Find::At { mut node, idx } => unsafe { Some(node.as_mut().val_mut(idx)) },
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the equivalent key and mutable associated value
+    ///
+    /// This is (only) useful when `Q` is a different type than `K`.
+    #[inline]
+    pub fn get_key_value_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+    where
+        K: Borrow<Q>,
+        C: Comparator<Q>,
+    {
+        match self.find(key) {
+            Find::At { mut node, idx } => unsafe { Some(node.as_mut().key_val_mut(idx)) },
+            _ => None,
+        }
+    }
+
+    /// Returns the first key and mutable value
+    #[inline]
+    pub fn first_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        self.first_leaf()
+            .map(|mut node| unsafe { node.as_mut().first_key_value_mut() })
+    }
+
+    /// Returns the last key and mutable value
+    #[inline]
+    pub fn last_key_value_mut(&mut self) -> Option<(&K, &mut V)> {
+        self.last_leaf()
+            .map(|mut node| unsafe { node.as_mut().last_key_value_mut() })
+    }
+
+    /// Get a reference to the value at the given key, or insert a new value if the key is not
+    /// present.
+    ///
+    /// If `Clone` or the comparator *panic*s, the map is unchanged. If the store is at its
+    /// [limit](BTreeStore::with_limit), *panic*s with the map unchanged; see
+    /// [try_get_or_insert](Self::try_get_or_insert).
+    #[inline]
+    pub fn get_or_insert(&mut self, key: K, val: V) -> &mut V
+    where
+        K: Separator,
+        C: Comparator<K>,
+    {
+        or_panic(self.try_get_or_insert(key, val))
+    }
+
+    /// Get a reference to the value at the given key, or insert a new value if the key is not
+    /// present, unless the store is at its [limit](BTreeStore::with_limit): then returns the
+    /// entry in a [CapacityError] and the map is unchanged.
+    #[inline]
+    pub fn try_get_or_insert(&mut self, key: K, val: V) -> Result<&mut V, CapacityError<K, V>>
+    where
+        K: Separator,
+        C: Comparator<K>,
+    {
+        match self.find(&key) {
+            Find::NoRoot => unsafe {
+                self.insert_root(key, val)?;
+                Ok(self.root.unwrap().as_mut().val_mut(0))
+            },
+            Find::Before { node, idx } => unsafe {
+                let (mut node, idx) = self.insert_before(key, val, node, idx)?;
+                Ok(node.as_mut().val_mut(idx))
+            },
+            Find::At { mut node, idx } => unsafe { Ok(node.as_mut().val_mut(idx)) },
+        }
+    }
+
+    /// Iterates over the map's key-value pairs in order. Values are mutable
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut::new(self)
+    }
+
+    /// Iterates over the map's values in order. Values are mutable
+    #[inline]
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.iter_mut())
+    }
+
+    /// Iterates over the map's key-value pairs in order, within the given range.. Values are mutable
+    #[inline]
+    pub fn range_mut<Q: ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> RangeMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+        C: Comparator<Q>,
+    {
+        RangeMut::new(self, bounds)
+    }
+
+    /// Iterates over the map's values in order, within the given range. Values are mutable
+    #[inline]
+    pub fn range_values_mut<Q: ?Sized>(
+        &mut self,
+        bounds: impl RangeBounds<Q>,
+    ) -> impl Iterator<Item = &mut V> + '_
+    where
+        K: Borrow<Q>,
+        C: Comparator<Q>,
+    {
+        self.range_mut(bounds).map(|(_, v)| v)
+    }
+
+    /// [BTreeMap::range_by] with mutable values
+    #[inline]
+    pub(crate) fn range_mut_by(
+        &mut self,
+        start: Bound<impl FnMut(&K) -> Ordering>,
+        end: Bound<impl FnMut(&K) -> Ordering>,
+    ) -> RangeMut<'_, K, V> {
+        RangeMut::from_bounds(search::node_bounds_by(self, start, end))
+    }
+}
+
+// region positional
+/// Positional b-trees, whose keys are all `()` and whose summaries count entries, so entries are
+/// found by index. See [BTreeVec](crate::BTreeVec).
+impl<'store, V> BTreeMap<'store, (), V, OrdComparator, Count> {
+    /// Leaf and index of the entry at `index`, or where it would be inserted if `index == len`
+    #[inline]
+    fn address_at(&self, mut index: usize) -> Option<(NodePtr<(), V, Count>, u16)> {
+        let mut node = self.root?;
+        for _ in 0..self.height {
+            let mut idx = 0;
+            unsafe {
+                while idx < node.as_ref().len {
+                    let Count(count) = node.as_ref().summary(idx);
+                    if index < count {
+                        break;
+                    }
+                    index -= count;
+                    idx += 1;
+                }
+                node = node.as_ref().edge(idx);
+            }
+        }
+        Some((node, index as u16))
+    }
+
+    /// Node bounds of the indices, which must be in bounds
+    #[inline]
+    fn node_bounds_at(
+        &self,
+        range: std::ops::Range<usize>,
+    ) -> Option<NodeBounds<NodePtr<(), V, Count>>> {
+        if range.is_empty() {
+            return None;
+        }
+        let (start_node, start_index) = self.address_at(range.start)?;
+        let (end_node, end_index) = self.address_at(range.end - 1)?;
+        Some(NodeBounds {
+            start_node,
+            end_node,
+            start_index,
+            end_index,
+        })
+    }
+
+    /// Returns the value at `index`, if it's in bounds
+    #[inline]
+    pub(crate) fn get_at(&self, index: usize) -> Option<&V> {
+        if index >= self.length {
+            return None;
+        }
+        let (node, idx) = self.address_at(index)?;
+        Some(unsafe { node.as_ref().val(idx) })
+    }
+
+    /// Returns the mutable value at `index`, if it's in bounds. Values aren't summarized, so
+    /// mutating them doesn't change the counts.
+    #[inline]
+    pub(crate) fn get_at_mut(&mut self, index: usize) -> Option<&mut V> {
+        if index >= self.length {
+            return None;
+        }
+        let (mut node, idx) = self.address_at(index)?;
+        Some(unsafe { node.as_mut().val_mut(idx) })
+    }
+
+    /// Inserts the value at `index`, which must be at most the length
+    #[inline]
+    pub(crate) fn insert_at(&mut self, index: usize, val: V) {
+        debug_assert!(index <= self.length);
+        match self.address_at(index) {
+            None => or_panic(self.insert_root((), val)),
+            Some((node, idx)) => unsafe {
+                or_panic(self.insert_before((), val, node, idx));
+            },
+        }
+    }
+
+    /// Removes and returns the value at `index`, which must be less than the length
+    #[inline]
+    pub(crate) fn remove_at_index(&mut self, index: usize) -> V {
+        debug_assert!(index < self.length);
+        let (node, idx) = self.address_at(index).unwrap();
+        unsafe { self.remove_at(node, idx).1 }
+    }
+
+    /// Iterates the values at the indices, which must be in bounds
+    #[inline]
+    pub(crate) fn range_at(&self, range: std::ops::Range<usize>) -> Range<'_, (), V, Count> {
+        Range::from_bounds(self.node_bounds_at(range))
+    }
+
+    /// [BTreeMap::range_at] with mutable values
+    #[inline]
+    pub(crate) fn range_mut_at(
+        &mut self,
+        range: std::ops::Range<usize>,
+    ) -> RangeMut<'_, (), V, Count> {
+        RangeMut::from_bounds(self.node_bounds_at(range))
+    }
+
+    /// Moves all of `other`'s values after ours, in `O(log n)`. *Panic*s if `other` is from another
+    /// store, or if the store is at its limit, with both trees unchanged.
+    pub(crate) fn append(&mut self, other: &mut Self) {
+        assert!(
+            ptr::eq(self.store, other.store),
+            "can't append a b-tree from another store"
+        );
+        let (Some(mut last), Some(mut other_first)) = (self.last_leaf(), other.first_leaf()) else {
+            if self.is_empty() {
+                mem::swap(self, other);
+            }
+            return;
+        };
+        // Joining may split a node on every level and add a root, so check before mutating
+        assert!(
+            self.store.has_room(self.height.max(other.height) + 1),
+            "BTreeStore node limit exceeded"
+        );
+        let other_root = other.root.take().unwrap();
+        let other_height = mem::take(&mut other.height);
+        self.length += mem::take(&mut other.length);
+        unsafe {
+            last.as_mut().set_next(Some(other_first));
+            other_first.as_mut().set_prev(Some(last));
+            if self.height >= other_height {
+                // Join `other`'s root after the node on our right border at its height
+                let mut left = self.root.unwrap();
+                for _ in other_height..self.height {
+                    left = left.as_ref().edge(left.as_ref().len);
+                }
+                self.join(left, other_root, other_height);
+            } else {
+                // Join our root before the node on `other`'s left border at its height. The joined
+                // node must stay in `other`'s tree, so first swap their contents
+                let height = self.height;
+                let mut left = self.root.unwrap();
+                let mut right = other_root;
+                for _ in height..other_height {
+                    right = right.as_ref().edge(0);
+                }
+                swap_contents(left, right, height);
+                if height == 0 {
+                    let next = right.as_ref().next();
+                    right.as_mut().set_prev(None);
+                    right.as_mut().set_next(Some(left));
+                    left.as_mut().set_prev(Some(right));
+                    left.as_mut().set_next(next);
+                    if let Some(mut next) = next {
+                        next.as_mut().set_prev(Some(left));
                     }
-                    break;
                 }
+                self.root = Some(other_root);
+                self.height = other_height;
+                self.join(right, left, height);
             }
+        }
+    }
 
-            // Try to redistribute with next sibling
-            if idx < parent.as_ref().len {
-                let mut next = parent.as_ref().edge(idx + 1);
-                if (next.as_ref().len as usize) > M / 2 {
-                    if is_leaf {
-                        parent
-                            .as_mut()
-                            .replace_key(idx, next.as_ref().key(1).clone());
-                        let (key, val) = next.as_mut().remove_val(0);
-                        node.as_mut().insert_val(node.as_ref().len, key, val);
-                    } else {
-                        let (key, mut edge) = next.as_mut().remove_edge(0, false);
-                        let key = parent.as_mut().replace_key(idx, key);
-                        let len = node.as_ref().len;
-                        edge.as_mut().set_parent(node, len + 1);
-                        node.as_mut().insert_edge(len, true, key, edge);
-                    }
-                    break;
+    /// Joins `right`, the root of a detached tree, after `left`, which is on our right border. Both
+    /// are at `height`, and the leaves are already linked.
+    unsafe fn join(
+        &mut self,
+        mut left: NodePtr<(), V, Count>,
+        right: NodePtr<(), V, Count>,
+        height: usize,
+    ) {
+        let left_len = num_items(left, height);
+        let right_len = num_items(right, height);
+        if left_len + right_len <= capacity(height) {
+            shift_left(left, right, right_len, height);
+            if height == 0 {
+                let next = right.as_ref().next();
+                left.as_mut().set_next(next);
+                if let Some(mut next) = next {
+                    next.as_mut().set_prev(Some(left));
                 }
             }
+            self.store.dealloc(right);
+            if height > 0 {
+                resummarize(left, height);
+            }
+            resummarize_up(left, height);
+        } else {
+            // Split the items evenly, so both nodes have at least the minimum
+            let half = (left_len + right_len) / 2;
+            if left_len < half {
+                shift_left(left, right, half - left_len, height);
+            } else {
+                shift_right(left, right, left_len - half, height);
+            }
+            if height > 0 {
+                resummarize(left, height);
+                resummarize(right, height);
+            }
+            self.insert_split((), left, right, height);
+        }
+    }
 
-            // Merge with prev sibling or next sibling. We prioritize prev just because, but
-            // must choose next if idx == 0
-            if idx > 0 {
-                let mut prev = parent.as_mut().edge(idx - 1);
-                if is_leaf {
-                    node.as_mut().merge_prev_leaf(prev.as_mut());
-                    if let Some(mut new_prev) = node.as_ref().prev() {
-                        new_prev.as_mut().set_next(Some(node));
+    /// Splits off the values from `index` on, which must be at most the length, into a new tree in
+    /// `O(log n)`. *Panic*s with the tree unchanged if the store is at its limit.
+    pub(crate) fn split_off_at(&mut self, index: usize) -> Self {
+        debug_assert!(index <= self.length);
+        let mut right = Self {
+            store: self.store,
+            root: None,
+            length: 0,
+            height: 0,
+            cmp: OrdComparator,
+            owns_separators: self.owns_separators,
+            _p: PhantomData,
+        };
+        if index == self.length {
+            return right;
+        } else if index == 0 {
+            mem::swap(self, &mut right);
+            return right;
+        }
+        // The cut allocates a node on every level, so check before mutating
+        assert!(
+            self.store.has_room(self.height + 1),
+            "BTreeStore node limit exceeded"
+        );
+
+        unsafe {
+            // Cut every node on the path to `index`, moving the entries and edges after it into new
+            // nodes. The nodes on the cut may be underfull (even empty), so we fix them after
+            let (mut left_node, idx) = self.address_at(index).unwrap();
+            let mut right_node = self.store.alloc(Node::leaf());
+            shift_right(left_node, right_node, (left_node.as_ref().len - idx) as usize, 0);
+            right_node.as_mut().set_next(left_node.as_ref().next());
+            if let Some(mut next) = left_node.as_ref().next() {
+                next.as_mut().set_prev(Some(right_node));
+            }
+            left_node.as_mut().set_next(None);
+
+            let mut height = 0;
+            while let Some((mut parent, parent_idx)) = left_node.as_ref().parent() {
+                height += 1;
+                let mut right_parent = self.store.alloc(Node::internal(self.owns_separators));
+                right_node.as_mut().set_parent(right_parent, 0);
+                right_parent.as_mut().set_last_edge(right_node);
+                while parent.as_ref().len > parent_idx {
+                    let (key, mut edge) = parent.as_mut().remove_edge(parent_idx, true);
+                    let len = right_parent.as_ref().len;
+                    edge.as_mut().set_parent(right_parent, len + 1);
+                    right_parent.as_mut().insert_edge(len, true, key, edge);
+                }
+                resummarize(parent, height);
+                resummarize(right_parent, height);
+                left_node = parent;
+                right_node = right_parent;
+            }
+
+            right.root = Some(right_node);
+            right.height = self.height;
+            right.length = self.length - index;
+            self.length = index;
+            self.fix_right_border();
+            right.fix_left_border();
+        }
+        right
+    }
+
+    /// Removes roots with only one edge
+    unsafe fn fix_top(&mut self) {
+        while self.height > 0 {
+            let root = self.root.unwrap();
+            if root.as_ref().len > 0 {
+                break;
+            }
+            let mut child = root.as_ref().edge(0);
+            child.as_mut().clear_parent();
+            self.store.dealloc(root);
+            self.root = Some(child);
+            self.height -= 1;
+        }
+    }
+
+    /// Fixes underfull nodes on our right border after a cut, from the top down. Each border node
+    /// is stocked from or merged into its previous sibling; a merge removes an edge from the parent,
+    /// which we [rebalance](BTreeMap::rebalance) like a removal.
+    unsafe fn fix_right_border(&mut self) {
+        self.fix_top();
+        let mut node = self.root.unwrap();
+        let mut height = self.height;
+        while height > 0 {
+            let len = node.as_ref().len;
+            let mut child = node.as_ref().edge(len);
+            let child_len = num_items(child, height - 1);
+            if child_len < min_items(height - 1) {
+                let prev = node.as_ref().edge(len - 1);
+                let prev_len = num_items(prev, height - 1);
+                if prev_len + child_len <= capacity(height - 1) {
+                    shift_left(prev, child, child_len, height - 1);
+                    let ((), edge) = node.as_mut().remove_last_edge();
+                    debug_assert!(edge.ptr_eq(&child));
+                    self.store.dealloc(child);
+                    child = prev;
+                    if height == 1 {
+                        child.as_mut().set_next(None);
+                    } else {
+                        resummarize(child, height - 1);
                     }
+                    resummarize(node, height);
+                    self.rebalance(node, height);
                 } else {
-                    let key = parent.as_ref().key(idx - 1).clone();
-                    for child in prev.as_mut().edges_mut() {
-                        child.as_mut().parent = Some(node);
+                    shift_right(prev, child, min_items(height - 1) - child_len, height - 1);
+                    if height > 1 {
+                        resummarize(prev, height - 1);
+                        resummarize(child, height - 1);
                     }
-                    node.as_mut().merge_prev_internal(key, prev.as_mut());
+                    resummarize(node, height);
                 }
+            }
+            node = child;
+            height -= 1;
+        }
+    }
 
-                // Dealloc and remove absorbed (empty) node and fix indices of the nodes
-                // after
-                let (_key, edge) = parent.as_mut().remove_edge(idx - 1, false);
-                debug_assert!(edge.ptr_eq(&prev));
-                self.store.dealloc(prev);
-            } else {
-                let mut next = parent.as_mut().edge(idx + 1);
-                if is_leaf {
-                    node.as_mut().merge_next_leaf(next.as_mut());
-                    if let Some(mut new_next) = node.as_ref().next() {
-                        new_next.as_mut().set_prev(Some(node));
+    /// [BTreeMap::fix_right_border] for our left border, where each border node is stocked from or
+    /// merged into its next sibling
+    unsafe fn fix_left_border(&mut self) {
+        self.fix_top();
+        let mut node = self.root.unwrap();
+        let mut height = self.height;
+        while height > 0 {
+            let mut child = node.as_ref().edge(0);
+            let child_len = num_items(child, height - 1);
+            if child_len < min_items(height - 1) {
+                let next = node.as_ref().edge(1);
+                let next_len = num_items(next, height - 1);
+                if child_len + next_len <= capacity(height - 1) {
+                    shift_right(child, next, child_len, height - 1);
+                    let ((), edge) = node.as_mut().remove_edge(0, false);
+                    debug_assert!(edge.ptr_eq(&child));
+                    self.store.dealloc(child);
+                    child = next;
+                    if height == 1 {
+                        child.as_mut().set_prev(None);
+                    } else {
+                        resummarize(child, height - 1);
                     }
+                    resummarize(node, height);
+                    self.rebalance(node, height);
                 } else {
-                    let key = parent.as_ref().key(idx).clone();
-                    for child in next.as_mut().edges_mut() {
-                        child.as_mut().parent = Some(node);
+                    shift_left(child, next, min_items(height - 1) - child_len, height - 1);
+                    if height > 1 {
+                        resummarize(child, height - 1);
+                        resummarize(next, height - 1);
                     }
-                    node.as_mut().merge_next_internal(key, next.as_mut());
+                    resummarize(node, height);
                 }
-
-                // Dealloc and remove absorbed (empty) node and fix indices of the nodes
-                // after
-                let (_key, edge) = parent.as_mut().remove_edge(idx, true);
-                debug_assert!(edge.ptr_eq(&next));
-                self.store.dealloc(next);
             }
+            node = child;
+            height -= 1;
+        }
+    }
+}
 
-            // Since we merged, we may now have to redistribute or merge the parent since it
-            // has 1 less child
-            node = parent;
-            is_leaf = false;
+/// Number of entries in a leaf, or edges in an internal node
+#[inline]
+unsafe fn num_items<K, V, S>(node: NodePtr<K, V, S>, height: usize) -> usize {
+    node.as_ref().len as usize + usize::from(height > 0)
+}
+
+/// Maximum number of entries or edges of a node at `height`
+#[inline]
+fn capacity(height: usize) -> usize {
+    M + usize::from(height > 0)
+}
+
+/// Minimum number of entries or edges of a non-root node at `height`
+#[inline]
+fn min_items(height: usize) -> usize {
+    M / 2 + usize::from(height > 0)
+}
+
+/// Moves the first `count` entries or edges of `right` to the end of `left`, in a positional tree.
+/// Moving all of an internal node's edges leaves it invalid, so it must be deallocated.
+#[inline]
+unsafe fn shift_left<V, S>(
+    mut left: NodePtr<(), V, S>,
+    mut right: NodePtr<(), V, S>,
+    count: usize,
+    height: usize,
+) {
+    for _ in 0..count {
+        let len = left.as_ref().len;
+        if height == 0 {
+            let ((), val) = right.as_mut().remove_val(0);
+            left.as_mut().insert_val(len, (), val);
+        } else {
+            let mut edge = match right.as_ref().len {
+                0 => right.as_ref().edge(0),
+                _ => right.as_mut().remove_edge(0, false).1,
+            };
+            edge.as_mut().set_parent(left, len + 1);
+            left.as_mut().insert_edge(len, true, (), edge);
         }
     }
-    // endregion
 }
 
-impl<K, V> NodeBounds<K, V> {
-    #[inline]
-    fn start(&self) -> (NodePtr<K, V>, u16) {
-        (self.start_node, self.start_index)
+/// Moves the last `count` entries or edges of `left` to the start of `right`, in a positional tree.
+/// Moving all of an internal node's edges leaves it invalid, so it must be deallocated.
+#[inline]
+unsafe fn shift_right<V, S>(
+    mut left: NodePtr<(), V, S>,
+    mut right: NodePtr<(), V, S>,
+    count: usize,
+    height: usize,
+) {
+    for _ in 0..count {
+        if height == 0 {
+            let ((), val) = left.as_mut().remove_val(left.as_ref().len - 1);
+            right.as_mut().insert_val(0, (), val);
+        } else {
+            let mut edge = match left.as_ref().len {
+                0 => left.as_ref().edge(0),
+                _ => left.as_mut().remove_last_edge().1,
+            };
+            edge.as_mut().set_parent(right, 0);
+            right.as_mut().insert_edge(0, false, (), edge);
+        }
     }
+}
 
-    #[inline]
-    fn end(&self) -> (NodePtr<K, V>, u16) {
-        (self.end_node, self.end_index)
+/// Swaps the entries or edges (and summaries) of two nodes at `height`, but not their parents or
+/// leaf links
+#[inline]
+unsafe fn swap_contents<V, S>(mut a: NodePtr<(), V, S>, mut b: NodePtr<(), V, S>, height: usize) {
+    let (a_node, b_node) = (a.as_mut(), b.as_mut());
+    mem::swap(&mut a_node.len, &mut b_node.len);
+    if height == 0 {
+        mem::swap(&mut a_node.d.leaf_mut().vals, &mut b_node.d.leaf_mut().vals);
+    } else {
+        let (a_data, b_data) = (a_node.d.internal_mut(), b_node.d.internal_mut());
+        mem::swap(&mut a_data.edges, &mut b_data.edges);
+        mem::swap(&mut a_data.summaries, &mut b_data.summaries);
+        for mut node in [a, b] {
+            for (idx, child) in node.as_mut().edges_mut().iter_mut().enumerate() {
+                child.as_mut().set_parent(node, idx as u16);
+            }
+        }
     }
 }
+// endregion
 
 // region common trait impls
-impl<'store, K: Debug, V: Debug> Debug for BTreeMap<'store, K, V> {
+impl<'store, K: Debug, V: Debug, C, S> Debug for BTreeMap<'store, K, V, C, S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         self.print(f)
     }
 }
 
-impl<'store, K: PartialEq, V: PartialEq> PartialEq for BTreeMap<'store, K, V> {
+impl<'store, K: PartialEq, V: PartialEq, C, S> PartialEq for BTreeMap<'store, K, V, C, S> {
     fn eq(&self, other: &Self) -> bool {
         self.iter().eq(other.iter())
     }
 }
 
-impl<'store, K: Eq, V: Eq> Eq for BTreeMap<'store, K, V> {}
+impl<'store, K: Eq, V: Eq, C, S> Eq for BTreeMap<'store, K, V, C, S> {}
 
-impl<'store, K: PartialOrd, V: PartialOrd> PartialOrd for BTreeMap<'store, K, V> {
+impl<'store, K: PartialOrd, V: PartialOrd, C, S> PartialOrd for BTreeMap<'store, K, V, C, S> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         self.iter().partial_cmp(other.iter())
     }
 }
 
-impl<'store, K: Ord, V: Ord> Ord for BTreeMap<'store, K, V> {
+impl<'store, K: Ord, V: Ord, C, S> Ord for BTreeMap<'store, K, V, C, S> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.iter().cmp(other.iter())
     }
 }
 
-impl<'store, K: Hash, V: Hash> Hash for BTreeMap<'store, K, V> {
+impl<'store, K: Hash, V: Hash, C, S> Hash for BTreeMap<'store, K, V, C, S> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         for (k, v) in self.iter() {
             k.hash(state);
@@ -1041,7 +2180,9 @@ impl<'store, K: Hash, V: Hash> Hash for BTreeMap<'store, K, V> {
     }
 }
 
-impl<'store, K: Ord + Clone, V> Extend<(K, V)> for BTreeMap<'store, K, V> {
+impl<'store, K: Separator, V, C: Comparator<K>, S: Summary<K, V>> Extend<(K, V)>
+    for BTreeMap<'store, K, V, C, S>
+{
     fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
         for (k, v) in iter {
             self.insert(k, v);
@@ -1050,51 +2191,183 @@ impl<'store, K: Ord + Clone, V> Extend<(K, V)> for BTreeMap<'store, K, V> {
 }
 // endregion
 
+// region bulk loading
+/// Allocates `num_leaves` empty leaves, linked to each other, to be filled with entries in order
+/// and [stitched](BTreeMap::stitch_leaves) into a map.
+///
+/// *Panic*s before allocating if the store can't fit the leaves and the internal nodes above them.
+pub(crate) fn alloc_leaves<K, V, S>(
+    store: &BTreeStore<K, V, S>,
+    num_leaves: usize,
+) -> Vec<NodePtr<K, V, S>> {
+    let mut num_nodes = num_leaves;
+    let mut level_len = num_leaves;
+    while level_len > 1 {
+        level_len = level_len.div_ceil(M + 1);
+        num_nodes += level_len;
+    }
+    assert!(store.has_room(num_nodes), "BTreeStore node limit exceeded");
+    let mut level = Vec::with_capacity(num_leaves);
+    let mut prev = None;
+    for _ in 0..num_leaves {
+        let mut leaf = Node::leaf();
+        unsafe { leaf.set_prev(prev) };
+        let leaf = store.alloc(leaf);
+        if let Some(mut prev) = prev {
+            unsafe { prev.as_mut().set_next(Some(leaf)) };
+        }
+        prev = Some(leaf);
+        level.push(leaf);
+    }
+    level
+}
+
+/// Indices of the `i`th of `num_chunks` chunks which divide `length` items evenly, with the
+/// remainder going to the first chunks
+#[inline]
+pub(crate) fn even_chunk(length: usize, num_chunks: usize, i: usize) -> std::ops::Range<usize> {
+    let (base, extra) = (length / num_chunks, length % num_chunks);
+    let start = i * base + i.min(extra);
+    start..start + base + usize::from(i < extra)
+}
+// endregion
+
+// region summaries
+/// Summary of the node's subtree: of its entries if it's a leaf, otherwise of its cached summaries
+#[inline]
+unsafe fn total<K, V, S: Summary<K, V>>(node: NodePtr<K, V, S>, height: usize) -> S {
+    let node = node.as_ref();
+    match height {
+        0 => fold_entries(node, 0..node.len),
+        _ => fold_summaries(node, 0..node.len + 1),
+    }
+}
+
+/// Summary of the entries in the leaf's index range
+#[inline]
+unsafe fn fold_entries<K, V, S: Summary<K, V>>(leaf: &Node<K, V, S>, idxs: std::ops::Range<u16>) -> S {
+    idxs.fold(S::empty(), |acc, idx| {
+        let (key, val) = leaf.key_val(idx);
+        S::combine(acc, S::summarize(key, val))
+    })
+}
+
+/// Summary of the internal node's cached summaries in the edge range
+#[inline]
+unsafe fn fold_summaries<K, V, S: Summary<K, V>>(node: &Node<K, V, S>, idxs: std::ops::Range<u16>) -> S {
+    idxs.fold(S::empty(), |acc, idx| S::combine(acc, node.summary(idx)))
+}
+
+/// Rewrites all of an internal node's cached summaries, after its edges changed
+#[inline]
+unsafe fn resummarize<K, V, S: Summary<K, V>>(mut node: NodePtr<K, V, S>, height: usize) {
+    if size_of::<S>() == 0 {
+        return;
+    }
+    for idx in 0..node.as_ref().len + 1 {
+        let child = node.as_ref().edge(idx);
+        node.as_mut().set_summary(idx, total(child, height - 1));
+    }
+}
+
+/// Rewrites the cached summaries of the node's ancestors, after its subtree changed
+#[inline]
+unsafe fn resummarize_up<K, V, S: Summary<K, V>>(mut node: NodePtr<K, V, S>, mut height: usize) {
+    if size_of::<S>() == 0 {
+        return;
+    }
+    while let Some((mut parent, idx)) = node.as_ref().parent() {
+        parent.as_mut().set_summary(idx, total(node, height));
+        node = parent;
+        height += 1;
+    }
+}
+// endregion
+
 // region drop and dealloc
-impl<'store, K, V> Drop for BTreeMap<'store, K, V> {
+impl<'store, K, V, C, S> Drop for BTreeMap<'store, K, V, C, S> {
     #[inline]
     fn drop(&mut self) {
-        if panicking() {
-            // TODO: Drop when panicking without causing UB (need to reorder some operations)
-            return;
-        }
-
+        // Operations keep the tree valid when user code *panic*s, so this is sound during
+        // unwinding too
         if let Some(root) = self.root.take() {
-            unsafe { drop_node_ptr(root, self.height, &mut |n| self.store.dealloc(n)) }
+            unsafe {
+                drop_node_ptr(root, self.height, self.owns_separators, &mut |n| {
+                    self.store.dealloc(n)
+                })
+            }
         }
     }
 }
 
-unsafe fn drop_node_ptr<K, V>(
-    mut node: NodePtr<K, V>,
+/// Drops the node's subtree and deallocates its nodes. If a key or value's destructor *panic*s,
+/// still drops the rest and deallocates every node, then resumes the first *panic*.
+unsafe fn drop_node_ptr<K, V, S>(
+    node: NodePtr<K, V, S>,
     height: usize,
-    dealloc: &mut impl FnMut(NodePtr<K, V>),
+    owns_separators: bool,
+    dealloc: &mut impl FnMut(NodePtr<K, V, S>),
+) {
+    let mut panic = None;
+    drop_subtree(node, height, owns_separators, dealloc, &mut panic);
+    if let Some(panic) = panic {
+        resume_unwind(panic);
+    }
+}
+
+pub(crate) unsafe fn drop_subtree<K, V, S>(
+    mut node: NodePtr<K, V, S>,
+    height: usize,
+    owns_separators: bool,
+    dealloc: &mut impl FnMut(NodePtr<K, V, S>),
+    panic: &mut Option<Box<dyn Any + Send>>,
 ) {
     let node_ref = node.as_mut();
 
-    for key in node_ref.keys_mut() {
-        drop_in_place(key as *mut _);
-    }
+    drop_entries(node_ref, height == 0, owns_separators, panic);
     if height > 0 {
         for &child in node_ref.edges() {
-            drop_node_ptr(child, height - 1, dealloc);
-        }
-    } else {
-        for val in node_ref.vals_mut() {
-            drop_in_place(val as *mut _);
+            drop_subtree(child, height - 1, owns_separators, dealloc, panic);
         }
     }
 
     dealloc(node);
 }
 
+/// Drops the node's keys (if it's a leaf or owns its separators) and values, but not its children
+pub(crate) unsafe fn drop_entries<K, V, S>(
+    node: &mut Node<K, V, S>,
+    is_leaf: bool,
+    owns_separators: bool,
+    panic: &mut Option<Box<dyn Any + Send>>,
+) {
+    if needs_drop::<K>() && (is_leaf || owns_separators) {
+        for key in node.keys_mut() {
+            drop_catching(key, panic);
+        }
+    }
+    if is_leaf && needs_drop::<V>() {
+        for val in node.vals_mut() {
+            drop_catching(val, panic);
+        }
+    }
+}
+
+/// Drops the value in place, storing its destructor's *panic* if it's the first one
+#[inline]
+unsafe fn drop_catching<T>(value: &mut T, panic: &mut Option<Box<dyn Any + Send>>) {
+    if let Err(err) = catch_unwind(AssertUnwindSafe(|| drop_in_place(value as *mut T))) {
+        panic.get_or_insert(err);
+    }
+}
+
 /// If this address is at the start of the node, deallocates the node, then checks if it's at the
 /// start of its parent, if so deallocates its parent, and so on.
 ///
 /// Doesn't drop any of the nodes' contents
-unsafe fn dealloc_up_firsts<K, V>(
-    mut address: (NodePtr<K, V>, u16),
-    mut dealloc: impl FnMut(NodePtr<K, V>),
+unsafe fn dealloc_up_firsts<K, V, S>(
+    mut address: (NodePtr<K, V, S>, u16),
+    mut dealloc: impl FnMut(NodePtr<K, V, S>),
 ) {
     loop {
         let (node, idx) = address;
@@ -1122,9 +2395,9 @@ unsafe fn dealloc_up_firsts<K, V>(
 ///
 /// Doesn't drop any of the nodes' contents
 #[inline]
-unsafe fn dealloc_up_lasts<K, V>(
-    (mut node, mut idx): (NodePtr<K, V>, u16),
-    mut dealloc: impl FnMut(NodePtr<K, V>),
+unsafe fn dealloc_up_lasts<K, V, S>(
+    (mut node, mut idx): (NodePtr<K, V, S>, u16),
+    mut dealloc: impl FnMut(NodePtr<K, V, S>),
 ) {
     debug_assert!(
         idx < node.as_ref().len,
@@ -1156,9 +2429,9 @@ unsafe fn dealloc_up_lasts<K, V>(
 // region iterators (almost all boilerplate)
 //noinspection DuplicatedCode
 // region iterator impls
-impl<'store: 'a, 'a, K, V> IntoIterator for &'a BTreeMap<'store, K, V> {
+impl<'store: 'a, 'a, K, V, C, S> IntoIterator for &'a BTreeMap<'store, K, V, C, S> {
     type Item = (&'a K, &'a V);
-    type IntoIter = Iter<'a, K, V>;
+    type IntoIter = Iter<'a, K, V, S>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -1166,7 +2439,7 @@ impl<'store: 'a, 'a, K, V> IntoIterator for &'a BTreeMap<'store, K, V> {
     }
 }
 
-impl<'store: 'a, 'a, K, V> IntoIterator for &'a mut BTreeMap<'store, K, V> {
+impl<'store: 'a, 'a, K, V, C> IntoIterator for &'a mut BTreeMap<'store, K, V, C> {
     type Item = (&'a K, &'a mut V);
     type IntoIter = IterMut<'a, K, V>;
 
@@ -1176,9 +2449,9 @@ impl<'store: 'a, 'a, K, V> IntoIterator for &'a mut BTreeMap<'store, K, V> {
     }
 }
 
-impl<'store, K, V> IntoIterator for BTreeMap<'store, K, V> {
+impl<'store, K, V, C, S> IntoIterator for BTreeMap<'store, K, V, C, S> {
     type Item = (K, V);
-    type IntoIter = IntoIter<'store, K, V>;
+    type IntoIter = IntoIter<'store, K, V, S>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -1188,17 +2461,26 @@ impl<'store, K, V> IntoIterator for BTreeMap<'store, K, V> {
 // endregion
 
 // region Iter
-pub struct Iter<'a, K, V> {
-    cursor: Cursor<'a, K, V>,
-    back_cursor: Cursor<'a, K, V>,
+pub struct Iter<'a, K, V, S = ()> {
+    cursor: Cursor<'a, K, V, S>,
+    back_cursor: Cursor<'a, K, V, S>,
     length: usize,
     _p: PhantomData<(&'a K, &'a V)>,
 }
 
 //noinspection DuplicatedCode
-impl<'a, K, V> Iter<'a, K, V> {
+impl<'a, K, V, S> Iter<'a, K, V, S> {
+    #[inline]
+    fn new<C>(tree: &'a BTreeMap<K, V, C, S>) -> Self {
+        unsafe { Self::new_detached(tree) }
+    }
+
+    /// Creates an iterator whose lifetime isn't tied to the reference to `tree`.
+    ///
+    /// # Safety
+    /// The tree's nodes must stay alive and not be mutated for `'a`.
     #[inline]
-    fn new(tree: &'a BTreeMap<K, V>) -> Self {
+    pub(crate) unsafe fn new_detached<C>(tree: &BTreeMap<K, V, C, S>) -> Self {
         Self {
             cursor: unsafe { Cursor::new(tree.first_leaf(), 0) },
             back_cursor: unsafe { Cursor::new_at_end(tree.last_leaf()) },
@@ -1246,7 +2528,7 @@ impl<'a, K, V> Iter<'a, K, V> {
     }
 }
 
-impl<'a, K, V> Iterator for Iter<'a, K, V> {
+impl<'a, K, V, S> Iterator for Iter<'a, K, V, S> {
     type Item = (&'a K, &'a V);
 
     #[inline]
@@ -1262,7 +2544,7 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+impl<'a, K, V, S> DoubleEndedIterator for Iter<'a, K, V, S> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         let key_value = self.peek_back()?;
@@ -1271,14 +2553,14 @@ impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
     }
 }
 
-impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
+impl<'a, K, V, S> ExactSizeIterator for Iter<'a, K, V, S> {
     #[inline]
     fn len(&self) -> usize {
         self.length
     }
 }
 
-impl<'a, K, V> FusedIterator for Iter<'a, K, V> {}
+impl<'a, K, V, S> FusedIterator for Iter<'a, K, V, S> {}
 // endregion
 
 // region IterMut
@@ -1293,7 +2575,7 @@ pub struct IterMut<'a, K, V> {
 //noinspection DuplicatedCode
 impl<'a, K, V> IterMut<'a, K, V> {
     #[inline]
-    fn new(tree: &'a BTreeMap<K, V>) -> Self {
+    fn new<C>(tree: &'a BTreeMap<K, V, C>) -> Self {
         Self {
             cursor: unsafe { Cursor::new(tree.first_leaf(), 0) },
             back_cursor: unsafe { Cursor::new_at_end(tree.last_leaf()) },
@@ -1395,18 +2677,18 @@ impl<'a, K, V> FusedIterator for IterMut<'a, K, V> {}
 // endregion
 
 // region IntoIter
-pub struct IntoIter<'store, K, V> {
-    store: &'store BTreeStore<K, V>,
-    cursor: Cursor<'store, K, V>,
-    back_cursor: Cursor<'store, K, V>,
+pub struct IntoIter<'store, K, V, S = ()> {
+    store: &'store BTreeStore<K, V, S>,
+    cursor: Cursor<'store, K, V, S>,
+    back_cursor: Cursor<'store, K, V, S>,
     length: usize,
     /// Unlike in [Cursor], `K` and `V` are owned
     _p: PhantomData<(K, V)>,
 }
 
-impl<'store, K, V> IntoIter<'store, K, V> {
+impl<'store, K, V, S> IntoIter<'store, K, V, S> {
     #[inline]
-    fn new(tree: BTreeMap<'store, K, V>) -> Self {
+    fn new<C>(mut tree: BTreeMap<'store, K, V, C, S>) -> Self {
         let result = Self {
             store: tree.store,
             cursor: unsafe { Cursor::new(tree.first_leaf(), 0) },
@@ -1414,14 +2696,14 @@ impl<'store, K, V> IntoIter<'store, K, V> {
             length: tree.length,
             _p: PhantomData,
         };
-        // We drop the tree's nodes, so prevent it drop dropping when it drops itself.
-        // This should be equivalent to `tree.root = None`
-        forget(tree);
+        // We drop the tree's nodes, so prevent it from dropping them when it drops itself (it still
+        // drops its comparator)
+        tree.root = None;
         result
     }
 }
 
-impl<'store, K, V> Iterator for IntoIter<'store, K, V> {
+impl<'store, K, V, S> Iterator for IntoIter<'store, K, V, S> {
     type Item = (K, V);
 
     #[inline]
@@ -1445,7 +2727,7 @@ impl<'store, K, V> Iterator for IntoIter<'store, K, V> {
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for IntoIter<'a, K, V> {
+impl<'a, K, V, S> DoubleEndedIterator for IntoIter<'a, K, V, S> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         if self.length == 0 {
@@ -1462,20 +2744,20 @@ impl<'a, K, V> DoubleEndedIterator for IntoIter<'a, K, V> {
     }
 }
 
-impl<'store, K, V> ExactSizeIterator for IntoIter<'store, K, V> {
+impl<'store, K, V, S> ExactSizeIterator for IntoIter<'store, K, V, S> {
     #[inline]
     fn len(&self) -> usize {
         self.length
     }
 }
 
-impl<'store, K, V> FusedIterator for IntoIter<'store, K, V> {}
+impl<'store, K, V, S> FusedIterator for IntoIter<'store, K, V, S> {}
 // endregion
 
 // region Keys
-pub struct Keys<'a, K, V>(Iter<'a, K, V>);
+pub struct Keys<'a, K, V, S = ()>(Iter<'a, K, V, S>);
 
-impl<'a, K, V> Iterator for Keys<'a, K, V> {
+impl<'a, K, V, S> Iterator for Keys<'a, K, V, S> {
     type Item = &'a K;
 
     #[inline]
@@ -1489,27 +2771,27 @@ impl<'a, K, V> Iterator for Keys<'a, K, V> {
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+impl<'a, K, V, S> DoubleEndedIterator for Keys<'a, K, V, S> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(k, _)| k)
     }
 }
 
-impl<'a, K, V> ExactSizeIterator for Keys<'a, K, V> {
+impl<'a, K, V, S> ExactSizeIterator for Keys<'a, K, V, S> {
     #[inline]
     fn len(&self) -> usize {
         self.0.len()
     }
 }
 
-impl<'a, K, V> FusedIterator for Keys<'a, K, V> {}
+impl<'a, K, V, S> FusedIterator for Keys<'a, K, V, S> {}
 // endregion
 
 // region Values
-pub struct Values<'a, K, V>(Iter<'a, K, V>);
+pub struct Values<'a, K, V, S = ()>(Iter<'a, K, V, S>);
 
-impl<'a, K, V> Iterator for Values<'a, K, V> {
+impl<'a, K, V, S> Iterator for Values<'a, K, V, S> {
     type Item = &'a V;
 
     #[inline]
@@ -1523,21 +2805,21 @@ impl<'a, K, V> Iterator for Values<'a, K, V> {
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+impl<'a, K, V, S> DoubleEndedIterator for Values<'a, K, V, S> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(_, v)| v)
     }
 }
 
-impl<'a, K, V> ExactSizeIterator for Values<'a, K, V> {
+impl<'a, K, V, S> ExactSizeIterator for Values<'a, K, V, S> {
     #[inline]
     fn len(&self) -> usize {
         self.0.len()
     }
 }
 
-impl<'a, K, V> FusedIterator for Values<'a, K, V> {}
+impl<'a, K, V, S> FusedIterator for Values<'a, K, V, S> {}
 // endregion
 
 // region ValuesMut
@@ -1575,21 +2857,27 @@ impl<'a, K, V> FusedIterator for ValuesMut<'a, K, V> {}
 // endregion
 
 // region Range
-pub struct Range<'a, K, V> {
-    cursor: Cursor<'a, K, V>,
-    back_cursor: Cursor<'a, K, V>,
-    bounds: MaybeUninit<NodeBounds<K, V>>,
+pub struct Range<'a, K, V, S = ()> {
+    cursor: Cursor<'a, K, V, S>,
+    back_cursor: Cursor<'a, K, V, S>,
     _p: PhantomData<(&'a K, &'a V)>,
 }
 
 //noinspection DuplicatedCode
-impl<'a, K, V> Range<'a, K, V> {
+impl<'a, K, V, S> Range<'a, K, V, S> {
     #[inline]
-    fn new<Q: Ord + ?Sized>(tree: &'a BTreeMap<K, V>, bounds: impl RangeBounds<Q>) -> Self
+    fn new<Q: ?Sized, C: Comparator<Q>>(
+        tree: &'a BTreeMap<K, V, C, S>,
+        bounds: impl RangeBounds<Q>,
+    ) -> Self
     where
         K: Borrow<Q>,
     {
-        let bounds = tree.node_bounds(bounds);
+        Self::from_bounds(tree.node_bounds(bounds))
+    }
+
+    #[inline]
+    pub(crate) fn from_bounds(bounds: Option<NodeBounds<NodePtr<K, V, S>>>) -> Self {
         let cursor = match bounds.as_ref().map(|b| b.start()) {
             None => Cursor::new_detached(),
             Some((start_node, start_idx)) => unsafe { Cursor::new(Some(start_node), start_idx) },
@@ -1598,14 +2886,9 @@ impl<'a, K, V> Range<'a, K, V> {
             None => Cursor::new_detached(),
             Some((end_node, end_idx)) => unsafe { Cursor::new(Some(end_node), end_idx) },
         };
-        let bounds = match bounds {
-            None => MaybeUninit::uninit(),
-            Some(bounds) => MaybeUninit::new(bounds),
-        };
         Self {
             cursor,
             back_cursor,
-            bounds,
             _p: PhantomData,
         }
     }
@@ -1625,37 +2908,31 @@ impl<'a, K, V> Range<'a, K, V> {
     /// Equivalent to `next` except *panics* if iteration is done.
     #[inline]
     pub fn advance(&mut self) {
-        self.cursor.advance();
-        if !self.cursor.is_attached() {
-            self.back_cursor.detach();
-        } else if self
-            .cursor
-            .address()
-            .ptr_eq(&Some(unsafe { self.bounds.assume_init_ref() }.end()))
-        {
+        if self.cursor.is_attached() && self.cursor.address().ptr_eq(&self.back_cursor.address()) {
+            // The cursors met, so that was the last element
             self.cursor.detach();
             self.back_cursor.detach()
+        } else {
+            self.cursor.advance();
         }
     }
 
     /// Equivalent to `next_back` except *panics* if iteration is done.
     #[inline]
     pub fn advance_back(&mut self) {
-        self.back_cursor.advance_back();
-        if !self.back_cursor.is_attached() {
-            self.cursor.detach();
-        } else if self
-            .back_cursor
-            .address()
-            .ptr_eq(&Some(unsafe { self.bounds.assume_init_ref() }.start()))
+        if self.back_cursor.is_attached()
+            && self.back_cursor.address().ptr_eq(&self.cursor.address())
         {
+            // The cursors met, so that was the last element
             self.cursor.detach();
             self.back_cursor.detach()
+        } else {
+            self.back_cursor.advance_back();
         }
     }
 }
 
-impl<'a, K, V> Iterator for Range<'a, K, V> {
+impl<'a, K, V, S> Iterator for Range<'a, K, V, S> {
     type Item = (&'a K, &'a V);
 
     #[inline]
@@ -1666,7 +2943,7 @@ impl<'a, K, V> Iterator for Range<'a, K, V> {
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
+impl<'a, K, V, S> DoubleEndedIterator for Range<'a, K, V, S> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         let key_value = self.peek_back()?;
@@ -1675,26 +2952,32 @@ impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
     }
 }
 
-impl<'a, K, V> FusedIterator for Range<'a, K, V> {}
+impl<'a, K, V, S> FusedIterator for Range<'a, K, V, S> {}
 // endregion
 
 // region RangeMut
-pub struct RangeMut<'a, K, V> {
-    cursor: Cursor<'a, K, V>,
-    back_cursor: Cursor<'a, K, V>,
-    bounds: MaybeUninit<NodeBounds<K, V>>,
+pub struct RangeMut<'a, K, V, S = ()> {
+    cursor: Cursor<'a, K, V, S>,
+    back_cursor: Cursor<'a, K, V, S>,
     /// Unlike [Cursor], the reference to `V` is mutable
     _p: PhantomData<(&'a K, &'a mut V)>,
 }
 
 //noinspection DuplicatedCode
-impl<'a, K, V> RangeMut<'a, K, V> {
+impl<'a, K, V, S> RangeMut<'a, K, V, S> {
     #[inline]
-    fn new<Q: Ord + ?Sized>(tree: &'a BTreeMap<K, V>, bounds: impl RangeBounds<Q>) -> Self
+    fn new<Q: ?Sized, C: Comparator<Q>>(
+        tree: &'a BTreeMap<K, V, C, S>,
+        bounds: impl RangeBounds<Q>,
+    ) -> Self
     where
         K: Borrow<Q>,
     {
-        let bounds = tree.node_bounds(bounds);
+        Self::from_bounds(tree.node_bounds(bounds))
+    }
+
+    #[inline]
+    pub(crate) fn from_bounds(bounds: Option<NodeBounds<NodePtr<K, V, S>>>) -> Self {
         let cursor = match bounds.as_ref().map(|b| b.start()) {
             None => Cursor::new_detached(),
             Some((start_node, start_idx)) => unsafe { Cursor::new(Some(start_node), start_idx) },
@@ -1703,14 +2986,9 @@ impl<'a, K, V> RangeMut<'a, K, V> {
             None => Cursor::new_detached(),
             Some((end_node, end_idx)) => unsafe { Cursor::new(Some(end_node), end_idx) },
         };
-        let bounds = match bounds {
-            None => MaybeUninit::uninit(),
-            Some(bounds) => MaybeUninit::new(bounds),
-        };
         Self {
             cursor,
             back_cursor,
-            bounds,
             _p: PhantomData,
         }
     }
@@ -1743,37 +3021,31 @@ impl<'a, K, V> RangeMut<'a, K, V> {
     /// Equivalent to `next` except *panics* if iteration is done.
     #[inline]
     pub fn advance(&mut self) {
-        self.cursor.advance();
-        if !self.cursor.is_attached() {
-            self.back_cursor.detach();
-        } else if self
-            .cursor
-            .address()
-            .ptr_eq(&Some(unsafe { self.bounds.assume_init_ref() }.end()))
-        {
+        if self.cursor.is_attached() && self.cursor.address().ptr_eq(&self.back_cursor.address()) {
+            // The cursors met, so that was the last element
             self.cursor.detach();
             self.back_cursor.detach()
+        } else {
+            self.cursor.advance();
         }
     }
 
     /// Equivalent to `next_back` except *panics* if iteration is done.
     #[inline]
     pub fn advance_back(&mut self) {
-        self.back_cursor.advance_back();
-        if !self.back_cursor.is_attached() {
-            self.cursor.detach();
-        } else if self
-            .back_cursor
-            .address()
-            .ptr_eq(&Some(unsafe { self.bounds.assume_init_ref() }.start()))
+        if self.back_cursor.is_attached()
+            && self.back_cursor.address().ptr_eq(&self.cursor.address())
         {
+            // The cursors met, so that was the last element
             self.cursor.detach();
             self.back_cursor.detach()
+        } else {
+            self.back_cursor.advance_back();
         }
     }
 }
 
-impl<'a, K, V> Iterator for RangeMut<'a, K, V> {
+impl<'a, K, V, S> Iterator for RangeMut<'a, K, V, S> {
     type Item = (&'a K, &'a mut V);
 
     #[inline]
@@ -1784,7 +3056,7 @@ impl<'a, K, V> Iterator for RangeMut<'a, K, V> {
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for RangeMut<'a, K, V> {
+impl<'a, K, V, S> DoubleEndedIterator for RangeMut<'a, K, V, S> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         let key_value = self.peek_back_mut()?;
@@ -1793,12 +3065,79 @@ impl<'a, K, V> DoubleEndedIterator for RangeMut<'a, K, V> {
     }
 }
 
-impl<'a, K, V> FusedIterator for RangeMut<'a, K, V> {}
+impl<'a, K, V, S> FusedIterator for RangeMut<'a, K, V, S> {}
 // endregion
 // endregion
 
+impl<'store, K, V, C, S> PtrEq for BTreeMap<'store, K, V, C, S> {
+    /// Whether both maps have the same root node, which means they have the same entries.
+    #[inline]
+    fn ptr_eq(&self, other: &Self) -> bool {
+        self.root.ptr_eq(&other.root) && self.height == other.height
+    }
+}
+
+impl<'store, K, V, C, S> SearchTree for BTreeMap<'store, K, V, C, S> {
+    type Key = K;
+    type Node = NodePtr<K, V, S>;
+
+    #[inline]
+    fn root(&self) -> Option<(NodePtr<K, V, S>, usize)> {
+        self.root.map(|root| (root, self.height))
+    }
+
+    #[inline]
+    unsafe fn keys(&self, node: NodePtr<K, V, S>) -> &[K] {
+        node.as_ref().keys()
+    }
+
+    #[inline]
+    unsafe fn edge(&self, node: NodePtr<K, V, S>, idx: u16) -> NodePtr<K, V, S> {
+        node.as_ref().edge(idx)
+    }
+
+    #[inline]
+    unsafe fn prev(&self, node: NodePtr<K, V, S>) -> Option<NodePtr<K, V, S>> {
+        node.as_ref().prev()
+    }
+
+    #[inline]
+    unsafe fn next(&self, node: NodePtr<K, V, S>) -> Option<NodePtr<K, V, S>> {
+        node.as_ref().next()
+    }
+
+    #[inline]
+    unsafe fn len(&self, node: NodePtr<K, V, S>) -> u16 {
+        node.as_ref().len
+    }
+}
+
+#[cfg(feature = "checked")]
+impl<'store, K, V> BTreeMap<'store, K, V> {
+    /// The stamp a copyable map created from this map records
+    #[inline]
+    pub(crate) fn stamp(&self) -> Stamp {
+        self.store.stamp()
+    }
+
+    /// *Panic*s if this is a copyable map created with `stamp` whose nodes may have been freed
+    #[inline]
+    pub(crate) fn assert_alive(&self, stamp: Stamp) {
+        self.store.assert_alive(stamp, self.root)
+    }
+
+    /// Identifies this map in the survivors of a `tracing_gc`, if it's a copyable map created with
+    /// `stamp`
+    #[inline]
+    pub(crate) fn survivor_key(&self, stamp: Stamp) -> Option<(NodePtr<K, V>, Stamp)> {
+        self.root.map(|root| (root, stamp))
+    }
+}
+
 #[cfg(feature = "copyable")]
-impl<'store, K, V> crate::copyable::sealed::BTree<'store, K, V> for BTreeMap<'store, K, V> {
+impl<'store, K, V, C> crate::copyable::sealed::BTree<'store, K, V>
+    for BTreeMap<'store, K, V, C>
+{
     #[inline]
     fn assert_store(&self, store: &BTreeStore<K, V>) {
         assert_eq!(
@@ -1812,9 +3151,21 @@ impl<'store, K, V> crate::copyable::sealed::BTree<'store, K, V> for BTreeMap<'st
     fn nodes(&self) -> crate::copyable::sealed::NodeIter<'store, K, V> {
         crate::copyable::sealed::NodeIter::new(self.root, self.height)
     }
+
+    #[inline]
+    fn parts(&self) -> (Option<NodePtr<K, V>>, usize, usize) {
+        (self.root, self.height, self.length)
+    }
+
+    #[cfg(feature = "checked")]
+    #[inline]
+    fn survivor_key(&self) -> Option<(NodePtr<K, V>, Stamp)> {
+        // Mutable maps never outlive their nodes
+        None
+    }
 }
 
-unsafe fn as_nullable_ptr<K, V>(ptr: Option<NodePtr<K, V>>) -> *const Node<K, V> {
+unsafe fn as_nullable_ptr<K, V, S>(ptr: Option<NodePtr<K, V, S>>) -> *const Node<K, V, S> {
     match ptr {
         Some(ptr) => ptr.as_ptr().as_ptr(),
         None => std::ptr::null(),
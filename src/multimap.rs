@@ -0,0 +1,294 @@
+use crate::{BTreeMap, BTreeStore, Separator};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::ops::Bound;
+
+/// A b-tree map which can have multiple values per key. Values with the same key are kept in
+/// insertion order.
+///
+/// Entries are stored in the store's nodes, keyed by the key and an insertion counter
+/// ([MultiKey]), so small groups don't allocate (unlike `BTreeMap<K, Vec<V>>`).
+pub struct BTreeMultiMap<'store, K, V> {
+    map: BTreeMap<'store, MultiKey<K>, V>,
+    /// Paired with the next inserted key, to order values with the same key by insertion
+    next_seq: u64,
+}
+
+/// Key of an entry in a [BTreeMultiMap]'s store: the key, and when it was inserted to order values
+/// with the same key.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MultiKey<K> {
+    key: K,
+    seq: u64,
+}
+
+// Safety: the separator is the key's separator, and the sequence number is plain data
+unsafe impl<K: Separator> Separator for MultiKey<K> {
+    const ALIASED: bool = K::ALIASED;
+
+    #[inline]
+    unsafe fn separator(&self) -> Self {
+        MultiKey {
+            key: self.key.separator(),
+            seq: self.seq,
+        }
+    }
+}
+
+impl<'store, K, V> BTreeMultiMap<'store, K, V> {
+    /// Creates an empty multimap.
+    #[inline]
+    pub fn new_in(store: &'store BTreeStore<MultiKey<K>, V>) -> Self {
+        Self {
+            map: BTreeMap::new_in(store),
+            next_seq: 0,
+        }
+    }
+
+    /// Returns the number of values in the multimap.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the multimap contains no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Clears the multimap, removing all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    /// Returns `true` if the multimap has a value for the key.
+    #[inline]
+    pub fn contains_key<Q: Ord + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.get_all(key).next().is_some()
+    }
+
+    /// Returns the number of values for the key.
+    #[inline]
+    pub fn count<Q: Ord + ?Sized>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        self.get_all(key).count()
+    }
+
+    /// Returns the first value inserted for the key, if any.
+    #[inline]
+    pub fn get<Q: Ord + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.get_all(key).next()
+    }
+
+    /// Iterates the values for the key in insertion order.
+    #[inline]
+    pub fn get_all<Q: Ord + ?Sized>(&self, key: &Q) -> GetAll<'_, K, V>
+    where
+        K: Borrow<Q>,
+    {
+        GetAll(self.map.range_by(group_start(key), group_end(key)))
+    }
+
+    /// Iterates the values for the key in insertion order. Values are mutable
+    #[inline]
+    pub fn get_all_mut<Q: Ord + ?Sized>(&mut self, key: &Q) -> GetAllMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+    {
+        GetAllMut(self.map.range_mut_by(group_start(key), group_end(key)))
+    }
+
+    /// Inserts a value after the other values for its key.
+    #[inline]
+    pub fn insert(&mut self, key: K, val: V)
+    where
+        K: Ord + Separator,
+    {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.map.insert(MultiKey { key, seq }, val);
+    }
+
+    /// Removes and returns the first value inserted for the key, if any.
+    #[inline]
+    pub fn remove_one<Q: Ord + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Ord + Separator + Borrow<Q>,
+    {
+        let (&MultiKey { seq, .. }, _) =
+            self.map.range_by(group_start(key), group_end(key)).next()?;
+        self.map
+            .remove_by(|k| k.key.borrow().cmp(key).then(k.seq.cmp(&seq)))
+            .map(|(_, v)| v)
+    }
+
+    /// Removes every value for the key and returns how many were removed.
+    #[inline]
+    pub fn remove_all<Q: Ord + ?Sized>(&mut self, key: &Q) -> usize
+    where
+        K: Ord + Separator + Borrow<Q>,
+    {
+        let mut count = 0;
+        while self.remove_one(key).is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    /// Validates the multimap, *panic*ing if it is invalid.
+    #[inline]
+    pub fn validate(&self)
+    where
+        K: Debug + Ord,
+        V: Debug,
+    {
+        self.map.validate()
+    }
+
+    /// Iterates over the multimap's entries, ordered by key and then by insertion.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.map.iter())
+    }
+}
+
+/// Orders keys before the group of `key`, and never returns [Ordering::Equal]
+#[inline]
+fn group_start<'a, K: Borrow<Q>, Q: Ord + ?Sized>(
+    key: &'a Q,
+) -> Bound<impl FnMut(&MultiKey<K>) -> Ordering + 'a> {
+    Bound::Excluded(move |k: &MultiKey<K>| k.key.borrow().cmp(key).then(Ordering::Greater))
+}
+
+/// Orders keys after the group of `key`, and never returns [Ordering::Equal]
+#[inline]
+fn group_end<'a, K: Borrow<Q>, Q: Ord + ?Sized>(
+    key: &'a Q,
+) -> Bound<impl FnMut(&MultiKey<K>) -> Ordering + 'a> {
+    Bound::Excluded(move |k: &MultiKey<K>| k.key.borrow().cmp(key).then(Ordering::Less))
+}
+
+// region common trait impls
+impl<'store, K: Debug, V: Debug> Debug for BTreeMultiMap<'store, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'store, K: PartialEq, V: PartialEq> PartialEq for BTreeMultiMap<'store, K, V> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<'store, K: Eq, V: Eq> Eq for BTreeMultiMap<'store, K, V> {}
+
+impl<'store, K: Ord + Separator, V> Extend<(K, V)> for BTreeMultiMap<'store, K, V> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, val) in iter {
+            self.insert(key, val);
+        }
+    }
+}
+
+impl<'a, 'store: 'a, K, V> IntoIterator for &'a BTreeMultiMap<'store, K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+// endregion
+
+// region iterators
+/// Iterator over the values for one key
+pub struct GetAll<'a, K, V>(crate::map::Range<'a, MultiKey<K>, V>);
+
+impl<'a, K, V> Iterator for GetAll<'a, K, V> {
+    type Item = &'a V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for GetAll<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V> FusedIterator for GetAll<'a, K, V> {}
+
+/// Iterator over the mutable values for one key
+pub struct GetAllMut<'a, K, V>(crate::map::RangeMut<'a, MultiKey<K>, V>);
+
+impl<'a, K, V> Iterator for GetAllMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for GetAllMut<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+impl<'a, K, V> FusedIterator for GetAllMut<'a, K, V> {}
+
+/// Iterator over all entries
+pub struct Iter<'a, K, V>(crate::map::Iter<'a, MultiKey<K>, V>);
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (&k.key, v))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, v)| (&k.key, v))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, K, V> FusedIterator for Iter<'a, K, V> {}
+// endregion
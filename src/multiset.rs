@@ -0,0 +1,263 @@
+use crate::{BTreeMap, BTreeStore, Separator};
+use std::borrow::Borrow;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+
+/// A b-tree set which counts how many times each value was inserted.
+///
+/// Each distinct value is stored once in the store's nodes, with its count.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BTreeMultiSet<'store, T> {
+    map: BTreeMap<'store, T, usize>,
+    /// Sum of the counts
+    length: usize,
+}
+
+impl<'store, T> BTreeMultiSet<'store, T> {
+    /// Creates an empty multiset.
+    #[inline]
+    pub fn new_in(store: &'store BTreeStore<T, usize>) -> Self {
+        Self {
+            map: BTreeMap::new_in(store),
+            length: 0,
+        }
+    }
+
+    /// Returns the number of values in the multiset, counting duplicates.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Returns the number of distinct values in the multiset.
+    #[inline]
+    pub fn distinct_len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the multiset contains no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Clears the multiset, removing all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.length = 0;
+    }
+
+    /// Returns `true` if the multiset contains the value at least once.
+    #[inline]
+    pub fn contains<U: Ord + ?Sized>(&self, value: &U) -> bool
+    where
+        T: Borrow<U>,
+    {
+        self.map.contains_key(value)
+    }
+
+    /// Returns how many times the value is in the multiset.
+    #[inline]
+    pub fn count<U: Ord + ?Sized>(&self, value: &U) -> usize
+    where
+        T: Borrow<U>,
+    {
+        self.map.get(value).copied().unwrap_or(0)
+    }
+
+    /// Inserts the value once. Returns its new count.
+    #[inline]
+    pub fn insert(&mut self, value: T) -> usize
+    where
+        T: Ord + Separator,
+    {
+        self.insert_many(value, 1)
+    }
+
+    /// Inserts the value `count` times. Returns its new count.
+    ///
+    /// *Panic*s if the length would overflow `usize`. If this *panic*s (including from `Ord` or
+    /// the store's node limit), the multiset is unchanged.
+    #[inline]
+    pub fn insert_many(&mut self, value: T, count: usize) -> usize
+    where
+        T: Ord + Separator,
+    {
+        if count == 0 {
+            return self.count(&value);
+        }
+        let length = self
+            .length
+            .checked_add(count)
+            .expect("BTreeMultiSet length overflowed");
+        let total = match self.map.get_mut(&value) {
+            Some(total) => {
+                *total = total
+                    .checked_add(count)
+                    .expect("BTreeMultiSet count overflowed");
+                *total
+            }
+            None => {
+                self.map.insert(value, count);
+                count
+            }
+        };
+        self.length = length;
+        total
+    }
+
+    /// Removes the value once. Returns `true` if it was present.
+    #[inline]
+    pub fn remove_one<U: Ord + ?Sized>(&mut self, value: &U) -> bool
+    where
+        T: Ord + Separator + Borrow<U>,
+    {
+        match self.map.get_mut(value) {
+            None => false,
+            Some(1) => self.remove_all(value) == 1,
+            Some(count) => {
+                *count -= 1;
+                self.length -= 1;
+                true
+            }
+        }
+    }
+
+    /// Removes every copy of the value and returns how many were removed.
+    #[inline]
+    pub fn remove_all<U: Ord + ?Sized>(&mut self, value: &U) -> usize
+    where
+        T: Ord + Separator + Borrow<U>,
+    {
+        let count = self.map.remove(value).unwrap_or(0);
+        self.length -= count;
+        count
+    }
+
+    /// Validates the multiset, *panic*ing if it is invalid.
+    #[inline]
+    pub fn validate(&self)
+    where
+        T: Debug + Ord,
+    {
+        self.map.validate();
+        assert_eq!(
+            self.map.values().sum::<usize>(),
+            self.length,
+            "length isn't the sum of counts"
+        );
+        assert!(self.map.values().all(|&c| c > 0), "has a count of 0");
+    }
+
+    /// Iterates over the distinct values and their counts, in order.
+    #[inline]
+    pub fn iter_counts(&self) -> crate::map::Iter<'_, T, usize> {
+        self.map.iter()
+    }
+
+    /// Iterates over the values in order, repeating each by its count.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.map.iter(),
+            front: None,
+            back: None,
+            remaining: self.length,
+        }
+    }
+}
+
+// region common trait impls
+impl<'store, T: Debug> Debug for BTreeMultiSet<'store, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter_counts()).finish()
+    }
+}
+
+impl<'store, T: Ord + Separator> Extend<T> for BTreeMultiSet<'store, T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a, 'store: 'a, T> IntoIterator for &'a BTreeMultiSet<'store, T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+// endregion
+
+// region Iter
+/// Iterator over the values, repeating each by its count
+pub struct Iter<'a, T> {
+    inner: crate::map::Iter<'a, T, usize>,
+    /// Value at the front and how many more times to yield it
+    front: Option<(&'a T, usize)>,
+    /// Value at the back and how many more times to yield it
+    back: Option<(&'a T, usize)>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        if !matches!(self.front, Some((_, n)) if n > 0) {
+            // When the front meets the back, take from the back's remaining count
+            self.front = Some(match self.inner.next() {
+                Some((value, &count)) => (value, count),
+                None => self.back.take().unwrap(),
+            });
+        }
+        let (value, n) = self.front.as_mut().unwrap();
+        *n -= 1;
+        Some(*value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        if !matches!(self.back, Some((_, n)) if n > 0) {
+            self.back = Some(match self.inner.next_back() {
+                Some((value, &count)) => (value, count),
+                None => self.front.take().unwrap(),
+            });
+        }
+        let (value, n) = self.back.as_mut().unwrap();
+        *n -= 1;
+        Some(*value)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<'a, T> FusedIterator for Iter<'a, T> {}
+// endregion
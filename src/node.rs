@@ -12,59 +12,72 @@ pub const M: usize = 8;
 
 /// A node in the b+tree. This can be either leaf node or internal node depending on the implicit
 /// height.
-pub struct Node<K, V> {
+pub struct Node<K, V, S = ()> {
     /// Parent node. We use [NonNull] in part because [LeafNode] must be covariant in `K` and `V`.
-    pub parent: Option<NodePtr<K, V>>,
+    pub parent: Option<NodePtr<K, V, S>>,
     /// This node's index into the parent node's `edges` array.
     /// `*node.parent.d.internal().edges[node.parent_idx]` should be the same thing as `node`.
     /// This is only guaranteed to be initialized when `parent` is non-null.
     pub parent_idx: MaybeUninit<u16>,
     /// Total # Of keys and values, not including children.
     pub len: u16,
-    /// Keys storage. The first `len` are initialized.
+    /// Whether the implicit height is 0, so [BTreeStore::clear](crate::BTreeStore::clear) can drop
+    /// a node's entries without knowing its tree.
+    pub is_leaf: bool,
+    /// Whether this internal node's keys are separators it owns, rather than bitwise copies which
+    /// must not be dropped (see [Separator](crate::Separator)). Always `false` in leaves.
+    pub owns_separators: bool,
+    /// Keys storage. The first `len` are initialized. In internal nodes these are separators, see
+    /// [Separator](crate::Separator).
     pub keys: [MaybeUninit<K>; M],
     /// Values or children depending on the implicit height.
-    pub d: NodeData<K, V>,
+    pub d: NodeData<K, V, S>,
 }
 
 /// Contains leaf/internal-specific data. An untagged union, whether it contains leaf or internal
 /// node data is determined by the implicit height.
-pub union NodeData<K, V> {
+pub union NodeData<K, V, S = ()> {
     /// Leaf data. Only exists if the implicit height is 0.
-    pub leaf: ManuallyDrop<LeafData<K, V>>,
+    pub leaf: ManuallyDrop<LeafData<K, V, S>>,
     /// Internal data. Only exists if the implicit height is positive.
-    pub internal: ManuallyDrop<InternalData<K, V>>,
+    pub internal: ManuallyDrop<InternalData<K, V, S>>,
 }
 
 /// Leaf data. Only exists if the implicit height is 0.
-pub struct LeafData<K, V> {
+pub struct LeafData<K, V, S = ()> {
     /// Vals storage. The first `len` are initialized.
     pub vals: [MaybeUninit<V>; M],
     /// Previous leaf node in the linked list.
-    pub prev: Option<NodePtr<K, V>>,
+    pub prev: Option<NodePtr<K, V, S>>,
     /// Next leaf node in the linked list.
-    pub next: Option<NodePtr<K, V>>,
+    pub next: Option<NodePtr<K, V, S>>,
 }
 
 /// Internal data. Only exists if the implicit height is positive.
-pub struct InternalData<K, V> {
+pub struct InternalData<K, V, S = ()> {
     /// Pointers to the node's children. `edges[i]` is the child whose keys are between
     /// `keys[i - 1]` and `keys[i]` (if either doesn't exist, just before or after the other). The
     /// first `len + 1` are initialized.
-    pub edges: [MaybeUninit<NodePtr<K, V>>; M + 1],
+    pub edges: [MaybeUninit<NodePtr<K, V, S>>; M + 1],
+    /// Cached summaries of the children's subtrees, see [Summary](crate::Summary). `summaries[i]`
+    /// summarizes `edges[i]`. Node operations don't move these with their edges: the map rewrites
+    /// all of a node's summaries after its edges change.
+    pub summaries: [MaybeUninit<S>; M + 1],
 }
 
 /// A managed, non-null pointer to a node. This is either a pointer to a leaf node or internal node,
 /// depending on the implicit height.
-pub type NodePtr<K, V> = UnsafeRef<Node<K, V>>;
+pub type NodePtr<K, V, S = ()> = UnsafeRef<Node<K, V, S>>;
 
-impl<K, V> Node<K, V> {
+impl<K, V, S> Node<K, V, S> {
     #[inline]
     pub fn leaf() -> Self {
         Node {
             parent: None,
             parent_idx: MaybeUninit::uninit(),
             len: 0,
+            is_leaf: true,
+            owns_separators: false,
             keys: maybe_uninit_array(),
             d: NodeData {
                 leaf: ManuallyDrop::new(LeafData {
@@ -77,22 +90,25 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub fn internal() -> Self {
+    pub fn internal(owns_separators: bool) -> Self {
         Node {
             parent: None,
             parent_idx: MaybeUninit::uninit(),
             len: 0,
+            is_leaf: false,
+            owns_separators,
             keys: maybe_uninit_array(),
             d: NodeData {
                 internal: ManuallyDrop::new(InternalData {
                     edges: maybe_uninit_array(),
+                    summaries: maybe_uninit_array(),
                 }),
             },
         }
     }
 
     #[inline]
-    pub fn parent(&self) -> Option<(NodePtr<K, V>, u16)> {
+    pub fn parent(&self) -> Option<(NodePtr<K, V, S>, u16)> {
         self.parent
             .map(|p| (p, unsafe { self.parent_idx.assume_init() }))
     }
@@ -104,7 +120,7 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub fn set_parent(&mut self, parent: NodePtr<K, V>, parent_idx: u16) {
+    pub fn set_parent(&mut self, parent: NodePtr<K, V, S>, parent_idx: u16) {
         self.parent = Some(parent);
         self.parent_idx.write(parent_idx);
     }
@@ -116,22 +132,22 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub unsafe fn prev(&self) -> Option<NodePtr<K, V>> {
+    pub unsafe fn prev(&self) -> Option<NodePtr<K, V, S>> {
         self.d.leaf().prev
     }
 
     #[inline]
-    pub unsafe fn set_prev(&mut self, prev: Option<NodePtr<K, V>>) {
+    pub unsafe fn set_prev(&mut self, prev: Option<NodePtr<K, V, S>>) {
         self.d.leaf_mut().prev = prev;
     }
 
     #[inline]
-    pub unsafe fn next(&self) -> Option<NodePtr<K, V>> {
+    pub unsafe fn next(&self) -> Option<NodePtr<K, V, S>> {
         self.d.leaf().next
     }
 
     #[inline]
-    pub unsafe fn set_next(&mut self, next: Option<NodePtr<K, V>>) {
+    pub unsafe fn set_next(&mut self, next: Option<NodePtr<K, V, S>>) {
         self.d.leaf_mut().next = next;
     }
 
@@ -228,7 +244,7 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub unsafe fn edge(&self, idx: u16) -> NodePtr<K, V> {
+    pub unsafe fn edge(&self, idx: u16) -> NodePtr<K, V, S> {
         debug_assert!(idx < self.len + 1);
         self.d
             .internal()
@@ -238,7 +254,7 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub unsafe fn edge_mut(&mut self, idx: u16) -> &mut NodePtr<K, V> {
+    pub unsafe fn edge_mut(&mut self, idx: u16) -> &mut NodePtr<K, V, S> {
         debug_assert!(idx < self.len + 1);
         self.d
             .internal_mut()
@@ -247,6 +263,30 @@ impl<K, V> Node<K, V> {
             .assume_init_mut()
     }
 
+    /// Cached summary of the child at `idx`. It must have been written since the edges changed.
+    #[inline]
+    pub unsafe fn summary(&self, idx: u16) -> S
+    where
+        S: Copy,
+    {
+        debug_assert!(idx < self.len + 1);
+        self.d
+            .internal()
+            .summaries
+            .get_unchecked(idx as usize)
+            .assume_init()
+    }
+
+    #[inline]
+    pub unsafe fn set_summary(&mut self, idx: u16, summary: S) {
+        debug_assert!(idx < self.len + 1);
+        self.d
+            .internal_mut()
+            .summaries
+            .get_unchecked_mut(idx as usize)
+            .write(summary);
+    }
+
     #[inline]
     pub unsafe fn keys(&self) -> &[K] {
         &*(&self.keys[..self.len as usize] as *const [MaybeUninit<K>] as *const [K])
@@ -270,16 +310,16 @@ impl<K, V> Node<K, V> {
     }
 
     #[inline]
-    pub unsafe fn edges(&self) -> &[NodePtr<K, V>] {
+    pub unsafe fn edges(&self) -> &[NodePtr<K, V, S>] {
         &*(&self.d.internal().edges[..(self.len + 1) as usize]
-            as *const [MaybeUninit<NodePtr<K, V>>] as *const [NodePtr<K, V>])
+            as *const [MaybeUninit<NodePtr<K, V, S>>] as *const [NodePtr<K, V, S>])
     }
 
     #[allow(unused)]
     #[inline]
-    pub unsafe fn edges_mut(&mut self) -> &mut [NodePtr<K, V>] {
+    pub unsafe fn edges_mut(&mut self) -> &mut [NodePtr<K, V, S>] {
         &mut *(&mut self.d.internal_mut().edges[..(self.len + 1) as usize]
-            as *mut [MaybeUninit<NodePtr<K, V>>] as *mut [NodePtr<K, V>])
+            as *mut [MaybeUninit<NodePtr<K, V, S>>] as *mut [NodePtr<K, V, S>])
     }
 
     #[inline]
@@ -364,7 +404,7 @@ impl<K, V> Node<K, V> {
 
     /// Doesn't rebalance. You must call `set_parent` on the edge beforehand.
     #[inline]
-    pub unsafe fn insert_edge(&mut self, idx: u16, after_key: bool, key: K, edge: NodePtr<K, V>) {
+    pub unsafe fn insert_edge(&mut self, idx: u16, after_key: bool, key: K, edge: NodePtr<K, V, S>) {
         debug_assert!(idx <= self.len);
         debug_assert!(
             (self.len as usize) < M,
@@ -420,7 +460,7 @@ impl<K, V> Node<K, V> {
 
     /// You must call `set_parent` on the edge beforehand.
     #[inline]
-    pub unsafe fn set_last_edge(&mut self, edge: NodePtr<K, V>) {
+    pub unsafe fn set_last_edge(&mut self, edge: NodePtr<K, V, S>) {
         debug_assert_eq!(
             edge.as_ref().parent_idx(),
             Some(self.len),
@@ -459,7 +499,7 @@ impl<K, V> Node<K, V> {
 
     /// Doesn't rebalance.
     #[inline]
-    pub unsafe fn remove_edge(&mut self, idx: u16, after_key: bool) -> (K, NodePtr<K, V>) {
+    pub unsafe fn remove_edge(&mut self, idx: u16, after_key: bool) -> (K, NodePtr<K, V, S>) {
         debug_assert!(idx < self.len);
         debug_assert!(self.len > 0);
         let edge_idx = match after_key {
@@ -505,7 +545,7 @@ impl<K, V> Node<K, V> {
 
     /// Doesn't rebalance, removes edge after key
     #[inline]
-    pub unsafe fn remove_last_edge(&mut self) -> (K, NodePtr<K, V>) {
+    pub unsafe fn remove_last_edge(&mut self) -> (K, NodePtr<K, V, S>) {
         debug_assert!(self.len > 0);
         debug_assert_eq!(
             self.edge(self.len).as_ref().parent_idx(),
@@ -539,16 +579,27 @@ impl<K, V> Node<K, V> {
         old_val
     }
 
-    /// This becomes the left node, returns the right node and replaces the key with the median key
-    /// ("split key").
+    /// The last key of the left node and first key of the right node after
+    /// [split_leaf](Self::split_leaf) inserts `key` at `idx`, so callers can create the separator
+    /// before modifying anything (in case `Clone` *panic*s).
+    #[inline]
+    pub unsafe fn split_leaf_keys<'a>(&'a self, idx: u16, key: &'a K) -> (&'a K, &'a K) {
+        let median = self.len / 2;
+        let key_at = |i: u16| match i.cmp(&idx) {
+            Ordering::Less => self.key(i),
+            Ordering::Equal => key,
+            Ordering::Greater => self.key(i - 1),
+        };
+        (key_at(median - 1), key_at(median))
+    }
+
+    /// This becomes the left node, returns the right node, whose first key is the median key
+    /// ("split key"). Doesn't run any user code, so it can't *panic*.
     ///
     /// `self.d.leaf().prev`, `right.d.leaf().next`, and `self.d.leaf().prev.next` are set, but you need to set
     /// `self.d.leaf().next`, `right.d.leaf().prev`, and `right.d.leaf().next.prev`.
     #[inline]
-    pub unsafe fn split_leaf(&mut self, mut idx: u16, key: &mut K, mut val: V) -> Node<K, V>
-    where
-        K: Clone,
-    {
+    pub unsafe fn split_leaf(&mut self, mut idx: u16, mut key: K, mut val: V) -> Node<K, V, S> {
         debug_assert!(idx <= self.len);
         debug_assert!(
             self.len as usize >= M / 2,
@@ -560,13 +611,13 @@ impl<K, V> Node<K, V> {
 
         // Insert so that idx is median, and key and val point to the median val
         while idx < median {
-            swap(self.key_mut(idx), key);
+            swap(self.key_mut(idx), &mut key);
             swap(self.val_mut(idx), &mut val);
             idx += 1;
         }
         while idx > median {
             idx -= 1;
-            swap(self.key_mut(idx), key);
+            swap(self.key_mut(idx), &mut key);
             swap(self.val_mut(idx), &mut val);
         }
 
@@ -579,9 +630,9 @@ impl<K, V> Node<K, V> {
             &mut right.d.leaf_mut().vals[1..median as usize + 1],
             &self.d.leaf().vals[median as usize..self.len as usize],
         );
-        // Remember: this is a B+ tree, so we copy the key in the leaf node, and write the val
-        // instead of propagating it to the internal.
-        right.keys[0].write(key.clone());
+        // Remember: this is a B+ tree, so the key stays in the leaf node, and the caller
+        // propagates a separator to the internal.
+        right.keys[0].write(key);
         right.d.leaf_mut().vals[0].write(val);
         right.len = self.len - median + 1;
         self.len = median;
@@ -599,8 +650,8 @@ impl<K, V> Node<K, V> {
         &mut self,
         mut idx: u16,
         key: &mut K,
-        mut edge: NodePtr<K, V>,
-    ) -> Node<K, V> {
+        mut edge: NodePtr<K, V, S>,
+    ) -> Node<K, V, S> {
         debug_assert!(idx <= self.len);
         debug_assert!(
             self.len as usize >= M / 2,
@@ -613,7 +664,7 @@ impl<K, V> Node<K, V> {
         );
 
         let median = self.len / 2;
-        let mut right = Node::internal();
+        let mut right = Node::internal(self.owns_separators);
 
         // Insert so that idx is median, and key and val point to the median val
         while idx < median {
@@ -663,7 +714,7 @@ impl<K, V> Node<K, V> {
     /// Absorbs all of `prev`'s keys and values and also its `prev`. Afterwards `prev` should be
     /// removed from the parent and discarded, and `self.prev.next` should be set to `self`.
     #[inline]
-    pub unsafe fn merge_prev_leaf(&mut self, prev: &mut Node<K, V>) {
+    pub unsafe fn merge_prev_leaf(&mut self, prev: &mut Node<K, V, S>) {
         debug_assert!(self.prev().ptr_eq(&Some(NodePtr::from_ref(prev))));
         debug_assert!(
             prev.parent.ptr_eq(&self.parent),
@@ -704,7 +755,7 @@ impl<K, V> Node<K, V> {
     /// Absorbs all of `next`'s keys and values and also its `next`. Afterwards `next` should be
     /// discarded and removed from the parent, and `self.next.prev` should be set to `self`.
     #[inline]
-    pub unsafe fn merge_next_leaf(&mut self, next: &mut Node<K, V>) {
+    pub unsafe fn merge_next_leaf(&mut self, next: &mut Node<K, V, S>) {
         debug_assert!(self.next().ptr_eq(&Some(NodePtr::from_ref(next))));
         debug_assert!(
             self.parent.ptr_eq(&next.parent),
@@ -735,7 +786,7 @@ impl<K, V> Node<K, V> {
     /// Absorbs all of `prev`'s key and edges. Beforehand `prev`'s edges' parent nodes should be
     /// updated to `self`, and afterwards `prev` should be removed from the parent and discarded.
     #[inline]
-    pub unsafe fn merge_prev_internal(&mut self, middle_key: K, prev: &mut Node<K, V>) {
+    pub unsafe fn merge_prev_internal(&mut self, middle_key: K, prev: &mut Node<K, V, S>) {
         debug_assert!(
             prev.parent.ptr_eq(&self.parent),
             "sanity check failed: prev.parent != self.parent (the failure happened before this function call, it was only detected now)"
@@ -782,7 +833,7 @@ impl<K, V> Node<K, V> {
     /// Absorbs all of `next`'s key and edges. Beforehand `next`'s edges' parent nodes should be
     /// updated to `self`, and afterwards `next` should be removed from the parent and discarded.
     #[inline]
-    pub unsafe fn merge_next_internal(&mut self, middle_key: K, next: &mut Node<K, V>) {
+    pub unsafe fn merge_next_internal(&mut self, middle_key: K, next: &mut Node<K, V, S>) {
         debug_assert!(
             self.parent.ptr_eq(&next.parent),
             "sanity check failed: self.parent != next.parent (the failure happened before this function call, it was only detected now)"
@@ -816,63 +867,24 @@ impl<K, V> Node<K, V> {
     }
 }
 
-impl<K, V> NodeData<K, V> {
-    pub unsafe fn leaf(&self) -> &LeafData<K, V> {
+impl<K, V, S> NodeData<K, V, S> {
+    pub unsafe fn leaf(&self) -> &LeafData<K, V, S> {
         &self.leaf
     }
 
-    pub unsafe fn leaf_mut(&mut self) -> &mut LeafData<K, V> {
+    pub unsafe fn leaf_mut(&mut self) -> &mut LeafData<K, V, S> {
         &mut self.leaf
     }
 
-    pub unsafe fn internal(&self) -> &InternalData<K, V> {
+    pub unsafe fn internal(&self) -> &InternalData<K, V, S> {
         &self.internal
     }
 
-    pub unsafe fn internal_mut(&mut self) -> &mut InternalData<K, V> {
+    pub unsafe fn internal_mut(&mut self) -> &mut InternalData<K, V, S> {
         &mut self.internal
     }
 }
 
-#[inline]
-pub unsafe fn normalize_address<K, V>(
-    node: NodePtr<K, V>,
-    idx: u16,
-) -> Option<(NodePtr<K, V>, u16)> {
-    let node_ref = node.as_ref();
-    if idx < node_ref.len {
-        Some((node, idx))
-    } else {
-        debug_assert_eq!(idx, node_ref.len);
-        node_ref.next().map(|node| (node, 0))
-    }
-}
-
-#[inline]
-pub unsafe fn address_before<K, V>(node: NodePtr<K, V>, idx: u16) -> Option<(NodePtr<K, V>, u16)> {
-    let node_ref = node.as_ref();
-    if idx > 0 {
-        Some((node, idx - 1))
-    } else {
-        node_ref.prev().map(|node| (node, node.as_ref().len - 1))
-    }
-}
-
-#[inline]
-pub unsafe fn address_after<K, V>(node: NodePtr<K, V>, idx: u16) -> Option<(NodePtr<K, V>, u16)> {
-    let node_ref = node.as_ref();
-    match idx.cmp(&(node_ref.len - 1)) {
-        Ordering::Less => Some((node, idx + 1)),
-        Ordering::Equal => node_ref.next().map(|node| (node, 0)),
-        Ordering::Greater => {
-            // Not normalize AND we want the address after anyways. Currently this branch is never
-            // actually reached, but if it was this is what we would do
-            debug_assert_eq!(idx, node_ref.len);
-            node_ref.next().map(|node| (node, 1))
-        }
-    }
-}
-
 #[inline]
 unsafe fn unsafe_copy_slice_overlapping<T>(
     data: &mut [T],
@@ -0,0 +1,431 @@
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+use std::ptr;
+
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::compare::{Comparator, OrdComparator};
+use crate::map::{alloc_leaves, even_chunk, Range, RangeMut};
+use crate::node::{NodePtr, M};
+use crate::search::NodeBounds;
+use crate::{BTreeMap, BTreeSet, BTreeStore, Separator, Summary};
+
+// region methods
+impl<'store, K, V, C, S> BTreeMap<'store, K, V, C, S> {
+    /// Iterates over the map's key-value pairs in parallel, splitting the work by subtrees.
+    #[inline]
+    pub fn par_iter(&self) -> ParIter<'_, K, V, S>
+    where
+        K: Sync,
+        V: Sync,
+    {
+        ParIter(Subtrees::new(all_bounds(self)))
+    }
+
+    /// Iterates over the map's key-value pairs within the given range in parallel, splitting the
+    /// work by subtrees.
+    #[inline]
+    pub fn par_range<Q: ?Sized>(&self, bounds: impl RangeBounds<Q>) -> ParIter<'_, K, V, S>
+    where
+        K: Sync + Borrow<Q>,
+        V: Sync,
+        C: Comparator<Q>,
+    {
+        ParIter(Subtrees::new(self.node_bounds(bounds)))
+    }
+}
+
+impl<'store, K, V, S> BTreeMap<'store, K, V, OrdComparator, S> {
+    /// [BTreeMap::from_sorted_iter_in], but checks the order and fills the leaves in parallel
+    /// chunks. Only those steps are parallel: the store isn't [Sync] and its arena can't adopt
+    /// nodes allocated elsewhere, so allocating every node and stitching the internal levels on
+    /// top of the leaves runs on the calling thread.
+    ///
+    /// *Panic*s if the keys aren't strictly ascending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use btree_plus_store::{BTreeMap, BTreeStore};
+    /// let store = BTreeStore::new();
+    /// let map = BTreeMap::par_from_sorted_vec_in(&store, (0..100).map(|i| (i, i * 2)).collect());
+    /// assert_eq!(map.get(&50), Some(&100));
+    /// ```
+    pub fn par_from_sorted_vec_in(
+        store: &'store BTreeStore<K, V, S>,
+        mut entries: Vec<(K, V)>,
+    ) -> Self
+    where
+        K: Ord + Separator + Send + Sync,
+        V: Send,
+        S: Summary<K, V>,
+    {
+        let mut map = Self::new_in(store);
+        let length = entries.len();
+        // SAFETY: tasks only share the keys, which are `Sync`
+        let source = SendPtr(entries.as_mut_ptr());
+        assert!(
+            (1..length)
+                .into_par_iter()
+                .all(|i| unsafe { (*source.get().add(i - 1)).0 < (*source.get().add(i)).0 }),
+            "BTreeMap::par_from_sorted_vec_in keys aren't strictly ascending"
+        );
+        if length == 0 {
+            return map;
+        }
+
+        let level = alloc_leaves(store, length.div_ceil(M));
+        let num_leaves = level.len();
+        // The entries are moved into the leaves, so the vector only deallocates its buffer
+        let leaves = SendPtr(level.as_ptr() as *mut NodePtr<K, V, S>);
+        unsafe { entries.set_len(0) };
+        (0..num_leaves).into_par_iter().for_each(|i| unsafe {
+            // SAFETY: each leaf and chunk of entries is accessed by exactly one task
+            let mut leaf = *leaves.get().add(i);
+            for (idx, entry_idx) in even_chunk(length, num_leaves, i).enumerate() {
+                let (key, val) = ptr::read(source.get().add(entry_idx));
+                leaf.as_mut().insert_val(idx as u16, key, val);
+            }
+        });
+        map.stitch_leaves(level, length);
+        map
+    }
+}
+
+impl<'store, K, V, C> BTreeMap<'store, K, V, C> {
+    /// Iterates over the map's key-value pairs in parallel, with mutable values.
+    #[inline]
+    pub fn par_iter_mut(&mut self) -> ParIterMut<'_, K, V>
+    where
+        K: Sync,
+        V: Send,
+    {
+        ParIterMut(Subtrees::new(all_bounds(self)))
+    }
+
+    /// Iterates over the map's key-value pairs within the given range in parallel, with mutable
+    /// values.
+    #[inline]
+    pub fn par_range_mut<Q: ?Sized>(&mut self, bounds: impl RangeBounds<Q>) -> ParIterMut<'_, K, V>
+    where
+        K: Sync + Borrow<Q>,
+        V: Send,
+        C: Comparator<Q>,
+    {
+        ParIterMut(Subtrees::new(self.node_bounds(bounds)))
+    }
+}
+
+impl<'store, T> BTreeSet<'store, T> {
+    /// Iterates over the set's values in parallel, splitting the work by subtrees.
+    #[inline]
+    pub fn par_iter(&self) -> ParSetIter<'_, T>
+    where
+        T: Sync,
+    {
+        ParSetIter(self.0.par_iter())
+    }
+
+    /// Iterates over the set's values within the given range in parallel, splitting the work by
+    /// subtrees.
+    #[inline]
+    pub fn par_range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> ParSetIter<'_, T>
+    where
+        T: Sync + Borrow<U>,
+    {
+        ParSetIter(self.0.par_range(bounds))
+    }
+}
+// endregion
+
+// region IntoParallelIterator
+impl<'a, 'store: 'a, K: Sync, V: Sync, C, S> IntoParallelIterator
+    for &'a BTreeMap<'store, K, V, C, S>
+{
+    type Iter = ParIter<'a, K, V, S>;
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<'a, 'store: 'a, K: Sync, V: Send, C> IntoParallelIterator
+    for &'a mut BTreeMap<'store, K, V, C>
+{
+    type Iter = ParIterMut<'a, K, V>;
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter_mut()
+    }
+}
+
+impl<'a, 'store: 'a, T: Sync> IntoParallelIterator for &'a BTreeSet<'store, T> {
+    type Iter = ParSetIter<'a, T>;
+    type Item = &'a T;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+// endregion
+
+// region Subtrees
+/// Bounds of all the map's entries
+#[inline]
+fn all_bounds<K, V, C, S>(map: &BTreeMap<'_, K, V, C, S>) -> Option<NodeBounds<NodePtr<K, V, S>>> {
+    let (first, last) = map.first_leaf().zip(map.last_leaf())?;
+    Some(NodeBounds {
+        start_node: first,
+        end_node: last,
+        start_index: 0,
+        end_index: unsafe { last.as_ref().len } - 1,
+    })
+}
+
+/// Part of a b-tree range: the edges `first..=last` of `node` (or the entries, if it's a leaf),
+/// where the range starts at `start` in the first edge's subtree, and ends at `end` in the last
+/// edge's subtree. `None` means the range includes the whole subtree on that side.
+///
+/// Splitting divides the edges, or descends into the edge if there's only one, so each part is a
+/// sequence of whole subtrees, except at the range's ends.
+struct Subtrees<'a, K, V, S> {
+    node: NodePtr<K, V, S>,
+    height: usize,
+    first: u16,
+    last: u16,
+    start: Option<(NodePtr<K, V, S>, u16)>,
+    end: Option<(NodePtr<K, V, S>, u16)>,
+    _p: PhantomData<(&'a K, &'a V)>,
+}
+
+impl<'a, K, V, S> Subtrees<'a, K, V, S> {
+    /// The range's lowest common subtree
+    #[inline]
+    fn new(bounds: Option<NodeBounds<NodePtr<K, V, S>>>) -> Option<Self> {
+        let bounds = bounds?;
+        let (mut left, mut right) = (bounds.start_node, bounds.end_node);
+        let (mut first, mut last) = (bounds.start_index, bounds.end_index);
+        let mut height = 0;
+        while left != right {
+            let (left_parent, left_idx) = unsafe { left.as_ref() }.parent().unwrap();
+            let (right_parent, right_idx) = unsafe { right.as_ref() }.parent().unwrap();
+            (left, right) = (left_parent, right_parent);
+            (first, last) = (left_idx, right_idx);
+            height += 1;
+        }
+        // The range is inverted
+        if first > last {
+            return None;
+        }
+        Some(Self {
+            node: left,
+            height,
+            first,
+            last,
+            start: Some(bounds.start()),
+            end: Some(bounds.end()),
+            _p: PhantomData,
+        })
+    }
+
+    /// Splits off the second half of the edges, if there are multiple subtrees. Leaves aren't
+    /// split, because they have few entries.
+    #[inline]
+    fn split(mut self) -> (Self, Option<Self>) {
+        unsafe {
+            while self.height > 0 && self.first == self.last {
+                let child = self.node.as_ref().edge(self.first);
+                self.height -= 1;
+                self.first = match self.start {
+                    Some((leaf, idx)) => edge_towards(leaf, idx, self.height),
+                    None => 0,
+                };
+                self.last = match self.end {
+                    Some((leaf, idx)) => edge_towards(leaf, idx, self.height),
+                    None if self.height == 0 => child.as_ref().len - 1,
+                    None => child.as_ref().len,
+                };
+                self.node = child;
+            }
+        }
+        if self.height == 0 {
+            return (self, None);
+        }
+        let middle = self.first + (self.last - self.first) / 2;
+        let right = Self {
+            node: self.node,
+            height: self.height,
+            first: middle + 1,
+            last: self.last,
+            start: None,
+            end: self.end.take(),
+            _p: PhantomData,
+        };
+        self.last = middle;
+        (self, Some(right))
+    }
+
+    /// The first and last entry, to iterate through the leaves
+    #[inline]
+    fn bounds(&self) -> NodeBounds<NodePtr<K, V, S>> {
+        unsafe {
+            let (start_node, start_index) = match (self.height, self.start) {
+                (0, _) => (self.node, self.first),
+                (_, Some(start)) => start,
+                (height, None) => {
+                    let mut node = self.node.as_ref().edge(self.first);
+                    for _ in 1..height {
+                        node = node.as_ref().edge(0);
+                    }
+                    (node, 0)
+                }
+            };
+            let (end_node, end_index) = match (self.height, self.end) {
+                (0, _) => (self.node, self.last),
+                (_, Some(end)) => end,
+                (height, None) => {
+                    let mut node = self.node.as_ref().edge(self.last);
+                    for _ in 1..height {
+                        node = node.as_ref().edge(node.as_ref().len);
+                    }
+                    (node, node.as_ref().len - 1)
+                }
+            };
+            NodeBounds {
+                start_node,
+                end_node,
+                start_index,
+                end_index,
+            }
+        }
+    }
+}
+
+/// Index of the edge at `height` whose subtree contains the leaf, or the entry index at height 0
+#[inline]
+unsafe fn edge_towards<K, V, S>(leaf: NodePtr<K, V, S>, idx: u16, height: usize) -> u16 {
+    if height == 0 {
+        return idx;
+    }
+    let mut node = leaf;
+    for _ in 1..height {
+        node = node.as_ref().parent().unwrap().0;
+    }
+    node.as_ref().parent_idx().unwrap()
+}
+
+/// Pointer to data which parallel tasks access disjoint parts of
+struct SendPtr<T>(*mut T);
+
+impl<T> SendPtr<T> {
+    /// Accessor, so closures capture the whole wrapper instead of its field
+    #[inline]
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
+// SAFETY: the function which creates it requires the entries to be `Send`, and tasks only move
+// them (or write to leaves containing them) on their own thread
+unsafe impl<T> Send for SendPtr<T> {}
+unsafe impl<T> Sync for SendPtr<T> {}
+
+/// Produces shared entries
+struct IterProducer<'a, K, V, S>(Subtrees<'a, K, V, S>);
+
+/// Produces entries with mutable values
+struct IterMutProducer<'a, K, V, S>(Subtrees<'a, K, V, S>);
+
+// SAFETY: producers only read the nodes (and write the values, for `IterMutProducer`), which the
+// map's borrow keeps alive and unmodified. Each entry is in exactly one producer, and the store
+// isn't accessed, so it doesn't need to be `Sync`.
+unsafe impl<'a, K: Sync, V: Sync, S> Send for IterProducer<'a, K, V, S> {}
+unsafe impl<'a, K: Sync, V: Send, S> Send for IterMutProducer<'a, K, V, S> {}
+
+impl<'a, K: Sync + 'a, V: Sync + 'a, S: 'a> UnindexedProducer for IterProducer<'a, K, V, S> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn split(self) -> (Self, Option<Self>) {
+        let (left, right) = self.0.split();
+        (Self(left), right.map(Self))
+    }
+
+    #[inline]
+    fn fold_with<F: Folder<Self::Item>>(self, folder: F) -> F {
+        folder.consume_iter(Range::from_bounds(Some(self.0.bounds())))
+    }
+}
+
+impl<'a, K: Sync + 'a, V: Send + 'a, S: 'a> UnindexedProducer for IterMutProducer<'a, K, V, S> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn split(self) -> (Self, Option<Self>) {
+        let (left, right) = self.0.split();
+        (Self(left), right.map(Self))
+    }
+
+    #[inline]
+    fn fold_with<F: Folder<Self::Item>>(self, folder: F) -> F {
+        folder.consume_iter(RangeMut::from_bounds(Some(self.0.bounds())))
+    }
+}
+// endregion
+
+// region parallel iterators
+/// Parallel iterator over a map's key-value pairs, or those within a range
+pub struct ParIter<'a, K, V, S = ()>(Option<Subtrees<'a, K, V, S>>);
+
+// SAFETY: see the producers
+unsafe impl<'a, K: Sync, V: Sync, S> Send for ParIter<'a, K, V, S> {}
+
+impl<'a, K: Sync + 'a, V: Sync + 'a, S: 'a> ParallelIterator for ParIter<'a, K, V, S> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn drive_unindexed<Co: UnindexedConsumer<Self::Item>>(self, consumer: Co) -> Co::Result {
+        match self.0 {
+            None => rayon::iter::empty().drive_unindexed(consumer),
+            Some(subtrees) => bridge_unindexed(IterProducer(subtrees), consumer),
+        }
+    }
+}
+
+/// Parallel iterator over a map's key-value pairs with mutable values, or those within a range
+pub struct ParIterMut<'a, K, V>(Option<Subtrees<'a, K, V, ()>>);
+
+// SAFETY: see the producers
+unsafe impl<'a, K: Sync, V: Send> Send for ParIterMut<'a, K, V> {}
+
+impl<'a, K: Sync + 'a, V: Send + 'a> ParallelIterator for ParIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn drive_unindexed<Co: UnindexedConsumer<Self::Item>>(self, consumer: Co) -> Co::Result {
+        match self.0 {
+            None => rayon::iter::empty().drive_unindexed(consumer),
+            Some(subtrees) => bridge_unindexed(IterMutProducer(subtrees), consumer),
+        }
+    }
+}
+
+/// Parallel iterator over a set's values, or those within a range
+pub struct ParSetIter<'a, T>(ParIter<'a, T, ()>);
+
+impl<'a, T: Sync + 'a> ParallelIterator for ParSetIter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn drive_unindexed<Co: UnindexedConsumer<Self::Item>>(self, consumer: Co) -> Co::Result {
+        self.0.map(|(value, ())| value).drive_unindexed(consumer)
+    }
+}
+// endregion
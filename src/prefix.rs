@@ -0,0 +1,152 @@
+use std::cmp::Ordering;
+
+/// A composite key whose leading components can be compared to a prefix `P`, so
+/// [BTreeMap::prefix](crate::BTreeMap::prefix) finds every key which starts with it, without
+/// constructing sentinel min/max keys.
+///
+/// Implemented for tuples of up to 4 components, where `P` is the first component or a tuple of
+/// the first components. Keys must be ordered lexicographically (like tuples' [Ord]), so the keys
+/// with a prefix are contiguous.
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore};
+///
+/// let store = BTreeStore::new();
+/// let mut events = BTreeMap::new_in(&store);
+/// events.insert(("tenant-a", 20, 1), "login");
+/// events.insert(("tenant-b", 10, 2), "login");
+/// events.insert(("tenant-a", 30, 3), "logout");
+/// events.insert(("tenant-a", 10, 4), "signup");
+///
+/// assert!(events.prefix(&"tenant-a").map(|(_, v)| *v).eq(["signup", "login", "logout"]));
+/// assert!(events.range_prefix(&"tenant-a", 15..).map(|(_, v)| *v).eq(["login", "logout"]));
+/// ```
+pub trait PrefixKey<P: ?Sized> {
+    /// The component after the prefix, which [BTreeMap::range_prefix](crate::BTreeMap::range_prefix)
+    /// ranges over
+    type Next: Ord;
+
+    /// Compares the key's leading components to the prefix
+    fn cmp_prefix(&self, prefix: &P) -> Ordering;
+
+    /// The component after the prefix
+    fn next_component(&self) -> &Self::Next;
+}
+
+macro_rules! impl_prefix_key {
+    ([$($prefix:ident $idx:tt),+] $next:ident $next_idx:tt [$($rest:ident),*]) => {
+        impl<$($prefix: Ord,)+ $next: Ord, $($rest),*> PrefixKey<($($prefix,)+)>
+            for ($($prefix,)+ $next, $($rest),*)
+        {
+            type Next = $next;
+
+            #[inline]
+            fn cmp_prefix(&self, prefix: &($($prefix,)+)) -> Ordering {
+                Ordering::Equal$(.then_with(|| self.$idx.cmp(&prefix.$idx)))+
+            }
+
+            #[inline]
+            fn next_component(&self) -> &$next {
+                &self.$next_idx
+            }
+        }
+    };
+}
+
+macro_rules! impl_first_prefix_key {
+    ($first:ident $next:ident [$($rest:ident),*]) => {
+        impl<$first: Ord, $next: Ord, $($rest),*> PrefixKey<$first> for ($first, $next, $($rest),*) {
+            type Next = $next;
+
+            #[inline]
+            fn cmp_prefix(&self, prefix: &$first) -> Ordering {
+                self.0.cmp(prefix)
+            }
+
+            #[inline]
+            fn next_component(&self) -> &$next {
+                &self.1
+            }
+        }
+    };
+}
+
+impl_first_prefix_key!(A B []);
+impl_first_prefix_key!(A B [C]);
+impl_first_prefix_key!(A B [C, D]);
+impl_prefix_key!([A 0, B 1] C 2 []);
+impl_prefix_key!([A 0, B 1] C 2 [D]);
+impl_prefix_key!([A 0, B 1, C 2] D 3 []);
+
+/// A key which is a string of bytes, ordered by the bytes (like [Ord] for strings and byte
+/// strings), so [BTreeMap::starts_with](crate::BTreeMap::starts_with) and
+/// [BTreeMap::longest_prefix_match](crate::BTreeMap::longest_prefix_match) can compare it to byte
+/// prefixes.
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore};
+///
+/// let store = BTreeStore::new();
+/// let mut routes = BTreeMap::new_in(&store);
+/// routes.insert(String::from("/"), "root");
+/// routes.insert(String::from("/api"), "api");
+/// routes.insert(String::from("/api/users"), "users");
+/// routes.insert(String::from("/apiary"), "bees");
+///
+/// assert!(routes.starts_with("/api").map(|(_, v)| *v).eq(["api", "users", "bees"]));
+/// assert_eq!(routes.longest_prefix_match("/api/users/42").map(|(_, v)| *v), Some("users"));
+/// assert_eq!(routes.longest_prefix_match("/apple").map(|(_, v)| *v), Some("root"));
+/// ```
+pub trait ByteKey {
+    /// The key's bytes
+    fn key_bytes(&self) -> &[u8];
+}
+
+impl ByteKey for String {
+    #[inline]
+    fn key_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl ByteKey for Box<str> {
+    #[inline]
+    fn key_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl ByteKey for &str {
+    #[inline]
+    fn key_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl ByteKey for Vec<u8> {
+    #[inline]
+    fn key_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl ByteKey for Box<[u8]> {
+    #[inline]
+    fn key_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl ByteKey for &[u8] {
+    #[inline]
+    fn key_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Compares the key's first bytes to the prefix, so keys which start with it are
+/// [Ordering::Equal]
+#[inline]
+pub(crate) fn cmp_byte_prefix(key: &[u8], prefix: &[u8]) -> Ordering {
+    key[..key.len().min(prefix.len())].cmp(prefix)
+}
@@ -0,0 +1,252 @@
+use crate::{BTreeMap, BTreeStore};
+use std::borrow::Borrow;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+
+/// A double-ended priority queue of distinct items, which can change or remove any item's
+/// priority.
+///
+/// Items are ordered by priority, then by item, in a b-tree map from `(priority, item)`; another
+/// map indexes each item's priority. Both maps are allocated in the caller's stores, so small
+/// queues don't allocate.
+pub struct BTreePriorityQueue<'store, P, T> {
+    queue: BTreeMap<'store, (P, T), ()>,
+    priorities: BTreeMap<'store, T, P>,
+}
+
+impl<'store, P, T> BTreePriorityQueue<'store, P, T> {
+    /// Creates an empty queue, whose ordered entries are in `queue_store` and priority index is in
+    /// `index_store`.
+    #[inline]
+    pub const fn new_in(
+        queue_store: &'store BTreeStore<(P, T), ()>,
+        index_store: &'store BTreeStore<T, P>,
+    ) -> Self {
+        Self {
+            queue: BTreeMap::new_in(queue_store),
+            priorities: BTreeMap::new_in(index_store),
+        }
+    }
+
+    /// Returns the number of items in the queue.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.priorities.len()
+    }
+
+    /// Returns `true` if the queue contains no items.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.priorities.is_empty()
+    }
+
+    /// Clears the queue, removing all items.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.priorities.clear();
+    }
+
+    /// Returns `true` if the queue contains the item.
+    #[inline]
+    pub fn contains<Q: Ord + ?Sized>(&self, item: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.priorities.contains_key(item)
+    }
+
+    /// Returns the item's priority, if it's in the queue.
+    #[inline]
+    pub fn priority<Q: Ord + ?Sized>(&self, item: &Q) -> Option<&P>
+    where
+        T: Borrow<Q>,
+    {
+        self.priorities.get(item)
+    }
+
+    /// Returns the item with the lowest priority and its priority. Items with equal priorities are
+    /// ordered by the items.
+    #[inline]
+    pub fn peek_min(&self) -> Option<(&T, &P)> {
+        self.queue
+            .first_key_value()
+            .map(|((priority, item), ())| (item, priority))
+    }
+
+    /// Returns the item with the highest priority and its priority.
+    #[inline]
+    pub fn peek_max(&self) -> Option<(&T, &P)> {
+        self.queue
+            .last_key_value()
+            .map(|((priority, item), ())| (item, priority))
+    }
+
+    /// Adds the item with the priority. If the item is already in the queue, changes its priority
+    /// instead and returns the old one.
+    pub fn push(&mut self, item: T, priority: P) -> Option<P>
+    where
+        P: Ord + Clone,
+        T: Ord + Clone,
+    {
+        let old_priority = self.remove(&item);
+        self.queue.insert((priority.clone(), item.clone()), ());
+        self.priorities.insert(item, priority);
+        old_priority
+    }
+
+    /// Removes and returns the item with the lowest priority and its priority.
+    #[inline]
+    pub fn pop_min(&mut self) -> Option<(T, P)>
+    where
+        P: Ord + Clone,
+        T: Ord + Clone,
+    {
+        let ((priority, item), ()) = self.queue.pop_first()?;
+        self.priorities.remove(&item);
+        Some((item, priority))
+    }
+
+    /// Removes and returns the item with the highest priority and its priority.
+    #[inline]
+    pub fn pop_max(&mut self) -> Option<(T, P)>
+    where
+        P: Ord + Clone,
+        T: Ord + Clone,
+    {
+        let ((priority, item), ()) = self.queue.pop_last()?;
+        self.priorities.remove(&item);
+        Some((item, priority))
+    }
+
+    /// Changes the item's priority and returns the old one, if the item is in the queue. Otherwise
+    /// does nothing.
+    pub fn change_priority<Q: Ord + ?Sized>(&mut self, item: &Q, priority: P) -> Option<P>
+    where
+        P: Ord + Clone,
+        T: Ord + Clone + Borrow<Q>,
+    {
+        let (item, old_priority) = self.priorities.remove_key_value(item)?;
+        let ((old_priority, item), ()) = self
+            .queue
+            .remove_key_value(&(old_priority, item))
+            .expect("item is indexed but not queued");
+        self.queue.insert((priority.clone(), item.clone()), ());
+        self.priorities.insert(item, priority);
+        Some(old_priority)
+    }
+
+    /// Removes the item and returns its priority, if it's in the queue.
+    pub fn remove<Q: Ord + ?Sized>(&mut self, item: &Q) -> Option<P>
+    where
+        P: Ord + Clone,
+        T: Ord + Clone + Borrow<Q>,
+    {
+        let (item, priority) = self.priorities.remove_key_value(item)?;
+        self.queue
+            .remove_key_value(&(priority, item))
+            .map(|((priority, _), ())| priority)
+    }
+
+    /// Iterates over the items and their priorities, from the lowest priority to the highest.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, P, T> {
+        Iter(self.queue.keys())
+    }
+
+    /// Validates the queue, *panic*ing if it is invalid. Specifically, we check both b-trees, and
+    /// that the index has exactly the queued items and priorities.
+    pub fn validate(&self)
+    where
+        P: Debug + Ord,
+        T: Debug + Ord,
+    {
+        self.queue.validate();
+        self.priorities.validate();
+        assert_eq!(
+            self.queue.len(),
+            self.priorities.len(),
+            "queue and index have different lengths"
+        );
+        for (item, priority) in self.iter() {
+            assert_eq!(
+                self.priorities.get(item),
+                Some(priority),
+                "{:?} isn't indexed with priority {:?}",
+                item,
+                priority
+            );
+        }
+    }
+}
+
+// region common trait impls
+impl<'store, P: Debug, T: Debug> Debug for BTreePriorityQueue<'store, P, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'store, P: PartialEq, T: PartialEq> PartialEq for BTreePriorityQueue<'store, P, T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.queue == other.queue
+    }
+}
+
+impl<'store, P: Eq, T: Eq> Eq for BTreePriorityQueue<'store, P, T> {}
+
+impl<'store, P: Ord + Clone, T: Ord + Clone> Extend<(T, P)> for BTreePriorityQueue<'store, P, T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (T, P)>>(&mut self, iter: I) {
+        for (item, priority) in iter {
+            self.push(item, priority);
+        }
+    }
+}
+
+impl<'a, 'store: 'a, P, T> IntoIterator for &'a BTreePriorityQueue<'store, P, T> {
+    type Item = (&'a T, &'a P);
+    type IntoIter = Iter<'a, P, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+// endregion
+
+// region iterators
+/// Iterator over the items and their priorities, from the lowest priority to the highest
+pub struct Iter<'a, P, T>(crate::map::Keys<'a, (P, T), ()>);
+
+impl<'a, P, T> Iterator for Iter<'a, P, T> {
+    type Item = (&'a T, &'a P);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(priority, item)| (item, priority))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, P, T> DoubleEndedIterator for Iter<'a, P, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(priority, item)| (item, priority))
+    }
+}
+
+impl<'a, P, T> ExactSizeIterator for Iter<'a, P, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, P, T> FusedIterator for Iter<'a, P, T> {}
+// endregion
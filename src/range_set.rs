@@ -0,0 +1,198 @@
+use crate::interval_map::{self, IntervalMap};
+use crate::BTreeStore;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::ops::Range;
+
+/// A b-tree set of points, stored as non-overlapping ranges. Overlapping and adjacent ranges are
+/// coalesced.
+///
+/// Ranges are half-open (`start..end`) and empty ranges are ignored.
+pub struct RangeSet<'store, K>(IntervalMap<'store, K, ()>);
+
+impl<'store, K> RangeSet<'store, K> {
+    /// Creates an empty range set.
+    #[inline]
+    pub fn new_in(store: &'store BTreeStore<K, (K, ())>) -> Self {
+        Self(IntervalMap::new_in(store))
+    }
+
+    /// Returns the number of (coalesced) ranges in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the set contains no ranges.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Clears the set, removing all ranges.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    /// Returns the range containing the point, if any.
+    #[inline]
+    pub fn get(&self, point: &K) -> Option<Range<&K>>
+    where
+        K: Ord,
+    {
+        self.0.get_range_value(point).map(|(range, ())| range)
+    }
+
+    /// Returns `true` if a range contains the point.
+    #[inline]
+    pub fn contains(&self, point: &K) -> bool
+    where
+        K: Ord,
+    {
+        self.0.contains(point)
+    }
+
+    /// Adds every point in the range.
+    #[inline]
+    pub fn insert_range(&mut self, range: Range<K>)
+    where
+        K: Ord + Clone,
+    {
+        self.0.insert_range(range, ())
+    }
+
+    /// Removes every point in the range, splitting the ranges which overlap its ends.
+    #[inline]
+    pub fn remove_range(&mut self, range: Range<K>)
+    where
+        K: Ord + Clone,
+    {
+        self.0.remove_range(range)
+    }
+
+    /// Iterates over the ranges which overlap the range, in order.
+    #[inline]
+    pub fn overlapping<'a>(&'a self, range: &'a Range<K>) -> Overlapping<'a, K>
+    where
+        K: Ord,
+    {
+        Overlapping(self.0.overlapping(range))
+    }
+
+    /// Iterates over the sub-ranges of the range which aren't in the set, in order.
+    #[inline]
+    pub fn gaps<'a>(&'a self, range: &'a Range<K>) -> interval_map::Gaps<'a, K, ()>
+    where
+        K: Ord,
+    {
+        self.0.gaps(range)
+    }
+
+    /// Validates the set, *panic*ing if it is invalid.
+    #[inline]
+    pub fn validate(&self)
+    where
+        K: Debug + Ord,
+    {
+        self.0.validate()
+    }
+
+    /// Iterates over the ranges, in order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.0.iter())
+    }
+}
+
+// region common trait impls
+impl<'store, K: Debug> Debug for RangeSet<'store, K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'store, K: PartialEq> PartialEq for RangeSet<'store, K> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<'store, K: Eq> Eq for RangeSet<'store, K> {}
+
+impl<'store, K: Ord + Clone> Extend<Range<K>> for RangeSet<'store, K> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = Range<K>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert_range(range);
+        }
+    }
+}
+
+impl<'a, 'store: 'a, K> IntoIterator for &'a RangeSet<'store, K> {
+    type Item = Range<&'a K>;
+    type IntoIter = Iter<'a, K>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+// endregion
+
+// region iterators
+/// Iterator over all ranges
+pub struct Iter<'a, K>(interval_map::Iter<'a, K, ()>);
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = Range<&'a K>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(range, ())| range)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for Iter<'a, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(range, ())| range)
+    }
+}
+
+impl<'a, K> ExactSizeIterator for Iter<'a, K> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a, K> FusedIterator for Iter<'a, K> {}
+
+/// Iterator over the ranges which overlap a range
+pub struct Overlapping<'a, K>(interval_map::Overlapping<'a, K, ()>);
+
+impl<'a, K> Iterator for Overlapping<'a, K> {
+    type Item = Range<&'a K>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(range, ())| range)
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for Overlapping<'a, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(range, ())| range)
+    }
+}
+
+impl<'a, K> FusedIterator for Overlapping<'a, K> {}
+// endregion
@@ -0,0 +1,255 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+use crate::compare::Comparator;
+
+/// A b-tree whose nodes can be descended and whose leaves are linked.
+///
+/// Methods taking nodes are unsafe because the nodes must belong to this tree, and the methods
+/// only defined for leaves or internal nodes must only be called on them.
+pub(crate) trait SearchTree {
+    type Key;
+    /// Reference to a node. Equal iff they reference the same node.
+    type Node: Copy + PartialEq;
+
+    /// Root and height, or `None` if empty
+    fn root(&self) -> Option<(Self::Node, usize)>;
+
+    unsafe fn keys(&self, node: Self::Node) -> &[Self::Key];
+
+    /// Child at `idx` of an internal node
+    unsafe fn edge(&self, node: Self::Node, idx: u16) -> Self::Node;
+
+    /// Previous leaf of a leaf
+    unsafe fn prev(&self, node: Self::Node) -> Option<Self::Node>;
+
+    /// Next leaf of a leaf
+    unsafe fn next(&self, node: Self::Node) -> Option<Self::Node>;
+
+    #[inline]
+    unsafe fn len(&self, node: Self::Node) -> u16 {
+        self.keys(node).len() as u16
+    }
+}
+
+/// The result of looking up an address to retrieve or insert an entry
+pub(crate) enum Find<N> {
+    /// The tree is empty
+    NoRoot,
+    /// The entry would be before this address
+    Before { node: N, idx: u16 },
+    /// The entry is at this address
+    At { node: N, idx: u16 },
+}
+
+/// Node and index to the start and end entry for a range within a tree.
+///
+/// These bounds are always inclusive. Use `Option<NodeBounds<N>>` to represent a
+/// potentially-empty range.
+pub(crate) struct NodeBounds<N> {
+    /// Start node (inclusive)
+    pub start_node: N,
+    /// End node (inclusive)
+    pub end_node: N,
+    /// Index in start node (inclusive)
+    pub start_index: u16,
+    /// Index in end node (inclusive)
+    pub end_index: u16,
+}
+
+impl<N: Copy> NodeBounds<N> {
+    #[inline]
+    pub fn start(&self) -> (N, u16) {
+        (self.start_node, self.start_index)
+    }
+
+    #[inline]
+    pub fn end(&self) -> (N, u16) {
+        (self.end_node, self.end_index)
+    }
+}
+
+#[inline]
+pub(crate) fn first_leaf<T: SearchTree + ?Sized>(tree: &T) -> Option<T::Node> {
+    let (mut node, height) = tree.root()?;
+    for _ in 0..height {
+        node = unsafe { tree.edge(node, 0) };
+    }
+    Some(node)
+}
+
+#[inline]
+pub(crate) fn last_leaf<T: SearchTree + ?Sized>(tree: &T) -> Option<T::Node> {
+    let (mut node, height) = tree.root()?;
+    for _ in 0..height {
+        node = unsafe { tree.edge(node, tree.len(node)) };
+    }
+    Some(node)
+}
+
+#[inline]
+pub(crate) fn find<T: SearchTree + ?Sized, Q: ?Sized>(
+    tree: &T,
+    cmp: &impl Comparator<Q>,
+    key: &Q,
+) -> Find<T::Node>
+where
+    T::Key: Borrow<Q>,
+{
+    find_by(tree, |k| cmp.cmp(k.borrow(), key))
+}
+
+/// Like [find], but `cmp` orders each key relative to the target. If `cmp` never returns
+/// [Ordering::Equal], this finds the partition point, e.g. before the first of many equivalent
+/// keys.
+#[inline]
+pub(crate) fn find_by<T: SearchTree + ?Sized>(
+    tree: &T,
+    mut cmp: impl FnMut(&T::Key) -> Ordering,
+) -> Find<T::Node> {
+    let Some((mut node, mut height)) = tree.root() else {
+        return Find::NoRoot;
+    };
+    loop {
+        match unsafe { tree.keys(node) }.binary_search_by(&mut cmp) {
+            Ok(idx) => {
+                let idx = idx as u16;
+                if height == 0 {
+                    break Find::At { node, idx };
+                }
+                height -= 1;
+                node = unsafe { tree.edge(node, idx + 1) }
+            }
+            Err(idx) => {
+                let idx = idx as u16;
+                if height == 0 {
+                    break Find::Before { node, idx };
+                }
+                height -= 1;
+                node = unsafe { tree.edge(node, idx) }
+            }
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn node_bounds<T: SearchTree + ?Sized, Q: ?Sized>(
+    tree: &T,
+    cmp: &impl Comparator<Q>,
+    bounds: impl RangeBounds<Q>,
+) -> Option<NodeBounds<T::Node>>
+where
+    T::Key: Borrow<Q>,
+{
+    node_bounds_by(
+        tree,
+        bounds
+            .start_bound()
+            .map(|bound| |k: &T::Key| cmp.cmp(k.borrow(), bound)),
+        bounds
+            .end_bound()
+            .map(|bound| |k: &T::Key| cmp.cmp(k.borrow(), bound)),
+    )
+}
+
+/// Like [node_bounds], but each bound is a function which orders keys relative to it, like
+/// [find_by]
+#[inline]
+pub(crate) fn node_bounds_by<T: SearchTree + ?Sized>(
+    tree: &T,
+    start: Bound<impl FnMut(&T::Key) -> Ordering>,
+    end: Bound<impl FnMut(&T::Key) -> Ordering>,
+) -> Option<NodeBounds<T::Node>> {
+    let (start_node, start_index) = match start {
+        Bound::Included(bound) => match find_by(tree, bound) {
+            Find::NoRoot => return None,
+            Find::Before { node, idx } => unsafe { normalize_address(tree, node, idx) }?,
+            Find::At { node, idx } => (node, idx),
+        },
+        Bound::Excluded(bound) => match find_by(tree, bound) {
+            Find::NoRoot => return None,
+            // normalize_address handles if idx == len, which means we are past this node and
+            // may be at the end.
+            Find::Before { node, idx } => unsafe { normalize_address(tree, node, idx) }?,
+            Find::At { node, idx } => unsafe { address_after(tree, node, idx) }?,
+        },
+        Bound::Unbounded => (first_leaf(tree)?, 0),
+    };
+    let (end_node, end_index) = match end {
+        Bound::Included(bound) => match find_by(tree, bound) {
+            Find::NoRoot => return None,
+            Find::Before { node, idx } => unsafe { address_before(tree, node, idx) }?,
+            Find::At { node, idx } => (node, idx),
+        },
+        Bound::Excluded(bound) => match find_by(tree, bound) {
+            Find::NoRoot => return None,
+            Find::Before { node, idx } | Find::At { node, idx } => {
+                unsafe { address_before(tree, node, idx) }?
+            }
+        },
+        Bound::Unbounded => last_leaf(tree).map(|leaf| (leaf, unsafe { tree.len(leaf) } - 1))?,
+    };
+
+    // Check for overlap (only need to check if address_after(start) == end)
+    if (start_node == end_node && start_index == end_index + 1)
+        || (start_index == 0 && unsafe { tree.prev(start_node) } == Some(end_node))
+    {
+        return None;
+    }
+
+    // Actually create
+    Some(NodeBounds {
+        start_node,
+        end_node,
+        start_index,
+        end_index,
+    })
+}
+
+#[inline]
+unsafe fn normalize_address<T: SearchTree + ?Sized>(
+    tree: &T,
+    node: T::Node,
+    idx: u16,
+) -> Option<(T::Node, u16)> {
+    let len = tree.len(node);
+    if idx < len {
+        Some((node, idx))
+    } else {
+        debug_assert_eq!(idx, len);
+        tree.next(node).map(|node| (node, 0))
+    }
+}
+
+#[inline]
+unsafe fn address_before<T: SearchTree + ?Sized>(
+    tree: &T,
+    node: T::Node,
+    idx: u16,
+) -> Option<(T::Node, u16)> {
+    if idx > 0 {
+        Some((node, idx - 1))
+    } else {
+        tree.prev(node).map(|node| (node, tree.len(node) - 1))
+    }
+}
+
+#[inline]
+unsafe fn address_after<T: SearchTree + ?Sized>(
+    tree: &T,
+    node: T::Node,
+    idx: u16,
+) -> Option<(T::Node, u16)> {
+    let len = tree.len(node);
+    match idx.cmp(&(len - 1)) {
+        Ordering::Less => Some((node, idx + 1)),
+        Ordering::Equal => tree.next(node).map(|node| (node, 0)),
+        Ordering::Greater => {
+            // Not normalize AND we want the address after anyways. Currently this branch is never
+            // actually reached, but if it was this is what we would do
+            debug_assert_eq!(idx, len);
+            tree.next(node).map(|node| (node, 1))
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::mem::forget;
+use std::ptr;
+
+/// Keys which can be copied into internal nodes, to separate the entries of their children.
+///
+/// This is implemented for every [Clone] key, whose separators are clones. Keys which can't be
+/// cloned (e.g. because they own a resource) can implement it with the defaults: then separators
+/// are bitwise copies of keys in leaves, which are never dropped, and are replaced before their
+/// key is removed from the map.
+///
+/// # Safety
+/// If `ALIASED` is `false`, [Separator::separator] must return a value which can be used and
+/// dropped independently of the key, like a clone.
+///
+/// If `ALIASED` is `true`, reading a bitwise copy of the key while the original is alive must be
+/// sound. This isn't true for keys containing a [Box] (or another type which asserts unique
+/// ownership when moved), so use a [Vec] or a [Clone] key instead.
+pub unsafe trait Separator: Sized {
+    /// Whether separators are bitwise copies of the keys in leaves
+    const ALIASED: bool = true;
+
+    /// Creates a separator equal to this key.
+    ///
+    /// # Safety
+    /// If `ALIASED`, the separator is a bitwise copy, so it must not be dropped and must not be
+    /// used after this key is moved out of the map or dropped.
+    #[inline]
+    unsafe fn separator(&self) -> Self {
+        ptr::read(self)
+    }
+}
+
+unsafe impl<K: Clone> Separator for K {
+    const ALIASED: bool = false;
+
+    #[inline]
+    unsafe fn separator(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// Drops a separator which was removed from the map, unless it's a bitwise copy.
+#[inline]
+pub(crate) fn drop_separator<K: Separator>(separator: K) {
+    if K::ALIASED {
+        forget(separator);
+    } else {
+        drop(separator);
+    }
+}
+
+/// Keys which can be shortened into separators, e.g. strings whose separator is the shortest
+/// prefix of one key which is greater than the key before it.
+///
+/// Maps only use these separators under the [ShortSeparators](crate::compare::ShortSeparators)
+/// comparator. Leaves still store full keys.
+pub trait SeparatorKey: Ord + Sized {
+    /// Returns a key `s` where `prev < s <= next`, ideally smaller than `next`.
+    ///
+    /// *panic*s or returns an invalid separator if `prev >= next`.
+    fn shortest_separator(prev: &Self, next: &Self) -> Self;
+}
+
+/// Length of the shortest prefix of `next` which is greater than `prev`
+#[inline]
+fn shortest_prefix_len(prev: &[u8], next: &[u8]) -> usize {
+    let common = prev.iter().zip(next).take_while(|(a, b)| a == b).count();
+    assert!(common < next.len(), "prev >= next");
+    common + 1
+}
+
+impl SeparatorKey for Vec<u8> {
+    #[inline]
+    fn shortest_separator(prev: &Self, next: &Self) -> Self {
+        next[..shortest_prefix_len(prev, next)].to_vec()
+    }
+}
+
+impl SeparatorKey for Box<[u8]> {
+    #[inline]
+    fn shortest_separator(prev: &Self, next: &Self) -> Self {
+        next[..shortest_prefix_len(prev, next)].into()
+    }
+}
+
+impl SeparatorKey for String {
+    #[inline]
+    fn shortest_separator(prev: &Self, next: &Self) -> Self {
+        next[..str_prefix_len(prev, next)].to_string()
+    }
+}
+
+impl SeparatorKey for Box<str> {
+    #[inline]
+    fn shortest_separator(prev: &Self, next: &Self) -> Self {
+        next[..str_prefix_len(prev, next)].into()
+    }
+}
+
+/// [shortest_prefix_len] rounded up to a char boundary. UTF-8 preserves the order of chars, so
+/// the prefix is still greater.
+#[inline]
+fn str_prefix_len(prev: &str, next: &str) -> usize {
+    let mut len = shortest_prefix_len(prev.as_bytes(), next.as_bytes());
+    while !next.is_char_boundary(len) {
+        len += 1;
+    }
+    len
+}
@@ -0,0 +1,227 @@
+use std::fmt::Formatter;
+
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{BTreeMap, BTreeSet, BTreeStore, Separator};
+
+/// Cap on the capacity we pre-allocate from a deserializer's size hint, so malicious input can't
+/// make us allocate a huge buffer up-front.
+const MAX_PREALLOC: usize = 4096;
+
+// region Serialize
+impl<'store, K: Serialize, V: Serialize> Serialize for BTreeMap<'store, K, V> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self)
+    }
+}
+
+impl<'store, T: Serialize> Serialize for BTreeSet<'store, T> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "copyable")]
+impl<'store, K: Serialize, V: Serialize> Serialize for crate::copyable::BTreeMap<'store, K, V> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self)
+    }
+}
+
+#[cfg(feature = "copyable")]
+impl<'store, T: Serialize> Serialize for crate::copyable::BTreeSet<'store, T> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+// endregion
+
+// region Deserialize
+/// Deserializes a [BTreeMap] in the given store.
+///
+/// A map can't implement [Deserialize] because it needs the store, so use this or
+/// [BTreeMap::deserialize_in] instead. If the input's keys are in ascending order (e.g. it was
+/// serialized from a b-tree map), the map is built bottom-up like [BTreeMap::from_sorted_iter_in].
+pub struct BTreeMapSeed<'store, K, V> {
+    store: &'store BTreeStore<K, V>,
+}
+
+/// Deserializes a [BTreeSet] in the given store.
+///
+/// A set can't implement [Deserialize] because it needs the store, so use this or
+/// [BTreeSet::deserialize_in] instead. If the input is in ascending order (e.g. it was serialized
+/// from a b-tree set), the set is built bottom-up like [BTreeSet::from_sorted_iter_in].
+pub struct BTreeSetSeed<'store, T> {
+    store: &'store BTreeStore<T, ()>,
+}
+
+impl<'store, K, V> BTreeMapSeed<'store, K, V> {
+    #[inline]
+    pub fn new(store: &'store BTreeStore<K, V>) -> Self {
+        Self { store }
+    }
+}
+
+impl<'store, T> BTreeSetSeed<'store, T> {
+    #[inline]
+    pub fn new(store: &'store BTreeStore<T, ()>) -> Self {
+        Self { store }
+    }
+}
+
+impl<'store, K, V> BTreeMap<'store, K, V> {
+    /// Deserializes a map in the given store. See [BTreeMapSeed].
+    #[inline]
+    pub fn deserialize_in<'de, D: Deserializer<'de>>(
+        store: &'store BTreeStore<K, V>,
+        deserializer: D,
+    ) -> Result<Self, D::Error>
+    where
+        K: Deserialize<'de> + Ord + Separator,
+        V: Deserialize<'de>,
+    {
+        BTreeMapSeed::new(store).deserialize(deserializer)
+    }
+}
+
+impl<'store, T> BTreeSet<'store, T> {
+    /// Deserializes a set in the given store. See [BTreeSetSeed].
+    #[inline]
+    pub fn deserialize_in<'de, D: Deserializer<'de>>(
+        store: &'store BTreeStore<T, ()>,
+        deserializer: D,
+    ) -> Result<Self, D::Error>
+    where
+        T: Deserialize<'de> + Ord + Separator,
+    {
+        BTreeSetSeed::new(store).deserialize(deserializer)
+    }
+}
+
+#[cfg(feature = "copyable")]
+impl<'store, K, V> crate::copyable::BTreeMap<'store, K, V> {
+    /// Deserializes a map in the given store. See [BTreeMapSeed].
+    #[inline]
+    pub fn deserialize_in<'de, D: Deserializer<'de>>(
+        store: &'store BTreeStore<K, V>,
+        deserializer: D,
+    ) -> Result<Self, D::Error>
+    where
+        K: Deserialize<'de> + Ord + Separator,
+        V: Deserialize<'de>,
+    {
+        BTreeMap::deserialize_in(store, deserializer).map(Self::from)
+    }
+}
+
+#[cfg(feature = "copyable")]
+impl<'store, T> crate::copyable::BTreeSet<'store, T> {
+    /// Deserializes a set in the given store. See [BTreeSetSeed].
+    #[inline]
+    pub fn deserialize_in<'de, D: Deserializer<'de>>(
+        store: &'store BTreeStore<T, ()>,
+        deserializer: D,
+    ) -> Result<Self, D::Error>
+    where
+        T: Deserialize<'de> + Ord + Separator,
+    {
+        BTreeSet::deserialize_in(store, deserializer).map(Self::from)
+    }
+}
+
+impl<'de, 'store, K, V> DeserializeSeed<'de> for BTreeMapSeed<'store, K, V>
+where
+    K: Deserialize<'de> + Ord + Separator,
+    V: Deserialize<'de>,
+{
+    type Value = BTreeMap<'store, K, V>;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'store, K, V> Visitor<'de> for BTreeMapSeed<'store, K, V>
+where
+    K: Deserialize<'de> + Ord + Separator,
+    V: Deserialize<'de>,
+{
+    type Value = BTreeMap<'store, K, V>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut entries =
+            Vec::<(K, V)>::with_capacity(access.size_hint().unwrap_or(0).min(MAX_PREALLOC));
+        let mut is_sorted = true;
+        while let Some((key, val)) = access.next_entry()? {
+            if let Some((last_key, _)) = entries.last() {
+                is_sorted &= *last_key < key;
+            }
+            entries.push((key, val));
+        }
+        Ok(build_map(self.store, entries, is_sorted))
+    }
+}
+
+impl<'de, 'store, T> DeserializeSeed<'de> for BTreeSetSeed<'store, T>
+where
+    T: Deserialize<'de> + Ord + Separator,
+{
+    type Value = BTreeSet<'store, T>;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'store, T> Visitor<'de> for BTreeSetSeed<'store, T>
+where
+    T: Deserialize<'de> + Ord + Separator,
+{
+    type Value = BTreeSet<'store, T>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut entries =
+            Vec::<(T, ())>::with_capacity(access.size_hint().unwrap_or(0).min(MAX_PREALLOC));
+        let mut is_sorted = true;
+        while let Some(value) = access.next_element()? {
+            if let Some((last_value, _)) = entries.last() {
+                is_sorted &= *last_value < value;
+            }
+            entries.push((value, ()));
+        }
+        Ok(BTreeSet(build_map(self.store, entries, is_sorted)))
+    }
+}
+
+/// Builds bottom-up if the entries are sorted, otherwise inserts one at a time (later duplicate
+/// keys replace earlier ones, like [std::collections::BTreeMap]'s deserialization).
+#[inline]
+fn build_map<K: Ord + Separator, V>(
+    store: &BTreeStore<K, V>,
+    entries: Vec<(K, V)>,
+    is_sorted: bool,
+) -> BTreeMap<'_, K, V> {
+    if is_sorted {
+        BTreeMap::from_sorted_vec_in(store, entries)
+    } else {
+        let mut map = BTreeMap::new_in(store);
+        map.extend(entries);
+        map
+    }
+}
+// endregion
@@ -1,16 +1,28 @@
-use crate::{BTreeMap, BTreeStore};
+#[cfg(feature = "copyable")]
+use crate::node::NodePtr;
+#[cfg(feature = "serde")]
+pub use crate::serde_impls::BTreeSetSeed;
+#[cfg(feature = "checked")]
+use crate::store::Stamp;
+use crate::{BTreeMap, BTreeStore, ByteKey, CapacityError, PrefixKey, Separator};
 use std::borrow::Borrow;
+use std::cmp::Ordering;
 use std::fmt::{Debug, Formatter};
 use std::hash::Hash;
-use std::iter::FusedIterator;
+use std::iter::{FusedIterator, Peekable};
 use std::ops::RangeBounds;
 
 /// A b-tree set.
 ///
 /// See [std::collections::BTreeSet] for more info.
+///
+/// # Unwind safety
+///
+/// Like [BTreeMap](BTreeMap#unwind-safety), if user code *panic*s during a mutating method, the
+/// set stays valid.
 // TODO: impl Clone
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct BTreeSet<'store, T>(BTreeMap<'store, T, ()>);
+pub struct BTreeSet<'store, T>(pub(crate) BTreeMap<'store, T, ()>);
 
 impl<'store, T> BTreeSet<'store, T> {
     /// Creates an empty set.
@@ -19,6 +31,24 @@ impl<'store, T> BTreeSet<'store, T> {
         Self(BTreeMap::new_in(store))
     }
 
+    /// Creates a set from values in strictly ascending order. This builds the nodes bottom-up,
+    /// which is faster than inserting each value.
+    ///
+    /// *Panic*s if the values aren't strictly ascending.
+    #[inline]
+    pub fn from_sorted_iter_in(
+        store: &'store BTreeStore<T, ()>,
+        iter: impl IntoIterator<Item = T>,
+    ) -> Self
+    where
+        T: Ord + Separator,
+    {
+        Self(BTreeMap::from_sorted_iter_in(
+            store,
+            iter.into_iter().map(|value| (value, ())),
+        ))
+    }
+
     /// Returns the number of elements in the set.
     #[inline]
     pub fn len(&self) -> usize {
@@ -32,6 +62,8 @@ impl<'store, T> BTreeSet<'store, T> {
     }
 
     /// Clears the set, removing all values.
+    ///
+    /// If a destructor *panic*s, the rest of the values are still dropped, and the set is empty.
     #[inline]
     pub fn clear(&mut self) {
         self.0.clear()
@@ -70,37 +102,72 @@ impl<'store, T> BTreeSet<'store, T> {
     }
 
     /// Inserts a value into the set. Returns `true` if the value was not already present.
+    ///
+    /// If `Ord` or `Clone` *panic*s, the set is unchanged. If the store is at its
+    /// [limit](BTreeStore::with_limit), *panic*s with the set unchanged.
     #[inline]
     pub fn insert(&mut self, value: T) -> bool
     where
-        T: Clone + Ord,
+        T: Separator + Ord,
     {
         self.0.insert(value, ()).is_none()
     }
 
+    /// Inserts a value into the set like [insert](Self::insert), unless the store is at its
+    /// [limit](BTreeStore::with_limit): then returns the value in a [CapacityError] and the set is
+    /// unchanged.
+    #[inline]
+    pub fn try_insert(&mut self, value: T) -> Result<bool, CapacityError<T, ()>>
+    where
+        T: Separator + Ord,
+    {
+        self.0.try_insert(value, ()).map(|old| old.is_none())
+    }
+
+    /// Inserts each value until one doesn't fit in the store's [limit](BTreeStore::with_limit):
+    /// then returns it in a [CapacityError]. The values before it stay inserted, and the ones after
+    /// it aren't taken from the iterator.
+    #[inline]
+    pub fn try_extend(
+        &mut self,
+        iter: impl IntoIterator<Item = T>,
+    ) -> Result<(), CapacityError<T, ()>>
+    where
+        T: Separator + Ord,
+    {
+        self.0.try_extend(iter.into_iter().map(|value| (value, ())))
+    }
+
     /// Removes a value from the set. Returns `true` if the value was present.
+    ///
+    /// If `Ord` or `Clone` *panic*s, the set is unchanged. If dropping a separator *panic*s, the
+    /// value is already removed, and is dropped while unwinding.
     #[inline]
     pub fn remove<U: Ord + ?Sized>(&mut self, value: &U) -> bool
     where
-        T: Borrow<U> + Clone,
+        T: Borrow<U> + Separator + Ord,
     {
         self.0.remove(value).is_some()
     }
 
     /// Removes the first value from the set.
+    ///
+    /// *Panic*s leave the set like [remove](Self::remove).
     #[inline]
     pub fn pop_first(&mut self) -> Option<T>
     where
-        T: Clone,
+        T: Separator + Ord,
     {
         self.0.pop_first().map(|(k, ())| k)
     }
 
     /// Removes the last value from the set.
+    ///
+    /// *Panic*s leave the set like [remove](Self::remove).
     #[inline]
     pub fn pop_last(&mut self) -> Option<T>
     where
-        T: Clone,
+        T: Separator + Ord,
     {
         self.0.pop_last().map(|(k, ())| k)
     }
@@ -135,12 +202,121 @@ impl<'store, T> BTreeSet<'store, T> {
 
     /// Returns an iterator over the set within the given bounds
     #[inline]
-    pub fn range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> Range<T>
+    pub fn range<U: Ord + ?Sized>(&self, bounds: impl RangeBounds<U>) -> Range<'_, T>
     where
         T: Borrow<U>,
     {
         Range(self.0.range(bounds))
     }
+
+    /// Returns an iterator over the values which start with the prefix, in order. See
+    /// [PrefixKey].
+    #[inline]
+    pub fn prefix<P: ?Sized>(&self, prefix: &P) -> Range<'_, T>
+    where
+        T: PrefixKey<P>,
+    {
+        Range(self.0.prefix(prefix))
+    }
+
+    /// Returns an iterator over the values which start with the prefix and whose next component is
+    /// in the range, in order. See [PrefixKey].
+    #[inline]
+    pub fn range_prefix<P: ?Sized>(
+        &self,
+        prefix: &P,
+        bounds: impl RangeBounds<T::Next>,
+    ) -> Range<'_, T>
+    where
+        T: PrefixKey<P>,
+    {
+        Range(self.0.range_prefix(prefix, bounds))
+    }
+
+    /// Returns an iterator over the values which start with the byte prefix, in order. See
+    /// [ByteKey].
+    #[inline]
+    pub fn starts_with(&self, prefix: impl AsRef<[u8]>) -> Range<'_, T>
+    where
+        T: ByteKey,
+    {
+        Range(self.0.starts_with(prefix))
+    }
+
+    /// Returns the longest value which is a prefix of `query`, if any. See [ByteKey].
+    #[inline]
+    pub fn longest_prefix_match(&self, query: impl AsRef<[u8]>) -> Option<&T>
+    where
+        T: ByteKey,
+    {
+        self.0.longest_prefix_match(query).map(|(k, ())| k)
+    }
+
+    /// Returns an iterator over the values in `self` but not in `other`, in order.
+    #[inline]
+    pub fn difference<'a>(&'a self, other: &'a BTreeSet<'_, T>) -> Difference<'a, T>
+    where
+        T: Ord,
+    {
+        Difference(MergeIter::new(self.iter(), other.iter()))
+    }
+
+    /// Returns an iterator over the values in `self` or `other` but not both, in order.
+    #[inline]
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a BTreeSet<'_, T>,
+    ) -> SymmetricDifference<'a, T>
+    where
+        T: Ord,
+    {
+        SymmetricDifference(MergeIter::new(self.iter(), other.iter()))
+    }
+
+    /// Returns an iterator over the values in both `self` and `other`, in order.
+    #[inline]
+    pub fn intersection<'a>(&'a self, other: &'a BTreeSet<'_, T>) -> Intersection<'a, T>
+    where
+        T: Ord,
+    {
+        Intersection(MergeIter::new(self.iter(), other.iter()))
+    }
+
+    /// Returns an iterator over the values in `self` or `other`, in order and without duplicates.
+    #[inline]
+    pub fn union<'a>(&'a self, other: &'a BTreeSet<'_, T>) -> Union<'a, T>
+    where
+        T: Ord,
+    {
+        Union(MergeIter::new(self.iter(), other.iter()))
+    }
+
+    /// Returns `true` if `self` and `other` have no values in common.
+    #[inline]
+    pub fn is_disjoint(&self, other: &BTreeSet<'_, T>) -> bool
+    where
+        T: Ord,
+    {
+        self.intersection(other).next().is_none()
+    }
+
+    /// Returns `true` if every value in `self` is also in `other`.
+    #[inline]
+    pub fn is_subset(&self, other: &BTreeSet<'_, T>) -> bool
+    where
+        T: Ord,
+    {
+        self.len() <= other.len() && self.difference(other).next().is_none()
+    }
+
+    /// Returns `true` if every value in `other` is also in `self`.
+    #[inline]
+    pub fn is_superset(&self, other: &BTreeSet<'_, T>) -> bool
+    where
+        T: Ord,
+    {
+        other.is_subset(self)
+    }
 }
 
 // region common trait impls
@@ -150,7 +326,7 @@ impl<'store, T: Debug> Debug for BTreeSet<'store, T> {
     }
 }
 
-impl<'store, T: Ord + Clone> Extend<T> for BTreeSet<'store, T> {
+impl<'store, T: Ord + Separator> Extend<T> for BTreeSet<'store, T> {
     #[inline]
     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
         self.0.extend(iter.into_iter().map(|v| (v, ())))
@@ -185,6 +361,18 @@ impl<'a, 'store: 'a, T> IntoIterator for &'a BTreeSet<'store, T> {
 // region Iter
 pub struct Iter<'a, T>(crate::map::Iter<'a, T, ()>);
 
+impl<'a, T> Iter<'a, T> {
+    /// Creates an iterator whose lifetime isn't tied to the reference to `set`.
+    ///
+    /// # Safety
+    /// The set's nodes must stay alive and not be mutated for `'a`.
+    #[cfg(feature = "copyable")]
+    #[inline]
+    pub(crate) unsafe fn new_detached(set: &BTreeSet<T>) -> Self {
+        Iter(crate::map::Iter::new_detached(&set.0))
+    }
+}
+
 impl<'a, T> Iterator for Iter<'a, T> {
     type Item = &'a T;
 
@@ -274,7 +462,132 @@ impl<'a, T> DoubleEndedIterator for Range<'a, T> {
     }
 }
 // endregion
+
+// region set operations
+/// Iterates two sets in order, yielding values from either or both sets at once.
+struct MergeIter<'a, T> {
+    a: Peekable<Iter<'a, T>>,
+    b: Peekable<Iter<'a, T>>,
+}
+
+impl<'a, T: Ord> MergeIter<'a, T> {
+    #[inline]
+    fn new(a: Iter<'a, T>, b: Iter<'a, T>) -> Self {
+        Self {
+            a: a.peekable(),
+            b: b.peekable(),
+        }
+    }
+
+    /// Returns the next value from `a`, `b`, or both if they are equal.
+    #[inline]
+    fn next(&mut self) -> (Option<&'a T>, Option<&'a T>) {
+        match (self.a.peek(), self.b.peek()) {
+            (None, None) => (None, None),
+            (Some(_), None) => (self.a.next(), None),
+            (None, Some(_)) => (None, self.b.next()),
+            (Some(a), Some(b)) => match a.cmp(b) {
+                Ordering::Less => (self.a.next(), None),
+                Ordering::Greater => (None, self.b.next()),
+                Ordering::Equal => (self.a.next(), self.b.next()),
+            },
+        }
+    }
+}
+
+pub struct Difference<'a, T>(MergeIter<'a, T>);
+
+impl<'a, T: Ord> Iterator for Difference<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Don't bother iterating the rest of `other` once `self` is exhausted
+            self.0.a.peek()?;
+            if let (Some(a), None) = self.0.next() {
+                return Some(a);
+            }
+        }
+    }
+}
+
+impl<'a, T: Ord> FusedIterator for Difference<'a, T> {}
+
+pub struct SymmetricDifference<'a, T>(MergeIter<'a, T>);
+
+impl<'a, T: Ord> Iterator for SymmetricDifference<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.0.next() {
+                (None, None) => return None,
+                (Some(a), None) => return Some(a),
+                (None, Some(b)) => return Some(b),
+                (Some(_), Some(_)) => {}
+            }
+        }
+    }
+}
+
+impl<'a, T: Ord> FusedIterator for SymmetricDifference<'a, T> {}
+
+pub struct Intersection<'a, T>(MergeIter<'a, T>);
+
+impl<'a, T: Ord> Iterator for Intersection<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.0.a.peek()?;
+            self.0.b.peek()?;
+            if let (Some(a), Some(_)) = self.0.next() {
+                return Some(a);
+            }
+        }
+    }
+}
+
+impl<'a, T: Ord> FusedIterator for Intersection<'a, T> {}
+
+pub struct Union<'a, T>(MergeIter<'a, T>);
+
+impl<'a, T: Ord> Iterator for Union<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.0.next() {
+            (Some(a), _) => Some(a),
+            (None, b) => b,
+        }
+    }
+}
+
+impl<'a, T: Ord> FusedIterator for Union<'a, T> {}
 // endregion
+// endregion
+
+#[cfg(feature = "checked")]
+impl<'store, T> BTreeSet<'store, T> {
+    #[inline]
+    pub(crate) fn stamp(&self) -> Stamp {
+        self.0.stamp()
+    }
+
+    #[inline]
+    pub(crate) fn assert_alive(&self, stamp: Stamp) {
+        self.0.assert_alive(stamp)
+    }
+
+    #[inline]
+    pub(crate) fn survivor_key(&self, stamp: Stamp) -> Option<(NodePtr<T, ()>, Stamp)> {
+        self.0.survivor_key(stamp)
+    }
+}
 
 #[cfg(feature = "copyable")]
 impl<'store, T> crate::copyable::sealed::BTree<'store, T, ()> for BTreeSet<'store, T> {
@@ -287,4 +600,15 @@ impl<'store, T> crate::copyable::sealed::BTree<'store, T, ()> for BTreeSet<'stor
     fn nodes(&self) -> crate::copyable::sealed::NodeIter<'store, T, ()> {
         self.0.nodes()
     }
+
+    #[inline]
+    fn parts(&self) -> (Option<NodePtr<T, ()>>, usize, usize) {
+        self.0.parts()
+    }
+
+    #[cfg(feature = "checked")]
+    #[inline]
+    fn survivor_key(&self) -> Option<(NodePtr<T, ()>, Stamp)> {
+        None
+    }
 }
@@ -1,32 +1,143 @@
+use crate::map::drop_entries;
 use crate::node::{Node, NodePtr};
+use crate::summary::Summary;
 use rustc_arena_modified::SlabArena;
+use std::cell::Cell;
+#[cfg(feature = "checked")]
+use std::cell::RefCell;
+#[cfg(feature = "checked")]
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::mem::needs_drop;
+use std::panic::resume_unwind;
+#[cfg(feature = "copyable")]
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Arena to store nodes from multiple b-trees.
-pub struct BTreeStore<K, V> {
-    pub(crate) nodes: SlabArena<Node<K, V>>,
+pub struct BTreeStore<K, V, S = ()> {
+    pub(crate) nodes: SlabArena<Node<K, V, S>>,
+    /// Number of allocated nodes
+    num_nodes: Cell<usize>,
+    /// Maximum number of allocated nodes, see [BTreeStore::with_limit]
+    limit: usize,
+    /// Unique among all stores in the process
+    #[cfg(feature = "copyable")]
+    pub(crate) id: u64,
+    #[cfg(feature = "checked")]
+    checked: Checked<K, V, S>,
 }
 
+/// Under the `checked` feature, identifies the GC generation a copyable b-tree was created in, so
+/// we can panic instead of reading freed nodes.
+#[cfg(feature = "checked")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[doc(hidden)]
+pub struct Stamp {
+    generation: u64,
+}
+
+#[cfg(feature = "checked")]
+struct Checked<K, V, S> {
+    /// Incremented by every `tracing_gc`
+    generation: Cell<u64>,
+    /// Root addresses and stamps of the copyable b-trees passed to the last `tracing_gc`. Trees
+    /// from older generations which aren't here had their nodes freed.
+    survivors: RefCell<HashSet<Survivor<K, V, S>>>,
+}
+
+/// Root address and stamp of a copyable b-tree which survived a `tracing_gc`
+#[cfg(feature = "checked")]
+type Survivor<K, V, S> = (NodePtr<K, V, S>, Stamp);
+
+#[cfg(feature = "copyable")]
+static NEXT_STORE_ID: AtomicU64 = AtomicU64::new(0);
+
 impl<K, V> BTreeStore<K, V> {
     #[inline]
     pub fn new() -> Self {
+        Self::summarized()
+    }
+
+    /// Creates a store which allocates at most `max_nodes` nodes at once, e.g. to enforce a memory
+    /// quota. Each node holds up to 8 entries.
+    ///
+    /// When the store is full, `try_` methods like
+    /// [BTreeMap::try_insert](crate::BTreeMap::try_insert) return a [CapacityError] and leave the
+    /// map unchanged, and other methods which allocate *panic*.
+    #[inline]
+    pub fn with_limit(max_nodes: usize) -> Self {
+        Self::summarized_with_limit(max_nodes)
+    }
+}
+
+impl<K, V, S> BTreeStore<K, V, S> {
+    /// Creates a store for maps which cache a [Summary] of each subtree, so they can
+    /// [aggregate](crate::BTreeMap::aggregate) ranges in `O(log n)`.
+    #[inline]
+    pub fn summarized() -> Self
+    where
+        S: Summary<K, V>,
+    {
         Self {
             nodes: SlabArena::new(),
+            num_nodes: Cell::new(0),
+            limit: usize::MAX,
+            #[cfg(feature = "copyable")]
+            id: NEXT_STORE_ID.fetch_add(1, Ordering::Relaxed),
+            #[cfg(feature = "checked")]
+            checked: Checked {
+                generation: Cell::new(0),
+                survivors: RefCell::new(HashSet::new()),
+            },
         }
     }
 
+    /// Creates a [summarized](Self::summarized) store which allocates at most `max_nodes` nodes at
+    /// once, like [BTreeStore::with_limit].
     #[inline]
-    pub(crate) fn alloc(&self, node: Node<K, V>) -> NodePtr<K, V> {
+    pub fn summarized_with_limit(max_nodes: usize) -> Self
+    where
+        S: Summary<K, V>,
+    {
+        Self {
+            limit: max_nodes,
+            ..Self::summarized()
+        }
+    }
+
+    /// Number of nodes allocated by the store's b-trees
+    #[inline]
+    pub fn num_nodes(&self) -> usize {
+        self.num_nodes.get()
+    }
+
+    /// Whether the store can allocate `count` more nodes without exceeding its limit
+    #[inline]
+    pub(crate) fn has_room(&self, count: usize) -> bool {
+        self.limit
+            .checked_sub(self.num_nodes.get())
+            .is_some_and(|free| free >= count)
+    }
+
+    /// *Panic*s if the store is at its limit. Operations which must not *panic* midway check
+    /// [BTreeStore::has_room] first.
+    #[inline]
+    pub(crate) fn alloc(&self, node: Node<K, V, S>) -> NodePtr<K, V, S> {
+        assert!(self.has_room(1), "BTreeStore node limit exceeded");
+        self.num_nodes.set(self.num_nodes.get() + 1);
         self.nodes.alloc(node).into_unsafe()
     }
 
     #[inline]
-    pub(crate) fn dealloc(&self, node: NodePtr<K, V>) {
+    pub(crate) fn dealloc(&self, node: NodePtr<K, V, S>) {
+        self.num_nodes.set(self.num_nodes.get() - 1);
         unsafe { node.discard(&self.nodes) }
     }
 
     #[allow(unused)]
     #[inline]
-    pub(crate) fn dealloc_and_return(&self, node: NodePtr<K, V>) -> Node<K, V> {
+    pub(crate) fn dealloc_and_return(&self, node: NodePtr<K, V, S>) -> Node<K, V, S> {
+        self.num_nodes.set(self.num_nodes.get() - 1);
         unsafe { node.take(&self.nodes) }
     }
 
@@ -34,9 +145,85 @@ impl<K, V> BTreeStore<K, V> {
     #[inline]
     pub(crate) unsafe fn retain_shared<F>(&self, mut f: F)
     where
-        F: FnMut(&Node<K, V>) -> bool,
+        F: FnMut(&Node<K, V, S>) -> bool,
     {
-        self.nodes.retain_shared(|node| f(node))
+        self.nodes.retain_shared(|node| {
+            let retain = f(node);
+            if !retain {
+                self.num_nodes.set(self.num_nodes.get() - 1);
+            }
+            retain
+        })
+    }
+
+    /// Frees every node but keeps the memory, so new b-trees reuse it without allocating. Taking
+    /// `&mut self` ensures no b-trees borrow the store, so this drops the keys and values of any
+    /// which weren't dropped (e.g. were leaked with [forget](std::mem::forget), or are copyable
+    /// b-trees). Entries in nodes shared between copyable b-trees are dropped once.
+    ///
+    /// If a key or value's destructor *panic*s, still drops the rest and frees every node, then
+    /// resumes the first *panic*.
+    pub fn clear(&mut self) {
+        let mut panic = None;
+        if needs_drop::<K>() || needs_drop::<V>() {
+            // Each node drops only its own entries, so we don't need to find the roots, and shared
+            // subtrees aren't dropped twice
+            self.nodes.retain(|node| {
+                let (is_leaf, owns_separators) = (node.is_leaf, node.owns_separators);
+                unsafe { drop_entries(node, is_leaf, owns_separators, &mut panic) };
+                false
+            });
+        }
+        self.clear_without_dropping();
+        if let Some(panic) = panic {
+            resume_unwind(panic);
+        }
+    }
+
+    /// Frees every node like [BTreeStore::clear], but leaks the keys and values of b-trees which
+    /// weren't dropped instead of dropping them. `clear` skips dropping too when it's a no-op (e.g.
+    /// the keys and values are `Copy`), so this is only faster when it would drop something.
+    ///
+    /// This is safe for the same reason [forget](std::mem::forget) is: leaking never causes
+    /// undefined behavior, and taking `&mut self` ensures no b-tree can read the freed nodes.
+    pub fn clear_without_dropping(&mut self) {
+        self.nodes.retain(|_| false);
+        self.num_nodes.set(0);
+        #[cfg(feature = "checked")]
+        self.checked.survivors.get_mut().clear();
+    }
+
+    /// The store's current generation
+    #[cfg(feature = "checked")]
+    #[inline]
+    pub(crate) fn stamp(&self) -> Stamp {
+        Stamp {
+            generation: self.checked.generation.get(),
+        }
+    }
+
+    /// *Panic*s if the nodes of a copyable b-tree from this store, with the given `stamp` and root,
+    /// were freed by `tracing_gc`.
+    #[cfg(feature = "checked")]
+    #[inline]
+    pub(crate) fn assert_alive(&self, stamp: Stamp, root: Option<NodePtr<K, V, S>>) {
+        // Empty trees have no nodes to free
+        let Some(root) = root else { return };
+        assert!(
+            stamp.generation == self.checked.generation.get()
+                || self.checked.survivors.borrow().contains(&(root, stamp)),
+            "copyable b-tree was used after tracing_gc freed its nodes"
+        );
+    }
+
+    /// Records the copyable b-trees which survived a `tracing_gc` and starts a new generation.
+    #[cfg(feature = "checked")]
+    #[inline]
+    pub(crate) fn end_gc(&self, survivors: HashSet<(NodePtr<K, V, S>, Stamp)>) {
+        *self.checked.survivors.borrow_mut() = survivors;
+        self.checked
+            .generation
+            .set(self.checked.generation.get() + 1);
     }
 }
 
@@ -46,3 +233,25 @@ impl<K, V> Default for BTreeStore<K, V> {
         Self::new()
     }
 }
+
+/// The store couldn't allocate a node for the entry, because it's at its
+/// [limit](BTreeStore::with_limit). Contains the entry which wasn't inserted.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CapacityError<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<K, V> Debug for CapacityError<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapacityError").finish_non_exhaustive()
+    }
+}
+
+impl<K, V> Display for CapacityError<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BTreeStore node limit exceeded")
+    }
+}
+
+impl<K, V> std::error::Error for CapacityError<K, V> {}
@@ -0,0 +1,78 @@
+/// A monoid which summarizes a map's entries, e.g. their count, sum, or max value. Internal nodes
+/// cache the summary of each child's subtree, so [BTreeMap::aggregate](crate::BTreeMap::aggregate)
+/// combines a range's summaries in `O(log n)`.
+///
+/// Summaries are [Copy] so nodes can cache them without dropping. `()` is the summary of maps
+/// which don't aggregate, and costs nothing.
+///
+/// ```
+/// use btree_plus_store::{BTreeMap, BTreeStore, Summary};
+///
+/// /// The number of entries and the sum of their values
+/// #[derive(Clone, Copy, Debug, PartialEq)]
+/// struct CountSum(usize, u64);
+///
+/// impl<K> Summary<K, u64> for CountSum {
+///     fn empty() -> Self {
+///         CountSum(0, 0)
+///     }
+///
+///     fn summarize(_key: &K, val: &u64) -> Self {
+///         CountSum(1, *val)
+///     }
+///
+///     fn combine(a: Self, b: Self) -> Self {
+///         CountSum(a.0 + b.0, a.1 + b.1)
+///     }
+/// }
+///
+/// let store = BTreeStore::<u32, u64, CountSum>::summarized();
+/// let mut map = BTreeMap::new_in(&store);
+/// for i in 0..100 {
+///     map.insert(i, u64::from(i) * 2);
+/// }
+/// assert_eq!(map.aggregate(10..20), CountSum(10, 290));
+/// ```
+pub trait Summary<K, V>: Copy {
+    /// The summary of no entries: combining it with another summary returns the other summary.
+    fn empty() -> Self;
+
+    /// The summary of one entry
+    fn summarize(key: &K, val: &V) -> Self;
+
+    /// The summary of `a`'s entries followed by `b`'s. Must be associative.
+    fn combine(a: Self, b: Self) -> Self;
+}
+
+impl<K, V> Summary<K, V> for () {
+    #[inline]
+    fn empty() -> Self {}
+
+    #[inline]
+    fn summarize(_key: &K, _val: &V) -> Self {}
+
+    #[inline]
+    fn combine((): Self, (): Self) -> Self {}
+}
+
+/// Summarizes the number of entries. [BTreeVec](crate::BTreeVec)s find entries by position with
+/// it, and it can also find the rank of a key in a map.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Count(pub usize);
+
+impl<K, V> Summary<K, V> for Count {
+    #[inline]
+    fn empty() -> Self {
+        Count(0)
+    }
+
+    #[inline]
+    fn summarize(_key: &K, _val: &V) -> Self {
+        Count(1)
+    }
+
+    #[inline]
+    fn combine(a: Self, b: Self) -> Self {
+        Count(a.0 + b.0)
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{BTreeMap, BTreeStore, Count, OrdComparator};
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::ops::{Bound, Index, IndexMut, RangeBounds};
+
+/// A sequence stored in a b-tree, which inserts and removes at any index, splits, and appends in
+/// `O(log n)`.
+///
+/// Internal nodes cache the number of values in each subtree (a [Count] summary) instead of
+/// separator keys, and leaves store the values and are linked like a map's. Create the store with
+/// [BTreeStore::summarized].
+pub struct BTreeVec<'store, T> {
+    map: BTreeMap<'store, (), T, OrdComparator, Count>,
+}
+
+impl<'store, T> BTreeVec<'store, T> {
+    /// Creates an empty sequence.
+    #[inline]
+    pub const fn new_in(store: &'store BTreeStore<(), T, Count>) -> Self {
+        Self {
+            map: BTreeMap::new_in(store),
+        }
+    }
+
+    /// Returns the number of values.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if there are no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Clears the sequence, removing all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    /// Returns the value at the index, if it's in bounds.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.map.get_at(index)
+    }
+
+    /// Returns a mutable reference to the value at the index, if it's in bounds.
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.map.get_at_mut(index)
+    }
+
+    /// Returns the first value, if any.
+    #[inline]
+    pub fn first(&self) -> Option<&T> {
+        self.map.first_key_value().map(|((), v)| v)
+    }
+
+    /// Returns the last value, if any.
+    #[inline]
+    pub fn last(&self) -> Option<&T> {
+        self.map.last_key_value().map(|((), v)| v)
+    }
+
+    /// Inserts the value at the index, shifting the values after it. *Panic*s if `index > len`.
+    #[inline]
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(
+            index <= self.len(),
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            self.len()
+        );
+        self.map.insert_at(index, value)
+    }
+
+    /// Removes and returns the value at the index, shifting the values after it. *Panic*s if the
+    /// index is out of bounds.
+    #[inline]
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.len(),
+            "removal index (is {}) should be < len (is {})",
+            index,
+            self.len()
+        );
+        self.map.remove_at_index(index)
+    }
+
+    /// Appends the value to the end.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        self.map.insert_at(self.len(), value)
+    }
+
+    /// Removes and returns the last value, if any.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        self.map.pop_last().map(|((), v)| v)
+    }
+
+    /// Splits the sequence at the index, returning the values from it on. *Panic*s if
+    /// `at > len`, or with the sequence unchanged if the store is at its
+    /// [limit](BTreeStore::summarized_with_limit).
+    #[inline]
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(
+            at <= self.len(),
+            "`at` split index (is {}) should be <= len (is {})",
+            at,
+            self.len()
+        );
+        Self {
+            map: self.map.split_off_at(at),
+        }
+    }
+
+    /// Moves all of `other`'s values to the end, leaving `other` empty. *Panic*s if `other` is in
+    /// a different store, or with both sequences unchanged if the store is at its
+    /// [limit](BTreeStore::summarized_with_limit).
+    #[inline]
+    pub fn append(&mut self, other: &mut Self) {
+        self.map.append(&mut other.map)
+    }
+
+    /// Iterates over the values.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.range(..)
+    }
+
+    /// Iterates over mutable references to the values.
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.range_mut(..)
+    }
+
+    /// Iterates over the values in the index range. *Panic*s if the range is out of bounds or
+    /// decreasing.
+    #[inline]
+    pub fn range(&self, range: impl RangeBounds<usize>) -> Iter<'_, T> {
+        let range = self.index_range(range);
+        Iter {
+            len: range.len(),
+            range: self.map.range_at(range),
+        }
+    }
+
+    /// Iterates over mutable references to the values in the index range. *Panic*s if the range is
+    /// out of bounds or decreasing.
+    #[inline]
+    pub fn range_mut(&mut self, range: impl RangeBounds<usize>) -> IterMut<'_, T> {
+        let range = self.index_range(range);
+        IterMut {
+            len: range.len(),
+            range: self.map.range_mut_at(range),
+        }
+    }
+
+    #[inline]
+    fn index_range(&self, range: impl RangeBounds<usize>) -> std::ops::Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start
+                .checked_add(1)
+                .expect("attempted to index from after maximum usize"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end
+                .checked_add(1)
+                .expect("attempted to index up to maximum usize"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(
+            start <= end && end <= self.len(),
+            "range {}..{} out of bounds for length {}",
+            start,
+            end,
+            self.len()
+        );
+        start..end
+    }
+
+    /// Validates the sequence, *panic*ing if it is invalid. Specifically, we check the b-tree and
+    /// its cached counts.
+    #[inline]
+    pub fn validate(&self)
+    where
+        T: Debug,
+    {
+        self.map.validate_by(None::<&OrdComparator>)
+    }
+}
+
+// region common trait impls
+impl<'store, T: Debug> Debug for BTreeVec<'store, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'store, T: PartialEq> PartialEq for BTreeVec<'store, T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<'store, T: Eq> Eq for BTreeVec<'store, T> {}
+
+impl<'store, T> Extend<T> for BTreeVec<'store, T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'store, T> Index<usize> for BTreeVec<'store, T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds")
+    }
+}
+
+impl<'store, T> IndexMut<usize> for BTreeVec<'store, T> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("index out of bounds")
+    }
+}
+
+impl<'a, 'store: 'a, T> IntoIterator for &'a BTreeVec<'store, T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, 'store: 'a, T> IntoIterator for &'a mut BTreeVec<'store, T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<'store, T> IntoIterator for BTreeVec<'store, T> {
+    type Item = T;
+    type IntoIter = IntoIter<'store, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.map.into_iter())
+    }
+}
+// endregion
+
+// region iterators
+/// Iterator over the values in an index range
+pub struct Iter<'a, T> {
+    range: crate::map::Range<'a, (), T, Count>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let ((), value) = self.range.next()?;
+        self.len -= 1;
+        Some(value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let ((), value) = self.range.next_back()?;
+        self.len -= 1;
+        Some(value)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a, T> FusedIterator for Iter<'a, T> {}
+
+/// Iterator over mutable references to the values in an index range
+pub struct IterMut<'a, T> {
+    range: crate::map::RangeMut<'a, (), T, Count>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let ((), value) = self.range.next()?;
+        self.len -= 1;
+        Some(value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let ((), value) = self.range.next_back()?;
+        self.len -= 1;
+        Some(value)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl<'a, T> FusedIterator for IterMut<'a, T> {}
+
+/// Consuming iterator over the values
+pub struct IntoIter<'store, T>(crate::map::IntoIter<'store, (), T, Count>);
+
+impl<'store, T> Iterator for IntoIter<'store, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|((), value)| value)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'store, T> DoubleEndedIterator for IntoIter<'store, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|((), value)| value)
+    }
+}
+
+impl<'store, T> ExactSizeIterator for IntoIter<'store, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'store, T> FusedIterator for IntoIter<'store, T> {}
+// endregion
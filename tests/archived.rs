@@ -0,0 +1,108 @@
+#![cfg(feature = "copyable")]
+
+use std::collections::BTreeMap as StdBTreeMap;
+use std::io::ErrorKind;
+
+use btree_plus_store::copyable::{self, ArchivedBTreeMap};
+use btree_plus_store::BTreeStore;
+
+/// Copies bytes into a buffer aligned to 16 bytes, like an mmapped file would be
+fn aligned(bytes: &[u8]) -> Vec<u128> {
+    let mut buf = vec![0u128; bytes.len().div_ceil(16)];
+    unsafe {
+        std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, bytes.len())
+            .copy_from_slice(bytes)
+    };
+    buf
+}
+
+fn as_bytes(buf: &[u128], len: usize) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(buf.as_ptr() as *const u8, len) }
+}
+
+#[test]
+fn archived_map() {
+    let store = BTreeStore::new();
+    for n in [0u64, 1, 7, 8, 9, 100, 1000] {
+        let map = copyable::BTreeMap::build(&store, |map| {
+            for i in 0..n {
+                map.insert(i * 2, [i as u32, !(i as u32)]);
+            }
+        });
+        let std_map = map
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect::<StdBTreeMap<_, _>>();
+
+        let mut bytes = Vec::new();
+        map.write_archived(&mut bytes).unwrap();
+        let buf = aligned(&bytes);
+        let archived = ArchivedBTreeMap::<u64, [u32; 2]>::new(as_bytes(&buf, bytes.len())).unwrap();
+
+        assert_eq!(archived.len(), n as usize);
+        assert_eq!(archived.is_empty(), n == 0);
+        assert!(archived.iter().eq(std_map.iter()));
+        assert!(archived.iter().rev().eq(std_map.iter().rev()));
+        assert_eq!(archived.iter().len(), n as usize);
+        assert_eq!(archived.first_key_value(), std_map.first_key_value());
+        assert_eq!(archived.last_key_value(), std_map.last_key_value());
+        for i in 0..n * 2 + 1 {
+            assert_eq!(archived.get(&i), std_map.get(&i));
+            assert_eq!(archived.contains_key(&i), std_map.contains_key(&i));
+        }
+        for (start, end) in [
+            (0, 0),
+            (0, 5),
+            (3, 17),
+            (8, 9),
+            (n, n * 2),
+            (n * 2 - n / 3, n * 3),
+        ] {
+            assert!(archived.range(start..end).eq(std_map.range(start..end)));
+            assert!(archived.range(start..=end).eq(std_map.range(start..=end)));
+            assert!(archived
+                .range(start..=end)
+                .rev()
+                .eq(std_map.range(start..=end).rev()));
+            assert!(archived.range(start..).eq(std_map.range(start..)));
+        }
+
+        // Iterating from both ends meets in the middle
+        let mut iter = archived.iter();
+        let mut count = 0;
+        while iter.next().is_some() {
+            count += 1;
+            if iter.next_back().is_some() {
+                count += 1;
+            }
+        }
+        assert_eq!(count, n);
+    }
+}
+
+#[test]
+fn archived_map_rejects_bad_input() {
+    let store = BTreeStore::new();
+    let map = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..100u64 {
+            map.insert(i, i);
+        }
+    });
+    let mut bytes = Vec::new();
+    map.write_archived(&mut bytes).unwrap();
+    let buf = aligned(&bytes);
+    let bytes = as_bytes(&buf, bytes.len());
+
+    // Different types
+    let err = ArchivedBTreeMap::<u32, u64>::new(bytes).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    // Misaligned
+    let err = ArchivedBTreeMap::<u64, u64>::new(&bytes[1..]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    // Truncated
+    let err = ArchivedBTreeMap::<u64, u64>::new(&bytes[..bytes.len() - 16]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    // Not an archive
+    let err = ArchivedBTreeMap::<u64, u64>::new(as_bytes(&[0; 64], 1024)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
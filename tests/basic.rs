@@ -112,6 +112,35 @@ pub fn extend() {
     }
 }
 
+#[test]
+pub fn from_sorted_iter() {
+    let store = BTreeStore::new();
+
+    for len in [0, 1, 7, 8, 9, 16, 17, 72, 73, 81, 82, 1000, 4097] {
+        let mut btree = BTreeMap::from_sorted_iter_in(&store, (0..len).map(|i| (i, i * 2)));
+        btree.validate();
+        assert_eq!(btree.len(), len);
+        assert!(btree
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..len).map(|i| (i, i * 2))));
+
+        // Still valid after mutation
+        for i in (0..len).step_by(3) {
+            btree.remove(&i);
+        }
+        btree.insert(len, 0);
+        btree.validate();
+    }
+}
+
+#[test]
+#[should_panic(expected = "keys aren't strictly ascending")]
+pub fn from_sorted_iter_unsorted() {
+    let store = BTreeStore::new();
+    BTreeMap::from_sorted_iter_in(&store, [(1, 1), (1, 2)]);
+}
+
 const ITEMS: [(usize, usize); 100] = [
     (4223, 5948),
     (8175, 4629),
@@ -0,0 +1,313 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::BTreeMap as StdBTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use btree_plus_store::{BTreeStore, BiBTreeMap};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+#[test]
+pub fn bimap() {
+    let left_store = BTreeStore::new();
+    let right_store = BTreeStore::new();
+    let mut bimap = BiBTreeMap::new_in(&left_store, &right_store);
+    let mut std_left = StdBTreeMap::new();
+    let mut std_right = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..5000 {
+        let left = rng.gen_range(0..500u32);
+        let right = format!("{:03}", rng.gen_range(0..500u32));
+        match rng.gen_range(0..4) {
+            0..=1 => {
+                let old_right = std_left.remove(&left);
+                if let Some(old_right) = &old_right {
+                    std_right.remove(old_right);
+                }
+                let old_left = std_right.remove(&right);
+                if let Some(old_left) = &old_left {
+                    std_left.remove(old_left);
+                }
+                std_left.insert(left, right.clone());
+                std_right.insert(right.clone(), left);
+                assert_eq!(bimap.insert(left, right), (old_right, old_left));
+            }
+            2 => {
+                let expected = std_left.remove(&left).map(|right| {
+                    std_right.remove(&right);
+                    (left, right)
+                });
+                assert_eq!(bimap.remove_by_left(&left), expected);
+            }
+            _ => {
+                let expected = std_right.remove(&right).map(|left| {
+                    std_left.remove(&left);
+                    (left, right.clone())
+                });
+                assert_eq!(bimap.remove_by_right(right.as_str()), expected);
+            }
+        }
+        if i % 100 == 0 {
+            bimap.validate();
+        }
+    }
+    bimap.validate();
+
+    assert_eq!(bimap.len(), std_left.len());
+    assert!(bimap.iter_by_left().eq(std_left.iter()));
+    assert!(bimap
+        .iter_by_right()
+        .map(|(left, right)| (right, left))
+        .eq(std_right.iter()));
+    for left in 0..500 {
+        assert_eq!(bimap.get_by_left(&left), std_left.get(&left));
+        if let Some(right) = std_left.get(&left) {
+            assert_eq!(bimap.get_by_right(right.as_str()), Some(&left));
+            assert!(bimap.contains_right(right.as_str()));
+        }
+    }
+    for _ in 0..100 {
+        let start = rng.gen_range(0..500u32);
+        let end = rng.gen_range(start..500);
+        assert!(bimap
+            .range_by_left(start..end)
+            .eq(std_left.range(start..end)));
+        let (start, end) = (format!("{:03}", start), format!("{:03}", end));
+        assert!(bimap
+            .range_by_right(start.clone()..end.clone())
+            .rev()
+            .map(|(left, right)| (right, left))
+            .eq(std_right.range(start..end).rev()));
+    }
+}
+
+thread_local! {
+    static PANIC_ON_CMP: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Key whose comparisons *panic* when told to
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fragile(u32);
+
+impl PartialOrd for Fragile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fragile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if PANIC_ON_CMP.with(Cell::get) {
+            panic!("comparison panicked");
+        }
+        self.0.cmp(&other.0)
+    }
+}
+
+#[test]
+pub fn panic_keeps_directions_in_sync() {
+    let left_store = BTreeStore::new();
+    let right_store = BTreeStore::new();
+    let mut bimap = BiBTreeMap::new_in(&left_store, &right_store);
+    for i in 0..100 {
+        bimap.insert(i, Fragile(i * 2));
+    }
+
+    // The left direction is updated, then the right direction panics, so the pair is put back
+    PANIC_ON_CMP.with(|p| p.set(true));
+    let result = catch_unwind(AssertUnwindSafe(|| bimap.remove_by_left(&50)));
+    assert!(result.is_err());
+    let result = catch_unwind(AssertUnwindSafe(|| bimap.insert(60, Fragile(1))));
+    assert!(result.is_err());
+    PANIC_ON_CMP.with(|p| p.set(false));
+    bimap.validate();
+    assert_eq!(bimap.len(), 100);
+    assert_eq!(bimap.get_by_left(&50), Some(&Fragile(100)));
+    assert_eq!(bimap.get_by_left(&60), Some(&Fragile(120)));
+    assert!(bimap
+        .iter_by_left()
+        .map(|(left, right)| (*left, right.0))
+        .eq((0..100).map(|i| (i, i * 2))));
+
+    bimap.insert(1, Fragile(3));
+    assert_eq!(bimap.get_by_right(&Fragile(3)), Some(&1));
+    assert_eq!(bimap.get_by_right(&Fragile(2)), None);
+    bimap.validate();
+}
+
+thread_local! {
+    static FLAKY_OPS: Cell<u32> = const { Cell::new(0) };
+    static PANIC_AT_OPS: Cell<(u32, u32)> = const { Cell::new((u32::MAX, u32::MAX)) };
+    static PANIC_ON_CLONE_DROP: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Key whose comparisons and clones *panic* at the scheduled operations, and whose clones
+/// *panic* once when dropped when told to
+#[derive(Debug)]
+struct Flaky {
+    value: u32,
+    is_clone: bool,
+}
+
+impl Flaky {
+    fn new(value: u32) -> Self {
+        Flaky {
+            value,
+            is_clone: false,
+        }
+    }
+
+    fn op() {
+        let op = FLAKY_OPS.with(|ops| ops.replace(ops.get() + 1));
+        let (first, second) = PANIC_AT_OPS.with(Cell::get);
+        if op == first || op == second {
+            panic!("operation {} panicked", op);
+        }
+    }
+
+    /// Panics at the given operations from now on
+    fn schedule(panic_at: (u32, u32)) {
+        FLAKY_OPS.with(|ops| ops.set(0));
+        PANIC_AT_OPS.with(|p| p.set(panic_at));
+    }
+}
+
+impl Clone for Flaky {
+    fn clone(&self) -> Self {
+        Self::op();
+        Flaky {
+            value: self.value,
+            is_clone: true,
+        }
+    }
+}
+
+impl Drop for Flaky {
+    fn drop(&mut self) {
+        if self.is_clone && PANIC_ON_CLONE_DROP.with(|p| p.replace(false)) {
+            panic!("drop panicked");
+        }
+    }
+}
+
+impl PartialEq for Flaky {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for Flaky {}
+
+impl PartialOrd for Flaky {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Flaky {
+    fn cmp(&self, other: &Self) -> Ordering {
+        Self::op();
+        self.value.cmp(&other.value)
+    }
+}
+
+#[test]
+pub fn panic_restores_removed_pairs() {
+    let left_store = BTreeStore::new();
+    let right_store = BTreeStore::new();
+    let mut bimap = BiBTreeMap::new_in(&left_store, &right_store);
+    let mut std_left = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    let (mut num_panics, mut num_double_panics) = (0, 0);
+    for _ in 0..3000 {
+        let (left, right) = (rng.gen_range(0..200u32), rng.gen_range(0..200u32));
+        let op = rng.gen_range(0..4);
+        // One *panic* must leave the map unchanged, after two the directions must still agree
+        let first = rng.gen_range(0..40);
+        let second = if rng.gen_bool(0.3) {
+            rng.gen_range(first + 1..60)
+        } else {
+            u32::MAX
+        };
+        Flaky::schedule((first, second));
+        let result = catch_unwind(AssertUnwindSafe(|| match op {
+            0..=1 => {
+                bimap.insert(Flaky::new(left), Flaky::new(right));
+            }
+            2 => {
+                bimap.remove_by_left(&Flaky::new(left));
+            }
+            _ => {
+                bimap.remove_by_right(&Flaky::new(right));
+            }
+        }));
+        let num_ops = FLAKY_OPS.with(Cell::get);
+        Flaky::schedule((u32::MAX, u32::MAX));
+        bimap.validate();
+
+        if result.is_ok() {
+            match op {
+                0..=1 => {
+                    std_left.retain(|l, r| *l != left && *r != right);
+                    std_left.insert(left, right);
+                }
+                2 => {
+                    std_left.remove(&left);
+                }
+                _ => std_left.retain(|_, r| *r != right),
+            }
+        } else if second >= num_ops {
+            num_panics += 1;
+        } else {
+            // The pairs involved may have been removed, but the others are untouched
+            num_double_panics += 1;
+            for (l, r) in bimap.iter_by_left() {
+                assert_eq!(std_left.get(&l.value), Some(&r.value));
+            }
+            for (l, r) in &std_left {
+                if *l != left && *r != right {
+                    assert_eq!(bimap.get_by_left(&Flaky::new(*l)), Some(&Flaky::new(*r)));
+                }
+            }
+            std_left.retain(|l, _| bimap.contains_left(&Flaky::new(*l)));
+        }
+        assert!(bimap
+            .iter_by_left()
+            .map(|(left, right)| (left.value, right.value))
+            .eq(std_left.iter().map(|(left, right)| (*left, *right))));
+    }
+    assert!(num_panics > 100);
+    assert!(num_double_panics > 10);
+}
+
+#[test]
+pub fn separator_drop_panic_removes_pair() {
+    let left_store = BTreeStore::new();
+    let right_store = BTreeStore::new();
+    let mut bimap = BiBTreeMap::new_in(&left_store, &right_store);
+    for i in 0..500 {
+        bimap.insert(Flaky::new(i), i * 2);
+    }
+
+    // The panic can come after the pair was removed from one direction, so it's found by value
+    // and removed from the other
+    let mut num_panics = 0;
+    for i in (0..500).step_by(3) {
+        PANIC_ON_CLONE_DROP.with(|p| p.set(true));
+        let result = catch_unwind(AssertUnwindSafe(|| bimap.remove_by_left(&Flaky::new(i))));
+        PANIC_ON_CLONE_DROP.with(|p| p.set(false));
+        num_panics += result.is_err() as usize;
+        bimap.validate();
+        assert!(!bimap.contains_left(&Flaky::new(i)));
+        assert!(!bimap.contains_right(&(i * 2)));
+        assert_eq!(bimap.len(), 500 - (i as usize / 3 + 1));
+    }
+    assert!(num_panics > 0);
+    assert!(bimap
+        .iter_by_left()
+        .map(|(left, right)| (left.value, *right))
+        .eq((0..500).filter(|i| i % 3 != 0).map(|i| (i, i * 2))));
+}
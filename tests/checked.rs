@@ -0,0 +1,109 @@
+#![cfg(feature = "checked")]
+
+use btree_plus_store::copyable::{self, BTreeStoreExt};
+use btree_plus_store::{BTreeMap, BTreeStore};
+
+#[test]
+fn test_survivors_readable() {
+    let store = BTreeStore::new();
+
+    let map = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..100 {
+            map.insert(i, i * 10);
+        }
+    });
+    let empty = copyable::BTreeMap::build(&store, |_| {});
+
+    unsafe { store.tracing_gc([map]) };
+    assert_eq!(map.get(&50), Some(&500));
+    assert!(empty.is_empty());
+
+    // Still alive after another gc, as long as it's passed again
+    let map2 = copyable::BTreeMap::build(&store, |map| {
+        map.insert(1, 1);
+    });
+    unsafe { store.tracing_gc([map, map2]) };
+    assert_eq!(map.len(), 100);
+    assert_eq!(map2.get(&1), Some(&1));
+}
+
+#[test]
+#[should_panic(expected = "copyable b-tree was used after tracing_gc freed its nodes")]
+fn test_use_after_gc() {
+    let store = BTreeStore::new();
+
+    let map = copyable::BTreeMap::build(&store, |map| {
+        map.insert(1, 10);
+    });
+    let map2 = copyable::BTreeMap::build(&store, |map| {
+        map.insert(2, 20);
+    });
+
+    unsafe { store.tracing_gc([map2]) };
+    assert_eq!(map2.get(&2), Some(&20));
+    map.get(&1);
+}
+
+#[test]
+#[should_panic(expected = "copyable b-tree was used after tracing_gc freed its nodes")]
+fn test_set_use_after_second_gc() {
+    let store = BTreeStore::new();
+
+    let set = copyable::BTreeSet::build(&store, |set| {
+        set.insert(1);
+    });
+
+    unsafe { store.tracing_gc([set]) };
+    assert!(set.contains(&1));
+    unsafe { store.tracing_gc(Vec::<copyable::BTreeSet<_>>::new()) };
+    set.contains(&1);
+}
+
+#[test]
+#[should_panic(expected = "b-tree is not from this store")]
+fn test_wrong_store() {
+    let store = BTreeStore::new();
+    let store2 = BTreeStore::new();
+
+    let mut map = BTreeMap::new_in(&store);
+    map.insert(1, 10);
+    let map = copyable::BTreeMap::from(map);
+
+    unsafe { store2.tracing_gc([map]) };
+}
+
+#[test]
+#[should_panic(expected = "b-tree is not from this store")]
+fn test_set_wrong_store() {
+    let store = BTreeStore::new();
+    let store2 = BTreeStore::new();
+
+    let set = copyable::BTreeSet::build(&store, |set| {
+        set.insert(1);
+    });
+    unsafe { store.tracing_gc([set]) };
+    assert!(set.contains(&1));
+
+    unsafe { store2.tracing_gc([set]) };
+}
+
+#[test]
+#[should_panic(expected = "copyable b-tree was used after tracing_gc freed its nodes")]
+fn test_restored_handle_after_gc() {
+    let store = BTreeStore::new();
+
+    let map = copyable::BTreeMap::build(&store, |map| {
+        map.insert(1u32, 10u32);
+    });
+    let map2 = copyable::BTreeMap::build(&store, |map| {
+        map.insert(2u32, 20u32);
+    });
+    let mut bytes = Vec::new();
+    store.snapshot([map, map2], &mut bytes).unwrap();
+
+    let (store2, handles) = BTreeStore::<u32, u32>::restore(bytes.as_slice()).unwrap();
+    let map2 = handles[1].to_map(&store2);
+    unsafe { store2.tracing_gc([map2]) };
+    assert_eq!(map2.get(&2), Some(&20));
+    handles[0].to_map(&store2);
+}
@@ -0,0 +1,85 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BTreeMap as StdBTreeMap;
+
+use btree_plus_store::{BTreeMap, BTreeStore, Comparator};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+/// A comparator chosen at runtime
+struct CaseInsensitive {
+    reverse: bool,
+}
+
+impl Comparator<String> for CaseInsensitive {
+    fn cmp(&self, a: &String, b: &String) -> Ordering {
+        let ordering = a.to_lowercase().cmp(&b.to_lowercase());
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+#[test]
+pub fn reverse_comparator() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::with_comparator_in(&store, |a: &u32, b: &u32| b.cmp(a));
+    let mut std_btree = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for _ in 0..2000 {
+        let key = rng.gen_range(0..500);
+        if rng.gen_bool(0.7) {
+            assert_eq!(
+                btree.insert(key, key * 2),
+                std_btree.insert(Reverse(key), key * 2)
+            );
+        } else {
+            assert_eq!(btree.remove(&key), std_btree.remove(&Reverse(key)));
+        }
+    }
+    btree.validate();
+
+    assert!(btree
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .eq(std_btree.iter().map(|(k, v)| (k.0, *v))));
+    // Ranges are in the comparator's order
+    let (start, end) = (400, 100);
+    assert!(btree.range(start..=end).map(|(k, _)| *k).eq(std_btree
+        .range(Reverse(start)..=Reverse(end))
+        .map(|(k, _)| k.0)));
+    assert_eq!(
+        btree.first_key_value().map(|(k, _)| *k),
+        std_btree.first_key_value().map(|(k, _)| k.0)
+    );
+}
+
+#[test]
+pub fn runtime_comparator() {
+    let store = BTreeStore::new();
+    for reverse in [false, true] {
+        let mut btree = BTreeMap::with_comparator_in(&store, CaseInsensitive { reverse });
+        for word in ["banana", "Apple", "cherry", "apple", "BANANA", "date"] {
+            btree.insert(word.to_string(), word.len());
+        }
+        btree.validate();
+
+        // Keys which compare equal replace the value but keep the original key
+        assert_eq!(btree.len(), 4);
+        assert_eq!(
+            btree.get_key_value(&String::from("APPLE")),
+            Some((&String::from("Apple"), &5))
+        );
+        let mut keys = btree.keys().cloned().collect::<Vec<_>>();
+        if reverse {
+            keys.reverse();
+        }
+        assert_eq!(keys, ["Apple", "banana", "cherry", "date"]);
+        assert_eq!(btree.remove(&String::from("CHERRY")), Some(6));
+        assert!(!btree.contains_key(&String::from("cherry")));
+        assert!(btree.comparator().reverse == reverse);
+    }
+}
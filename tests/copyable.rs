@@ -1,7 +1,10 @@
 #![cfg(feature = "copyable")]
 
-use btree_plus_store::{copyable, BTreeMap, BTreeStore};
+use btree_plus_store::copyable::BTreeStoreExt;
+use btree_plus_store::{copyable, BTreeMap, BTreeSet, BTreeStore};
 use std::cell::Cell;
+use std::collections::BTreeMap as StdBTreeMap;
+use std::ops::Bound;
 use std::rc::Rc;
 
 #[test]
@@ -142,3 +145,238 @@ fn test_drop_contents() {
     drop(map3);
     assert_eq!(drop_count.get(), 2);
 }
+
+#[test]
+fn test_into_iter_by_value() {
+    let store = BTreeStore::new();
+
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(i, i * 10);
+    }
+    let map = copyable::BTreeMap::from(map);
+
+    // The references outlive the copy they came from
+    let pairs = {
+        let map2 = map;
+        map2.into_iter().collect::<Vec<_>>()
+    };
+    assert_eq!(pairs.len(), 100);
+    for (i, (key, value)) in pairs.into_iter().enumerate() {
+        assert_eq!((*key, *value), (i, i * 10));
+    }
+    assert_eq!(map.into_iter().next_back(), Some((&99, &990)));
+}
+
+#[test]
+fn test_set_ops() {
+    let store = BTreeStore::new();
+
+    let mut set1 = BTreeSet::new_in(&store);
+    let mut set2 = BTreeSet::new_in(&store);
+    for i in 0..50 {
+        set1.insert(i * 2);
+        set2.insert(i * 3);
+    }
+    let set1 = copyable::BTreeSet::from(set1);
+    let set2 = copyable::BTreeSet::from(set2);
+
+    assert_eq!(
+        set1.intersection(&set2).copied().collect::<Vec<_>>(),
+        (0..100).filter(|i| i % 6 == 0).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        set1.union(&set2).count(),
+        set1.len() + set2.len() - set1.intersection(&set2).count()
+    );
+    assert_eq!(
+        set2.difference(&set1).copied().collect::<Vec<_>>(),
+        (0..150).filter(|i| i % 3 == 0 && (i % 2 != 0 || *i >= 100)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        set1.symmetric_difference(&set2).count(),
+        set1.difference(&set2).count() + set2.difference(&set1).count()
+    );
+    assert!(!set1.is_disjoint(&set2));
+    assert!(!set1.is_subset(&set2));
+    assert!(set1.is_superset(&set1));
+    assert_eq!(set1.into_iter().next_back(), Some(&98));
+}
+
+#[test]
+fn test_diff() {
+    use copyable::map::DiffItem;
+
+    let store = BTreeStore::new();
+
+    let old = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..200 {
+            map.insert(i, i);
+        }
+    });
+    let new = copyable::BTreeMap::build(&store, |map| {
+        for i in 50..250 {
+            map.insert(i, if i % 10 == 0 { i + 1 } else { i });
+        }
+    });
+
+    // Shared roots short-circuit
+    let old2 = old;
+    assert_eq!(old.diff(&old2).count(), 0);
+
+    let mut expected = Vec::new();
+    expected.extend((0..50).map(|i| (i, DiffItem::Removed(i, i))));
+    expected.extend((50..200).filter(|i| i % 10 == 0).map(|i| {
+        (
+            i,
+            DiffItem::Changed {
+                key: i,
+                old: i,
+                new: i + 1,
+            },
+        )
+    }));
+    expected
+        .extend((200..250).map(|i| (i, DiffItem::Added(i, if i % 10 == 0 { i + 1 } else { i }))));
+    expected.sort_by_key(|(i, _)| *i);
+    let expected = expected
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect::<Vec<_>>();
+
+    let diff = old
+        .diff(&new)
+        .map(|item| match item {
+            DiffItem::Added(k, v) => DiffItem::Added(*k, *v),
+            DiffItem::Removed(k, v) => DiffItem::Removed(*k, *v),
+            DiffItem::Changed { key, old, new } => DiffItem::Changed {
+                key: *key,
+                old: *old,
+                new: *new,
+            },
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(diff, expected);
+}
+
+#[test]
+fn test_diff_copies() {
+    /// Values which can't be compared, so diffing copies must skip them
+    #[derive(Debug)]
+    struct Incomparable;
+
+    impl PartialEq for Incomparable {
+        fn eq(&self, _: &Self) -> bool {
+            panic!("diff compared a value of a copy")
+        }
+    }
+
+    let store = BTreeStore::new();
+    let map = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..10_000 {
+            map.insert(i, Incomparable);
+        }
+    });
+    let copy = map;
+    assert_eq!(map.diff(&copy).count(), 0);
+    assert_eq!(copy.diff(&map).count(), 0);
+}
+
+#[test]
+fn test_tracing_gc() {
+    let store = BTreeStore::new();
+
+    let maps = (0..20)
+        .map(|j| {
+            copyable::BTreeMap::build(&store, |map| {
+                for i in 0..(j * 500) {
+                    map.insert(i, i * 10);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut map = BTreeMap::new_in(&store);
+    map.insert(1, 1);
+
+    let live = maps.iter().step_by(2).collect::<Vec<_>>();
+    unsafe { store.tracing_gc(live.iter().copied().chain([&map.into()])) };
+
+    // Reuses the freed nodes
+    let map2 = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..5000 {
+            map.insert(i, i);
+        }
+    });
+
+    for (j, map) in live.into_iter().enumerate() {
+        map.validate();
+        assert_eq!(map.len(), j * 1000);
+        assert!(map
+            .iter()
+            .enumerate()
+            .all(|(i, (k, v))| *k == i && *v == i * 10));
+    }
+    map2.validate();
+    assert_eq!(map2.len(), 5000);
+}
+
+#[test]
+fn test_cursors() {
+    let store = BTreeStore::new();
+    let map = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..500 {
+            map.insert(i * 2, i);
+        }
+        map.insert(501, 0);
+        map.insert(1000, 500);
+    });
+    let std_map = map
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .collect::<StdBTreeMap<_, _>>();
+
+    for key in [0, 1, 2, 500, 501, 502, 998, 999, 1000, 1001] {
+        let bounds = [
+            Bound::Included(&key),
+            Bound::Excluded(&key),
+            Bound::Unbounded,
+        ];
+        for bound in bounds {
+            // Walk the whole map from the cursor in both directions
+            let mut cursor = map.lower_bound(bound);
+            let mut expected = std_map.range((bound, Bound::Unbounded));
+            while let Some((k, v)) = cursor.key_value() {
+                assert_eq!(Some((k, v)), expected.next());
+                cursor.move_next();
+            }
+            assert_eq!(expected.next(), None);
+            assert!(!cursor.is_attached());
+            cursor.move_prev();
+            assert_eq!(cursor.key(), None);
+
+            let mut cursor = map.upper_bound(bound);
+            let mut expected = std_map.range((Bound::Unbounded, bound)).rev();
+            while let Some(value) = cursor.value() {
+                assert_eq!(Some(value), expected.next().map(|(_, v)| v));
+                cursor.move_prev();
+            }
+            assert_eq!(expected.next(), None);
+        }
+    }
+    let mut cursor = map.lower_bound(Bound::Included(&500));
+    cursor.move_next();
+    cursor.move_prev();
+    assert_eq!(cursor.key_value(), Some((&500, &250)));
+
+    let set_store = BTreeStore::new();
+    let set = copyable::BTreeSet::build(&set_store, |set| {
+        set.extend((0..100).map(|i| i * 3));
+    });
+    let mut cursor = set.lower_bound(Bound::Excluded(&30));
+    assert_eq!(cursor.value(), Some(&33));
+    cursor.move_prev();
+    cursor.move_prev();
+    assert_eq!(cursor.value(), Some(&27));
+    assert_eq!(set.upper_bound(Bound::Excluded(&0)).value(), None);
+    assert_eq!(set.upper_bound::<i32>(Bound::Unbounded).value(), Some(&297));
+}
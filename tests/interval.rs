@@ -0,0 +1,174 @@
+use std::ops::Range;
+
+use btree_plus_store::{BTreeStore, IntervalMap, RangeSet};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+const DOMAIN: u32 = 300;
+
+/// Ranges of equal values in the naive model, which is coalesced like the map
+fn model_ranges(model: &[Option<u8>]) -> Vec<(Range<u32>, u8)> {
+    let mut ranges = Vec::<(Range<u32>, u8)>::new();
+    for (point, value) in model.iter().enumerate() {
+        let point = point as u32;
+        let Some(value) = *value else { continue };
+        match ranges.last_mut() {
+            Some((range, last_value)) if range.end == point && *last_value == value => {
+                range.end += 1
+            }
+            _ => ranges.push((point..point + 1, value)),
+        }
+    }
+    ranges
+}
+
+fn random_range(rng: &mut SmallRng) -> Range<u32> {
+    let start = rng.gen_range(0..DOMAIN);
+    start..(start + rng.gen_range(0..20)).min(DOMAIN)
+}
+
+#[test]
+pub fn interval_map() {
+    let store = BTreeStore::new();
+    let mut map = IntervalMap::new_in(&store);
+    let mut model = vec![None; DOMAIN as usize];
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..2000 {
+        let range = random_range(&mut rng);
+        if rng.gen_bool(0.7) {
+            let value = rng.gen_range(0..3u8);
+            map.insert_range(range.clone(), value);
+            model[range.start as usize..range.end as usize].fill(Some(value));
+        } else {
+            map.remove_range(range.clone());
+            model[range.start as usize..range.end as usize].fill(None);
+        }
+        if i % 100 == 0 {
+            map.validate();
+        }
+    }
+    map.validate();
+
+    let ranges = model_ranges(&model);
+    assert!(map
+        .iter()
+        .map(|(range, v)| (*range.start..*range.end, *v))
+        .eq(ranges.iter().cloned()));
+    for point in 0..DOMAIN {
+        assert_eq!(map.get(&point), model[point as usize].as_ref());
+    }
+
+    for _ in 0..200 {
+        let query = random_range(&mut rng);
+        let expected_overlapping = ranges
+            .iter()
+            .filter(|(range, _)| {
+                !query.is_empty() && range.start < query.end && query.start < range.end
+            })
+            .cloned();
+        assert!(map
+            .overlapping(&query)
+            .map(|(range, v)| (*range.start..*range.end, *v))
+            .eq(expected_overlapping));
+
+        let gap_ranges = model_ranges(
+            &(query.clone())
+                .map(|point| model[point as usize].is_none().then_some(0))
+                .collect::<Vec<_>>(),
+        );
+        assert!(map
+            .gaps(&query)
+            .map(|range| *range.start..*range.end)
+            .eq(gap_ranges
+                .into_iter()
+                .map(|(range, _)| range.start + query.start..range.end + query.start)));
+    }
+}
+
+#[test]
+pub fn splitting_and_coalescing() {
+    let store = BTreeStore::new();
+    let mut map = IntervalMap::new_in(&store);
+    map.insert_range(0..10, 'a');
+    map.insert_range(3..5, 'b');
+    map.validate();
+    assert!(map
+        .iter()
+        .eq([(&0..&3, &'a'), (&3..&5, &'b'), (&5..&10, &'a')]));
+
+    map.insert_range(3..5, 'a');
+    map.validate();
+    assert!(map.iter().eq([(&0..&10, &'a')]));
+
+    map.remove_range(2..4);
+    map.insert_range(12..15, 'a');
+    assert!(map.gaps(&(0..20)).eq([&2..&4, &10..&12, &15..&20]));
+    assert_eq!(map.get_range_value(&5), Some((&4..&10, &'a')));
+    assert_eq!(map.get(&10), None);
+
+    // Empty ranges are ignored
+    map.insert_range(7..7, 'c');
+    let (start, end) = (9, 5);
+    map.remove_range(start..end);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.overlapping(&(5..5)).count(), 0);
+}
+
+#[test]
+pub fn range_set() {
+    let store = BTreeStore::new();
+    let mut set = RangeSet::new_in(&store);
+    let mut model = vec![None; DOMAIN as usize];
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for _ in 0..2000 {
+        let range = random_range(&mut rng);
+        if rng.gen_bool(0.6) {
+            set.insert_range(range.clone());
+            model[range.start as usize..range.end as usize].fill(Some(0));
+        } else {
+            set.remove_range(range.clone());
+            model[range.start as usize..range.end as usize].fill(None);
+        }
+    }
+    set.validate();
+
+    let ranges = model_ranges(&model);
+    assert!(set
+        .iter()
+        .map(|range| *range.start..*range.end)
+        .eq(ranges.iter().map(|(range, _)| range.clone())));
+    for point in 0..DOMAIN {
+        assert_eq!(set.contains(&point), model[point as usize].is_some());
+        assert_eq!(
+            set.get(&point).map(|range| *range.start..*range.end),
+            ranges
+                .iter()
+                .find(|(range, _)| range.contains(&point))
+                .map(|(range, _)| range.clone())
+        );
+    }
+    let query = 50..150;
+    let gap_ranges = model_ranges(
+        &(query.clone())
+            .map(|point| model[point as usize].is_none().then_some(0))
+            .collect::<Vec<_>>(),
+    );
+    assert!(set
+        .gaps(&query)
+        .map(|range| *range.start..*range.end)
+        .eq(gap_ranges
+            .into_iter()
+            .map(|(range, _)| range.start + query.start..range.end + query.start)));
+    // Overlapping ranges and gaps tile the query
+    let covered = set
+        .overlapping(&query)
+        .map(|range| (*range.end).min(query.end) - (*range.start).max(query.start))
+        .sum::<u32>();
+    let uncovered = set
+        .gaps(&query)
+        .map(|range| range.end - range.start)
+        .sum::<u32>();
+    assert_eq!(covered + uncovered, query.end - query.start);
+}
@@ -308,3 +308,91 @@ fn set_into_iter() {
 
     assert_eq!(counter.get(), 100);
 }
+
+#[test]
+fn set_ops() {
+    let store = BTreeStore::new();
+    let mut set1 = BTreeSet::new_in(&store);
+    let mut set2 = BTreeSet::new_in(&store);
+    for i in 0..100 {
+        set1.insert(i * 2);
+        set2.insert(i * 3);
+    }
+
+    assert_eq!(
+        set1.intersection(&set2).copied().collect::<Vec<_>>(),
+        (0..200).filter(|i| i % 6 == 0).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        set1.union(&set2).copied().collect::<Vec<_>>(),
+        (0..300)
+            .filter(|i| (i % 2 == 0 && *i < 200) || i % 3 == 0)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        set1.difference(&set2).copied().collect::<Vec<_>>(),
+        (0..200).filter(|i| i % 2 == 0 && i % 3 != 0).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        set1.symmetric_difference(&set2).copied().collect::<Vec<_>>(),
+        (0..300)
+            .filter(|i| (i % 2 == 0 && *i < 200) != (i % 3 == 0))
+            .collect::<Vec<_>>()
+    );
+
+    assert!(!set1.is_disjoint(&set2));
+    assert!(!set1.is_subset(&set2));
+    let mut set3 = BTreeSet::new_in(&store);
+    set3.insert(6);
+    set3.insert(12);
+    assert!(set3.is_subset(&set1));
+    assert!(set3.is_subset(&set2));
+    assert!(set1.is_superset(&set3));
+    set3.insert(1);
+    assert!(!set3.is_subset(&set1));
+    assert!(set3.is_disjoint(&BTreeSet::new_in(&store)));
+}
+
+#[test]
+fn range() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(i * 2, i);
+    }
+
+    let keys = |range: btree_plus_store::map::Range<'_, i32, i32>| {
+        range.map(|(k, _)| *k).collect::<Vec<_>>()
+    };
+    assert_eq!(keys(map.range(10..16)), vec![10, 12, 14]);
+    assert_eq!(keys(map.range(10..=16)), vec![10, 12, 14, 16]);
+    assert_eq!(keys(map.range(9..15)), vec![10, 12, 14]);
+    assert_eq!(keys(map.range(..4)), vec![0, 2]);
+    assert_eq!(keys(map.range(194..)), vec![194, 196, 198]);
+    assert_eq!(map.range(..).count(), 100);
+    assert_eq!(map.range(10..10).count(), 0);
+    assert_eq!(map.range(11..12).count(), 0);
+    assert_eq!(map.range(300..).count(), 0);
+    assert_eq!(
+        map.range(10..=16)
+            .rev()
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>(),
+        vec![16, 14, 12, 10]
+    );
+
+    // Both ends
+    let mut range = map.range(0..=8);
+    assert_eq!(range.next(), Some((&0, &0)));
+    assert_eq!(range.next_back(), Some((&8, &4)));
+    assert_eq!(range.next(), Some((&2, &1)));
+    assert_eq!(range.next_back(), Some((&6, &3)));
+    assert_eq!(range.next(), Some((&4, &2)));
+    assert_eq!(range.next_back(), None);
+    assert_eq!(range.next(), None);
+
+    for (_, v) in map.range_mut(..=10) {
+        *v += 1000;
+    }
+    assert_eq!(map.values().filter(|v| **v >= 1000).count(), 6);
+}
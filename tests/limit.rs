@@ -0,0 +1,212 @@
+use std::collections::BTreeMap as StdBTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use btree_plus_store::{BTreeMap, BTreeSet, BTreeStore, BTreeVec, CapacityError, Count};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+fn assert_same(map: &BTreeMap<'_, u32, u32>, expected: &StdBTreeMap<u32, u32>) {
+    map.validate();
+    assert!(map
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .eq(expected.iter().map(|(k, v)| (*k, *v))));
+}
+
+#[test]
+fn try_insert_at_limit() {
+    let mut rng = SmallRng::from_seed(*SEED);
+    let store = BTreeStore::with_limit(12);
+    let mut map = BTreeMap::new_in(&store);
+    let mut expected = StdBTreeMap::new();
+    let mut num_errors = 0;
+    for _ in 0..1000 {
+        let key = rng.gen_range(0..200);
+        let value = rng.gen();
+        match map.try_insert(key, value) {
+            Ok(old) => assert_eq!(old, expected.insert(key, value)),
+            Err(err) => {
+                assert_eq!(err, CapacityError { key, value });
+                assert!(!expected.contains_key(&key));
+                num_errors += 1;
+            }
+        }
+        assert_same(&map, &expected);
+        assert!(store.num_nodes() <= 12);
+    }
+    assert!(num_errors > 0);
+
+    // Removing entries frees nodes
+    while let Some((key, _)) = map.pop_first() {
+        expected.remove(&key);
+        if map.len().is_multiple_of(10) {
+            assert_same(&map, &expected);
+        }
+    }
+    assert_eq!(store.num_nodes(), 0);
+    map.try_extend((0..8).map(|i| (i, i))).unwrap();
+    assert_eq!(store.num_nodes(), 1);
+}
+
+#[test]
+fn insert_panics_at_limit() {
+    let store = BTreeStore::with_limit(1);
+    let mut map = BTreeMap::new_in(&store);
+    let mut expected = StdBTreeMap::new();
+    for i in 0..8 {
+        map.insert(i, i);
+        expected.insert(i, i);
+    }
+    assert_eq!(map.try_get_or_insert(3, 0), Ok(&mut 3));
+    assert_eq!(
+        map.try_insert(8, 8),
+        Err(CapacityError { key: 8, value: 8 })
+    );
+    assert_eq!(
+        map.try_get_or_insert(8, 8),
+        Err(CapacityError { key: 8, value: 8 })
+    );
+    assert!(catch_unwind(AssertUnwindSafe(|| map.insert(8, 8))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| *map.get_or_insert(8, 8))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| map.update(8, |_| Some(8)))).is_err());
+    assert_same(&map, &expected);
+    assert_eq!(store.num_nodes(), 1);
+
+    // Replacing never allocates
+    assert_eq!(map.try_insert(0, 10), Ok(Some(0)));
+}
+
+#[test]
+fn try_extend_stops_at_limit() {
+    let store = BTreeStore::with_limit(3);
+    let mut map = BTreeMap::new_in(&store);
+    let mut iter = (0..100).map(|i| (i, i));
+    let err = map.try_extend(&mut iter).unwrap_err();
+    assert_eq!(err.key, map.len() as u32);
+    assert_eq!(iter.next(), Some((err.key + 1, err.key + 1)));
+    assert!(map.keys().copied().eq(0..err.key));
+    assert_eq!(store.num_nodes(), 3);
+    map.validate();
+}
+
+#[test]
+fn set_try_insert() {
+    let store = BTreeStore::with_limit(1);
+    let mut set = BTreeSet::new_in(&store);
+    assert_eq!(set.try_insert(0), Ok(true));
+    assert_eq!(set.try_insert(0), Ok(false));
+    set.try_extend(1..8).unwrap();
+    assert_eq!(set.try_insert(8), Err(CapacityError { key: 8, value: () }));
+    assert_eq!(
+        set.try_extend(8..10),
+        Err(CapacityError { key: 8, value: () })
+    );
+    assert!(set.iter().copied().eq(0..8));
+}
+
+#[test]
+fn bulk_load_over_limit() {
+    let store = BTreeStore::with_limit(10);
+    let entries = (0..100).map(|i| (i, i)).collect::<Vec<_>>();
+    assert!(catch_unwind(AssertUnwindSafe(|| {
+        BTreeMap::from_sorted_iter_in(&store, entries.clone())
+    }))
+    .is_err());
+    assert_eq!(store.num_nodes(), 0);
+
+    let map = BTreeMap::from_sorted_iter_in(&store, entries[..64].to_vec());
+    map.validate();
+    assert_eq!(store.num_nodes(), 9);
+}
+
+#[test]
+fn clear_store() {
+    let mut store = BTreeStore::with_limit(40);
+    let mut map = BTreeMap::new_in(&store);
+    map.try_extend((0..100).map(|i| (i, i))).unwrap();
+    let num_nodes = store.num_nodes();
+    std::mem::forget(map);
+
+    store.clear_without_dropping();
+    assert_eq!(store.num_nodes(), 0);
+    let mut map = BTreeMap::new_in(&store);
+    map.try_extend((0..100).map(|i| (i, i))).unwrap();
+    assert_eq!(store.num_nodes(), num_nodes);
+    map.validate();
+    std::mem::forget(map);
+
+    store.clear();
+    assert_eq!(store.num_nodes(), 0);
+}
+
+#[test]
+fn zero_limit() {
+    let store = BTreeStore::with_limit(0);
+    let mut map = BTreeMap::new_in(&store);
+    assert_eq!(map.try_insert(0, 0), Err(CapacityError { key: 0, value: 0 }));
+    assert!(catch_unwind(AssertUnwindSafe(|| map.insert(0, 0))).is_err());
+    assert!(map.is_empty());
+    assert_eq!(store.num_nodes(), 0);
+}
+
+#[test]
+fn summarized_limit() {
+    let store = BTreeStore::<u32, u32, Count>::summarized_with_limit(5);
+    let mut map = BTreeMap::new_in(&store);
+    let mut expected = StdBTreeMap::new();
+    for i in 0..100 {
+        if map.try_insert(i, i).is_ok() {
+            expected.insert(i, i);
+        }
+        assert!(store.num_nodes() <= 5);
+    }
+    assert!(expected.len() < 100);
+    map.validate();
+    assert!(map.iter().eq(expected.iter()));
+    assert_eq!(map.aggregate::<u32>(..), Count(expected.len()));
+
+    let store = BTreeStore::summarized_with_limit(2);
+    let mut vec = BTreeVec::new_in(&store);
+    let pushed = catch_unwind(AssertUnwindSafe(|| {
+        for i in 0..100 {
+            vec.push(i);
+        }
+    }));
+    assert!(pushed.is_err());
+    assert!(store.num_nodes() <= 2);
+}
+
+#[test]
+fn vec_split_off_append_at_limit() {
+    // Measure how many nodes 100 values need, then leave room for only one more
+    let store = BTreeStore::summarized();
+    let mut vec = BTreeVec::new_in(&store);
+    vec.extend(0..100);
+    let num_nodes = store.num_nodes();
+    drop(vec);
+
+    let store = BTreeStore::summarized_with_limit(num_nodes + 1);
+    let mut vec = BTreeVec::new_in(&store);
+    vec.extend((0..100).map(|i| i.to_string()));
+    assert!(catch_unwind(AssertUnwindSafe(|| vec.split_off(37))).is_err());
+    vec.validate();
+    assert!(vec.iter().cloned().eq((0..100).map(|i| i.to_string())));
+    assert_eq!(vec[60], "60");
+    assert_eq!(store.num_nodes(), num_nodes);
+
+    // Splitting at the ends doesn't allocate
+    assert!(vec.split_off(100).is_empty());
+
+    let mut other = BTreeVec::new_in(&store);
+    other.push("100".to_string());
+    assert!(catch_unwind(AssertUnwindSafe(|| vec.append(&mut other))).is_err());
+    vec.validate();
+    other.validate();
+    assert_eq!(vec.len(), 100);
+    assert_eq!(other.len(), 1);
+
+    drop(vec);
+    drop(other);
+    assert_eq!(store.num_nodes(), 0);
+}
@@ -0,0 +1,175 @@
+use std::collections::{BTreeMap as StdBTreeMap, VecDeque};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use btree_plus_store::{BTreeMultiMap, BTreeMultiSet, BTreeStore};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+#[test]
+pub fn multimap() {
+    let store = BTreeStore::new();
+    let mut multimap = BTreeMultiMap::new_in(&store);
+    let mut std_multimap = StdBTreeMap::<u32, VecDeque<usize>>::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..3000 {
+        let key = rng.gen_range(0..50);
+        match rng.gen_range(0..10) {
+            0..=6 => {
+                multimap.insert(key, i);
+                std_multimap.entry(key).or_default().push_back(i);
+            }
+            7..=8 => {
+                let std_removed = std_multimap.get_mut(&key).and_then(|vals| vals.pop_front());
+                assert_eq!(multimap.remove_one(&key), std_removed);
+            }
+            _ => {
+                let std_removed = std_multimap.remove(&key).map_or(0, |vals| vals.len());
+                assert_eq!(multimap.remove_all(&key), std_removed);
+            }
+        }
+        std_multimap.retain(|_, vals| !vals.is_empty());
+        if i % 100 == 0 {
+            multimap.validate();
+        }
+    }
+    multimap.validate();
+
+    assert_eq!(
+        multimap.len(),
+        std_multimap.values().map(|vals| vals.len()).sum::<usize>()
+    );
+    for key in 0..50 {
+        let std_vals = std_multimap.get(&key);
+        assert_eq!(multimap.count(&key), std_vals.map_or(0, |vals| vals.len()));
+        assert_eq!(multimap.contains_key(&key), std_vals.is_some());
+        assert_eq!(multimap.get(&key), std_vals.and_then(|vals| vals.front()));
+        assert!(multimap.get_all(&key).eq(std_vals.into_iter().flatten()));
+        assert!(multimap
+            .get_all(&key)
+            .rev()
+            .eq(std_vals.into_iter().flatten().rev()));
+    }
+    assert!(multimap.iter().eq(std_multimap
+        .iter()
+        .flat_map(|(k, vals)| vals.iter().map(move |v| (k, v)))));
+
+    for val in multimap.get_all_mut(&7) {
+        *val += 1;
+    }
+    assert!(multimap.get_all(&7).eq(std_multimap[&7]
+        .iter()
+        .map(|v| v + 1)
+        .collect::<Vec<_>>()
+        .iter()));
+}
+
+#[test]
+pub fn non_clone_multimap() {
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Key(u32);
+
+    // Safety: reading a bitwise copy of a `u32` is sound
+    unsafe impl btree_plus_store::Separator for Key {}
+
+    let store = BTreeStore::new();
+    let mut multimap = BTreeMultiMap::new_in(&store);
+    for i in 0..200 {
+        multimap.insert(Key(i % 7), i);
+    }
+    for i in 0..7 {
+        assert_eq!(multimap.remove_one(&Key(i)), Some(i));
+    }
+    multimap.validate();
+    assert_eq!(multimap.remove_all(&Key(3)), 28);
+    assert_eq!(multimap.len(), 200 - 7 - 28);
+}
+
+#[test]
+pub fn multiset() {
+    let store = BTreeStore::new();
+    let mut multiset = BTreeMultiSet::new_in(&store);
+    let mut std_multiset = StdBTreeMap::<u32, usize>::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..3000 {
+        let value = rng.gen_range(0..100);
+        match rng.gen_range(0..10) {
+            0..=5 => {
+                let std_count = std_multiset.entry(value).or_default();
+                *std_count += 1;
+                assert_eq!(multiset.insert(value), *std_count);
+            }
+            6..=8 => {
+                let std_removed = match std_multiset.get_mut(&value) {
+                    None => false,
+                    Some(count) => {
+                        *count -= 1;
+                        if *count == 0 {
+                            std_multiset.remove(&value);
+                        }
+                        true
+                    }
+                };
+                assert_eq!(multiset.remove_one(&value), std_removed);
+            }
+            _ => assert_eq!(
+                multiset.remove_all(&value),
+                std_multiset.remove(&value).unwrap_or(0)
+            ),
+        }
+        if i % 100 == 0 {
+            multiset.validate();
+        }
+    }
+    multiset.validate();
+
+    assert_eq!(multiset.len(), std_multiset.values().sum::<usize>());
+    assert_eq!(multiset.distinct_len(), std_multiset.len());
+    for value in 0..100 {
+        assert_eq!(
+            multiset.count(&value),
+            std_multiset.get(&value).map_or(0, |&c| c)
+        );
+    }
+    assert!(multiset
+        .iter_counts()
+        .map(|(v, &c)| (*v, c))
+        .eq(std_multiset.iter().map(|(v, &c)| (*v, c))));
+
+    let expanded = std_multiset
+        .iter()
+        .flat_map(|(v, &c)| std::iter::repeat_n(v, c))
+        .collect::<Vec<_>>();
+    assert!(multiset.iter().eq(expanded.iter().copied()));
+    assert!(multiset.iter().rev().eq(expanded.iter().rev().copied()));
+    assert_eq!(multiset.iter().len(), expanded.len());
+    // Iterate from both ends
+    let mut iter = multiset.iter();
+    let mut from_ends = Vec::new();
+    let mut back = Vec::new();
+    while let Some(v) = iter.next() {
+        from_ends.push(v);
+        if let Some(v) = iter.next_back() {
+            back.push(v);
+        }
+    }
+    from_ends.extend(back.into_iter().rev());
+    assert!(from_ends.into_iter().eq(expanded.iter().copied()));
+}
+
+#[test]
+fn multiset_insert_many_panics() {
+    let store = BTreeStore::with_limit(1);
+    let mut multiset = BTreeMultiSet::new_in(&store);
+    for value in 0..8 {
+        multiset.insert_many(value, 2);
+    }
+    assert!(catch_unwind(AssertUnwindSafe(|| multiset.insert_many(8, 2))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| multiset.insert_many(0, usize::MAX))).is_err());
+    multiset.validate();
+    assert_eq!(multiset.len(), 16);
+    assert_eq!(multiset.count(&0), 2);
+    assert_eq!(multiset.count(&8), 0);
+}
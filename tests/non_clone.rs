@@ -0,0 +1,136 @@
+use std::collections::BTreeMap as StdBTreeMap;
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+use btree_plus_store::{BTreeMap, BTreeSet, BTreeStore, Separator};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+static LIVE_HANDLES: AtomicIsize = AtomicIsize::new(0);
+
+/// A key which owns a resource and can't be cloned
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Handle(u32);
+
+// Safety: reading a bitwise copy of a `u32` is sound
+unsafe impl Separator for Handle {}
+
+impl Handle {
+    fn new(id: u32) -> Self {
+        LIVE_HANDLES.fetch_add(1, Ordering::SeqCst);
+        Handle(id)
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        LIVE_HANDLES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+pub fn non_clone_keys() {
+    let store = BTreeStore::new();
+    {
+        let mut btree = BTreeMap::new_in(&store);
+        let mut std_btree = StdBTreeMap::new();
+
+        let mut rng = SmallRng::from_seed(*SEED);
+        for i in 0..5000 {
+            let id = rng.gen_range(0..400);
+            match rng.gen_range(0..10) {
+                0..=5 => assert_eq!(btree.insert(Handle::new(id), i), std_btree.insert(id, i)),
+                6..=7 => assert_eq!(
+                    btree
+                        .remove_key_value(&Handle::new(id))
+                        .map(|(k, v)| (k.0, v)),
+                    std_btree.remove(&id).map(|v| (id, v))
+                ),
+                8 => assert_eq!(
+                    btree.pop_first().map(|(k, v)| (k.0, v)),
+                    std_btree.pop_first()
+                ),
+                _ => {
+                    *btree.get_or_insert(Handle::new(id), i) += 1;
+                    *std_btree.entry(id).or_insert(i) += 1;
+                }
+            }
+            if i % 100 == 0 {
+                btree.validate();
+            }
+        }
+        btree.validate();
+        assert!(btree
+            .iter()
+            .map(|(k, v)| (k.0, *v))
+            .eq(std_btree.iter().map(|(k, v)| (*k, *v))));
+        assert_eq!(LIVE_HANDLES.load(Ordering::SeqCst), btree.len() as isize);
+
+        // Iterate by value from both ends
+        let mut into_iter = btree.into_iter();
+        let mut count = 0;
+        while into_iter.next_back().is_some() {
+            count += 1;
+            if into_iter.next().is_some() {
+                count += 1;
+            }
+        }
+        assert_eq!(count, std_btree.len());
+        assert_eq!(LIVE_HANDLES.load(Ordering::SeqCst), 0);
+
+        let set_store = BTreeStore::new();
+        let mut set = BTreeSet::new_in(&set_store);
+        set.extend((0..100).map(Handle::new));
+        set.remove(&Handle::new(50));
+        assert_eq!(set.pop_last().map(|k| k.0), Some(99));
+        set.validate();
+        assert_eq!(LIVE_HANDLES.load(Ordering::SeqCst), 98);
+    }
+    assert_eq!(LIVE_HANDLES.load(Ordering::SeqCst), 0);
+}
+
+static LIVE_CLONES: AtomicIsize = AtomicIsize::new(0);
+
+/// A key whose separators are clones
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Counted(u32);
+
+impl Counted {
+    fn new(id: u32) -> Self {
+        LIVE_CLONES.fetch_add(1, Ordering::SeqCst);
+        Counted(id)
+    }
+}
+
+impl Clone for Counted {
+    fn clone(&self) -> Self {
+        Counted::new(self.0)
+    }
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        LIVE_CLONES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+pub fn clone_key_separators_are_dropped() {
+    let store = BTreeStore::new();
+    {
+        let mut btree = BTreeMap::new_in(&store);
+        let mut rng = SmallRng::from_seed(*SEED);
+        for i in 0..2000 {
+            let id = rng.gen_range(0..300);
+            if rng.gen_bool(0.6) {
+                btree.insert(Counted::new(id), i);
+            } else {
+                btree.remove(&Counted::new(id));
+            }
+        }
+        btree.validate();
+        // Separators are live clones
+        assert!(LIVE_CLONES.load(Ordering::SeqCst) > btree.len() as isize);
+    }
+    assert_eq!(LIVE_CLONES.load(Ordering::SeqCst), 0);
+}
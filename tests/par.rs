@@ -0,0 +1,135 @@
+#![cfg(feature = "rayon")]
+
+use std::collections::BTreeMap as StdBTreeMap;
+use std::ops::Bound;
+
+use btree_plus_store::{BTreeMap, BTreeSet, BTreeStore};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+#[test]
+pub fn par_iter() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+    let mut std_btree = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for len in [0, 1, 7, 8, 9, 64, 65, 1000, 20000] {
+        btree.clear();
+        std_btree.clear();
+        while btree.len() < len {
+            let key = rng.gen_range(0..100000u32);
+            btree.insert(key, key as u64 * 2);
+            std_btree.insert(key, key as u64 * 2);
+        }
+
+        let collected = btree.par_iter().collect::<Vec<_>>();
+        assert!(collected.into_iter().eq(std_btree.iter()), "len {}", len);
+        let sum = btree.par_iter().map(|(_, v)| *v).sum::<u64>();
+        assert_eq!(sum, std_btree.values().sum::<u64>());
+        assert_eq!((&btree).into_par_iter().count(), len);
+
+        for _ in 0..50 {
+            let start = rng.gen_range(0..110000u32);
+            let end = rng.gen_range(0..110000u32);
+            let bounds = [
+                (Bound::Included(start), Bound::Included(end)),
+                (Bound::Included(start), Bound::Excluded(end)),
+                (Bound::Excluded(start), Bound::Excluded(end)),
+                (Bound::Unbounded, Bound::Excluded(end)),
+                (Bound::Excluded(start), Bound::Unbounded),
+            ];
+            for bounds in bounds {
+                let inverted = !matches!(bounds, (Bound::Unbounded, _) | (_, Bound::Unbounded))
+                    && (start > end
+                        || (start == end
+                            && matches!(bounds, (Bound::Excluded(_), Bound::Excluded(_)))));
+                if inverted {
+                    // std panics on inverted ranges
+                    assert_eq!(btree.par_range(bounds).count(), 0);
+                    continue;
+                }
+                let collected = btree.par_range(bounds).collect::<Vec<_>>();
+                assert!(
+                    collected.into_iter().eq(std_btree.range(bounds)),
+                    "len {}, range {:?}",
+                    len,
+                    bounds
+                );
+            }
+        }
+    }
+}
+
+#[test]
+pub fn par_iter_mut() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+    btree.extend((0..10000).map(|i| (i, i)));
+
+    btree.par_iter_mut().for_each(|(k, v)| *v += k);
+    assert!(btree.iter().all(|(k, v)| *v == 2 * k));
+
+    btree.par_range_mut(2000..3000).for_each(|(_, v)| *v = 0);
+    (&mut btree).into_par_iter().for_each(|(_, v)| *v += 1);
+    assert!(btree.iter().all(|(k, v)| *v
+        == if (2000..3000).contains(k) {
+            1
+        } else {
+            2 * k + 1
+        }));
+    btree.validate();
+}
+
+#[test]
+pub fn set_par_iter() {
+    let store = BTreeStore::new();
+    let set = BTreeSet::from_sorted_iter_in(&store, (0..5000).map(|i| i * 3));
+    assert!(set
+        .par_iter()
+        .copied()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .eq((0..5000).map(|i| i * 3)));
+    assert!(set
+        .par_range(100..=200)
+        .copied()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .eq((34..=66).map(|i| i * 3)));
+    assert_eq!((&set).into_par_iter().count(), 5000);
+}
+
+#[test]
+pub fn par_from_sorted_vec() {
+    let store = BTreeStore::new();
+    let mut rng = SmallRng::from_seed(*SEED);
+    for len in [0, 1, 7, 8, 9, 16, 17, 72, 73, 1000, 50000] {
+        let mut keys = (0..len).map(|_| rng.gen::<u64>()).collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+        let entries = keys.iter().map(|k| (*k, k.to_string())).collect::<Vec<_>>();
+
+        let btree = BTreeMap::par_from_sorted_vec_in(&store, entries.clone());
+        btree.validate();
+        assert_eq!(btree.len(), entries.len());
+        assert!(btree
+            .iter()
+            .map(|(k, v)| (*k, v.clone()))
+            .eq(entries.iter().cloned()));
+        assert_eq!(
+            btree,
+            BTreeMap::from_sorted_iter_in(&store, entries.iter().cloned())
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "keys aren't strictly ascending")]
+pub fn par_from_unsorted_vec() {
+    let store = BTreeStore::new();
+    let entries = (0..1000).map(|i| (i % 999, i.to_string())).collect();
+    BTreeMap::par_from_sorted_vec_in(&store, entries);
+}
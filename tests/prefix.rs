@@ -0,0 +1,146 @@
+use std::collections::BTreeMap as StdBTreeMap;
+use std::ops::{Bound, RangeBounds};
+
+use btree_plus_store::compare::ShortSeparators;
+use btree_plus_store::{BTreeMap, BTreeSet, BTreeStore};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+/// A key type with no min or max, so sentinel keys can't bound its prefixes
+type Key = (String, i64, u32);
+
+fn random_key(rng: &mut SmallRng) -> Key {
+    (
+        format!("tenant-{}", rng.gen_range(0..10)),
+        rng.gen_range(-50..50),
+        rng.gen_range(0..5),
+    )
+}
+
+#[test]
+pub fn prefix_scans() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+    let mut std_btree = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..3000 {
+        let key = random_key(&mut rng);
+        if rng.gen_bool(0.7) {
+            assert_eq!(btree.insert(key.clone(), i), std_btree.insert(key, i));
+        } else {
+            assert_eq!(btree.remove(&key), std_btree.remove(&key));
+        }
+    }
+    btree.validate();
+
+    for _ in 0..300 {
+        let (tenant, timestamp, _) = random_key(&mut rng);
+        assert!(btree
+            .prefix(&tenant)
+            .eq(std_btree.iter().filter(|(k, _)| k.0 == tenant)));
+        assert!(btree
+            .prefix(&(tenant.clone(), timestamp))
+            .rev()
+            .eq(std_btree
+                .iter()
+                .filter(|(k, _)| k.0 == tenant && k.1 == timestamp)
+                .rev()));
+
+        let start = rng.gen_range(-60..60);
+        let end = rng.gen_range(-60..60);
+        let bounds = [
+            (Bound::Included(start), Bound::Included(end)),
+            (Bound::Included(start), Bound::Excluded(end)),
+            (Bound::Excluded(start), Bound::Included(end)),
+            (Bound::Excluded(start), Bound::Excluded(end)),
+            (Bound::Unbounded, Bound::Excluded(end)),
+            (Bound::Excluded(start), Bound::Unbounded),
+        ];
+        for bounds in bounds {
+            assert!(
+                btree.range_prefix(&tenant, bounds).eq(std_btree
+                    .iter()
+                    .filter(|(k, _)| k.0 == tenant && bounds.contains(&k.1))),
+                "range_prefix({}, {:?})",
+                tenant,
+                bounds
+            );
+        }
+    }
+
+    assert_eq!(btree.prefix(&String::from("nonexistent")).count(), 0);
+}
+
+#[test]
+pub fn set_prefix_scans() {
+    let store = BTreeStore::new();
+    let set = BTreeSet::from_sorted_iter_in(
+        &store,
+        (0..10u8).flat_map(|a| (0..10u8).map(move |b| (a, b))),
+    );
+    assert!(set.prefix(&3).copied().eq((0..10).map(|b| (3, b))));
+    assert!(set
+        .range_prefix(&7, 2..=4)
+        .copied()
+        .eq([(7, 2), (7, 3), (7, 4)]));
+    let (start, end) = (4, 2);
+    assert_eq!(set.range_prefix(&7, start..end).count(), 0);
+    assert_eq!(set.range_prefix(&10, ..).count(), 0);
+}
+
+fn random_path(rng: &mut SmallRng) -> String {
+    let len = rng.gen_range(0..6);
+    (0..len)
+        .map(|_| ['a', 'b', '/'][rng.gen_range(0..3)])
+        .collect()
+}
+
+#[test]
+pub fn byte_prefixes() {
+    let store = BTreeStore::new();
+    let short_store = BTreeStore::new();
+    let mut btree = BTreeMap::new_in(&store);
+    let mut short_btree = BTreeMap::with_comparator_in(&short_store, ShortSeparators);
+    let mut std_btree = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..500 {
+        let key = random_path(&mut rng);
+        btree.insert(key.clone(), i);
+        short_btree.insert(key.clone(), i);
+        std_btree.insert(key, i);
+    }
+    btree.validate();
+    short_btree.validate();
+
+    for _ in 0..500 {
+        let query = random_path(&mut rng);
+        let expected = std_btree.iter().filter(|(k, _)| k.starts_with(&query));
+        assert!(btree.starts_with(&query).eq(expected.clone()));
+        assert!(short_btree.starts_with(&query).eq(expected));
+
+        let expected = std_btree
+            .iter()
+            .filter(|(k, _)| query.starts_with(k.as_str()))
+            .max_by_key(|(k, _)| k.len());
+        assert_eq!(btree.longest_prefix_match(&query), expected);
+        assert_eq!(short_btree.longest_prefix_match(&query), expected);
+    }
+}
+
+#[test]
+pub fn set_byte_prefixes() {
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+    set.extend([&b"10.0"[..], b"10.0.1", b"10.1", b"192.168"]);
+    assert!(set
+        .starts_with(b"10.")
+        .copied()
+        .eq([&b"10.0"[..], b"10.0.1", b"10.1"]));
+    assert_eq!(set.longest_prefix_match(b"10.0.1.7"), Some(&&b"10.0.1"[..]));
+    assert_eq!(set.longest_prefix_match(b"10.0.2.7"), Some(&&b"10.0"[..]));
+    assert_eq!(set.longest_prefix_match(b"127.0.0.1"), None);
+    assert_eq!(set.starts_with("").count(), 4);
+}
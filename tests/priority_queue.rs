@@ -0,0 +1,90 @@
+use std::collections::BTreeMap as StdBTreeMap;
+
+use btree_plus_store::{BTreePriorityQueue, BTreeStore};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+/// The queue's expected order: by priority, then item
+fn model_order(model: &StdBTreeMap<u32, u8>) -> Vec<(u32, u8)> {
+    let mut order = model.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+    order.sort_by_key(|(item, priority)| (*priority, *item));
+    order
+}
+
+#[test]
+pub fn priority_queue() {
+    let queue_store = BTreeStore::new();
+    let index_store = BTreeStore::new();
+    let mut queue = BTreePriorityQueue::new_in(&queue_store, &index_store);
+    let mut model = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..5000 {
+        let item = rng.gen_range(0..300u32);
+        let priority = rng.gen_range(0..50u8);
+        match rng.gen_range(0..8) {
+            0..=2 => assert_eq!(queue.push(item, priority), model.insert(item, priority)),
+            3 => {
+                let expected = model
+                    .get_mut(&item)
+                    .map(|old| std::mem::replace(old, priority));
+                assert_eq!(queue.change_priority(&item, priority), expected);
+            }
+            4 => assert_eq!(queue.remove(&item), model.remove(&item)),
+            5 => {
+                let expected = model_order(&model).first().copied();
+                if let Some((item, _)) = expected {
+                    model.remove(&item);
+                }
+                assert_eq!(queue.pop_min(), expected);
+            }
+            6 => {
+                let expected = model_order(&model).last().copied();
+                if let Some((item, _)) = expected {
+                    model.remove(&item);
+                }
+                assert_eq!(queue.pop_max(), expected);
+            }
+            _ => {
+                let order = model_order(&model);
+                assert_eq!(
+                    queue.peek_min().map(|(k, v)| (*k, *v)),
+                    order.first().copied()
+                );
+                assert_eq!(
+                    queue.peek_max().map(|(k, v)| (*k, *v)),
+                    order.last().copied()
+                );
+            }
+        }
+        if i % 100 == 0 {
+            queue.validate();
+        }
+        assert_eq!(queue.len(), model.len());
+    }
+    queue.validate();
+
+    assert!(queue.iter().map(|(k, v)| (*k, *v)).eq(model_order(&model)));
+    for item in 0..300 {
+        assert_eq!(queue.priority(&item), model.get(&item));
+        assert_eq!(queue.contains(&item), model.contains_key(&item));
+    }
+}
+
+#[test]
+pub fn decrease_key() {
+    let queue_store = BTreeStore::new();
+    let index_store = BTreeStore::new();
+    let mut queue = BTreePriorityQueue::new_in(&queue_store, &index_store);
+    queue.extend([("a", 5), ("b", 3), ("c", 8)]);
+    assert_eq!(queue.peek_min(), Some((&"b", &3)));
+
+    assert_eq!(queue.change_priority("c", 1), Some(8));
+    assert_eq!(queue.change_priority("d", 0), None);
+    assert_eq!(queue.pop_min(), Some(("c", 1)));
+    assert_eq!(queue.pop_max(), Some(("a", 5)));
+    assert_eq!(queue.remove("b"), Some(3));
+    assert!(queue.is_empty());
+    queue.validate();
+}
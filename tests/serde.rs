@@ -0,0 +1,107 @@
+#![cfg(feature = "serde")]
+
+use btree_plus_store::map::BTreeMapSeed;
+use btree_plus_store::{BTreeMap, BTreeSet, BTreeStore};
+use serde::de::DeserializeSeed;
+
+#[test]
+fn map_round_trip() {
+    let store = BTreeStore::new();
+    let map = BTreeMap::from_sorted_iter_in(&store, (0..100).map(|i| (i, format!("v{}", i))));
+
+    let json = serde_json::to_string(&map).unwrap();
+    assert!(json.starts_with(r#"{"0":"v0","1":"v1","#));
+
+    let map2 = BTreeMap::<u32, String>::deserialize_in(
+        &store,
+        &mut serde_json::Deserializer::from_str(&json),
+    )
+    .unwrap();
+    map2.validate();
+    assert_eq!(map, map2);
+}
+
+#[test]
+fn map_unsorted_input() {
+    let store = BTreeStore::new();
+    let json = r#"{"3": 30, "1": 10, "2": 20, "1": 11}"#;
+
+    let map = BTreeMapSeed::new(&store)
+        .deserialize(&mut serde_json::Deserializer::from_str(json))
+        .unwrap();
+    map.validate();
+    assert_eq!(
+        map.iter()
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<(u32, u32)>>(),
+        vec![(1, 11), (2, 20), (3, 30)]
+    );
+}
+
+#[test]
+fn set_round_trip() {
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+    for i in [5, 3, 9, 1, 7] {
+        set.insert(i);
+    }
+
+    let json = serde_json::to_string(&set).unwrap();
+    assert_eq!(json, "[1,3,5,7,9]");
+
+    let set2 =
+        BTreeSet::<i32>::deserialize_in(&store, &mut serde_json::Deserializer::from_str(&json))
+            .unwrap();
+    set2.validate();
+    assert_eq!(set, set2);
+
+    let set3 = BTreeSet::<i32>::deserialize_in(
+        &store,
+        &mut serde_json::Deserializer::from_str("[2, 1, 2]"),
+    )
+    .unwrap();
+    assert_eq!(set3.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn invalid_input() {
+    let store = BTreeStore::<i32, i32>::new();
+    assert!(
+        BTreeMap::deserialize_in(&store, &mut serde_json::Deserializer::from_str("[1, 2]"))
+            .is_err()
+    );
+}
+
+#[cfg(feature = "copyable")]
+#[test]
+fn copyable_round_trip() {
+    use btree_plus_store::copyable;
+
+    let store = BTreeStore::new();
+    let map = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..50 {
+            map.insert(i, i * 2);
+        }
+    });
+    let json = serde_json::to_string(&map).unwrap();
+    let map2 = copyable::BTreeMap::<i32, i32>::deserialize_in(
+        &store,
+        &mut serde_json::Deserializer::from_str(&json),
+    )
+    .unwrap();
+    assert_eq!(map, map2);
+
+    let set_store = BTreeStore::new();
+    let set = copyable::BTreeSet::build(&set_store, |set| {
+        set.insert("b".to_string());
+        set.insert("a".to_string());
+    });
+    let json = serde_json::to_string(&set).unwrap();
+    assert_eq!(json, r#"["a","b"]"#);
+    let set2 = copyable::BTreeSet::<String>::deserialize_in(
+        &set_store,
+        &mut serde_json::Deserializer::from_str(&json),
+    )
+    .unwrap();
+    assert_eq!(set, set2);
+}
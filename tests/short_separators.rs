@@ -0,0 +1,81 @@
+use std::collections::BTreeMap as StdBTreeMap;
+
+use btree_plus_store::{BTreeMap, BTreeStore, SeparatorKey, ShortSeparators};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+#[test]
+pub fn shortest_separators() {
+    assert_eq!(
+        String::shortest_separator(&"/usr/lib/abc".into(), &"/usr/share/abc".into()),
+        "/usr/s"
+    );
+    assert_eq!(
+        String::shortest_separator(&"ab".into(), &"abc".into()),
+        "abc"
+    );
+    assert_eq!(String::shortest_separator(&"".into(), &"xyz".into()), "x");
+    // Rounds up to a char boundary
+    assert_eq!(
+        String::shortest_separator(&"aé".into(), &"aü!".into()),
+        "aü"
+    );
+    assert_eq!(
+        Vec::shortest_separator(&vec![1, 2, 3, 4], &vec![1, 2, 5, 0]),
+        vec![1, 2, 5]
+    );
+}
+
+#[test]
+pub fn path_keys() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::with_comparator_in(&store, ShortSeparators);
+    let mut std_btree = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..3000 {
+        let key = format!(
+            "/home/user/projects/crate/src/{}/{}.rs",
+            rng.gen_range(0..20),
+            rng.gen_range(0..50)
+        );
+        match rng.gen_range(0..10) {
+            0..=5 => assert_eq!(btree.insert(key.clone(), i), std_btree.insert(key, i)),
+            6..=7 => assert_eq!(btree.remove(&key), std_btree.remove(&key)),
+            8 => assert_eq!(btree.pop_first(), std_btree.pop_first()),
+            _ => assert_eq!(btree.pop_last(), std_btree.pop_last()),
+        }
+        if i % 100 == 0 {
+            btree.validate();
+        }
+    }
+    btree.validate();
+
+    assert!(btree.iter().eq(std_btree.iter()));
+    let start = String::from("/home/user/projects/crate/src/12");
+    let end = String::from("/home/user/projects/crate/src/5/");
+    assert!(btree
+        .range(start.clone()..end.clone())
+        .eq(std_btree.range(start..end)));
+}
+
+#[test]
+pub fn byte_keys() {
+    let store = BTreeStore::new();
+    let mut btree = BTreeMap::with_comparator_in(&store, ShortSeparators);
+    let mut std_btree = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..2000 {
+        let mut key = vec![7; 32];
+        key.extend((0..rng.gen_range(0..3)).map(|_| rng.gen_range(0..8u8)));
+        if rng.gen_bool(0.7) {
+            assert_eq!(btree.insert(key.clone(), i), std_btree.insert(key, i));
+        } else {
+            assert_eq!(btree.remove(&key), std_btree.remove(&key));
+        }
+    }
+    btree.validate();
+    assert!(btree.iter().eq(std_btree.iter()));
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "copyable")]
+
+use btree_plus_store::copyable::{self, BTreeStoreExt};
+use btree_plus_store::{BTreeMap, BTreeStore};
+
+#[test]
+fn snapshot_restore() {
+    let store = BTreeStore::new();
+    let big = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..1000u32 {
+            map.insert(i, format!("value {}", i));
+        }
+    });
+    let small = copyable::BTreeMap::build(&store, |map| {
+        map.insert(5, String::from("five"));
+    });
+    let empty = copyable::BTreeMap::build(&store, |_| {});
+    let mut mutable = BTreeMap::new_in(&store);
+    for i in (0..100).rev() {
+        mutable.insert(i * 2, i.to_string());
+    }
+
+    let mut bytes = Vec::new();
+    store
+        .snapshot([&big, &small, &big, &empty], &mut bytes)
+        .unwrap();
+    let mut bytes2 = Vec::new();
+    store.snapshot([&mutable], &mut bytes2).unwrap();
+
+    // The shared tree is only written once
+    let mut unshared_bytes = Vec::new();
+    store
+        .snapshot([&big, &small, &empty], &mut unshared_bytes)
+        .unwrap();
+    assert!(bytes.len() < unshared_bytes.len() + 32);
+
+    let (store2, handles) = BTreeStore::<u32, String>::restore(bytes.as_slice()).unwrap();
+    assert_eq!(handles.len(), 4);
+    assert_eq!(handles[0].len(), 1000);
+    let maps = handles
+        .iter()
+        .map(|handle| handle.to_map(&store2))
+        .collect::<Vec<_>>();
+    for map in &maps {
+        map.validate();
+    }
+    assert_eq!(maps[0], big);
+    assert_eq!(maps[1], small);
+    assert_eq!(maps[2], big);
+    assert!(maps[3].is_empty());
+    assert_eq!(maps[0].range(10..13).count(), 3);
+    assert_eq!(maps[0].iter().next_back(), big.last_key_value());
+
+    // The restored store is usable for new trees and garbage collection
+    let mut new_map = BTreeMap::new_in(&store2);
+    new_map.insert(1, String::from("one"));
+    unsafe { store2.tracing_gc([maps[0], maps[1], copyable::BTreeMap::from(new_map)]) };
+    assert_eq!(maps[0], big);
+
+    let (store3, handles) = BTreeStore::<u32, String>::restore(bytes2.as_slice()).unwrap();
+    let map = handles[0].to_map(&store3);
+    map.validate();
+    assert!(map.iter().eq(mutable.iter()));
+}
+
+#[test]
+fn snapshot_restore_set() {
+    let store = BTreeStore::new();
+    let set = copyable::BTreeSet::build(&store, |set| {
+        for i in 0..300i64 {
+            set.insert((i, i % 7 == 0));
+        }
+    });
+
+    let mut bytes = Vec::new();
+    store.snapshot([set], &mut bytes).unwrap();
+    let (store2, handles) = BTreeStore::<(i64, bool), ()>::restore(bytes.as_slice()).unwrap();
+    let set2 = handles[0].to_set(&store2);
+    set2.validate();
+    assert_eq!(set, set2);
+}
+
+#[test]
+fn restore_invalid() {
+    let store = BTreeStore::new();
+    let map = copyable::BTreeMap::build(&store, |map| {
+        for i in 0..100u8 {
+            map.insert(i, i);
+        }
+    });
+    let mut bytes = Vec::new();
+    store.snapshot([map], &mut bytes).unwrap();
+
+    for len in 0..bytes.len() {
+        assert!(BTreeStore::<u8, u8>::restore(&bytes[..len]).is_err());
+    }
+    let mut corrupted = bytes.clone();
+    corrupted[0] = b'X';
+    assert!(BTreeStore::<u8, u8>::restore(corrupted.as_slice()).is_err());
+    // Bad root height
+    let mut corrupted = bytes.clone();
+    let height_offset = corrupted.len() - 8;
+    corrupted[height_offset] += 1;
+    assert!(BTreeStore::<u8, u8>::restore(corrupted.as_slice()).is_err());
+}
+
+#[test]
+#[should_panic(expected = "tree handle is not from this store")]
+fn handle_wrong_store() {
+    let store = BTreeStore::new();
+    let map = copyable::BTreeMap::build(&store, |map| {
+        map.insert(1u8, 1u8);
+    });
+    let mut bytes = Vec::new();
+    store.snapshot([map], &mut bytes).unwrap();
+    let (_store2, handles) = BTreeStore::<u8, u8>::restore(bytes.as_slice()).unwrap();
+    handles[0].to_map(&store);
+}
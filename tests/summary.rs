@@ -0,0 +1,123 @@
+use std::collections::BTreeMap as StdBTreeMap;
+use std::ops::Bound;
+
+use btree_plus_store::{BTreeMap, BTreeStore, Summary};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+/// Count, sum, and first and last key, so the order of combining matters
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stats {
+    count: usize,
+    sum: u64,
+    first: Option<u32>,
+    last: Option<u32>,
+}
+
+impl Summary<u32, u64> for Stats {
+    fn empty() -> Self {
+        Stats {
+            count: 0,
+            sum: 0,
+            first: None,
+            last: None,
+        }
+    }
+
+    fn summarize(key: &u32, val: &u64) -> Self {
+        Stats {
+            count: 1,
+            sum: *val,
+            first: Some(*key),
+            last: Some(*key),
+        }
+    }
+
+    fn combine(a: Self, b: Self) -> Self {
+        Stats {
+            count: a.count + b.count,
+            sum: a.sum + b.sum,
+            first: a.first.or(b.first),
+            last: b.last.or(a.last),
+        }
+    }
+}
+
+fn brute_force<'a>(entries: impl Iterator<Item = (&'a u32, &'a u64)>) -> Stats {
+    entries.fold(Stats::empty(), |acc, (k, v)| {
+        Stats::combine(acc, Stats::summarize(k, v))
+    })
+}
+
+#[test]
+pub fn aggregate() {
+    let store = BTreeStore::<u32, u64, Stats>::summarized();
+    let mut btree = BTreeMap::new_in(&store);
+    let mut std_btree = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..5000 {
+        let key = rng.gen_range(0..1000);
+        let val = rng.gen_range(0..100);
+        match rng.gen_range(0..10) {
+            0..=4 => assert_eq!(btree.insert(key, val), std_btree.insert(key, val)),
+            5 => {
+                btree.update(key, |old| old.map(|old| old + val));
+                if let Some(old) = std_btree.get_mut(&key) {
+                    *old += val;
+                }
+            }
+            6..=7 => assert_eq!(btree.remove(&key), std_btree.remove(&key)),
+            8 => assert_eq!(btree.pop_first(), std_btree.pop_first()),
+            _ => assert_eq!(btree.pop_last(), std_btree.pop_last()),
+        }
+        if i % 100 == 0 {
+            btree.validate();
+        }
+
+        let start = rng.gen_range(0..1000);
+        let end = rng.gen_range(start..1000);
+        assert_eq!(
+            btree.aggregate(start..end),
+            brute_force(std_btree.range(start..end))
+        );
+    }
+    btree.validate();
+
+    assert_eq!(btree.summary(), brute_force(std_btree.iter()));
+    assert_eq!(btree.aggregate(..), brute_force(std_btree.iter()));
+    for _ in 0..200 {
+        let start = rng.gen_range(0..1000);
+        let end = rng.gen_range(start..1000);
+        let bounds = (Bound::Excluded(start), Bound::Included(end));
+        assert_eq!(
+            btree.aggregate(bounds),
+            brute_force(std_btree.range(bounds))
+        );
+    }
+}
+
+#[test]
+pub fn aggregate_from_sorted() {
+    let store = BTreeStore::<u32, u64, Stats>::summarized();
+    let btree = BTreeMap::from_sorted_iter_in(&store, (0..1000).map(|i| (i, u64::from(i) % 7)));
+    btree.validate();
+    let std_btree = btree
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .collect::<StdBTreeMap<_, _>>();
+
+    assert_eq!(btree.summary(), brute_force(std_btree.iter()));
+    assert_eq!(btree.aggregate(250..750).count, 500);
+    assert_eq!(btree.aggregate(500..500), Stats::empty());
+    assert_eq!(
+        btree.aggregate(123..=877),
+        brute_force(std_btree.range(123..=877))
+    );
+    let (start, end) = (600, 400);
+    assert_eq!(
+        btree.aggregate((Bound::Included(start), Bound::Excluded(end))),
+        Stats::empty()
+    );
+}
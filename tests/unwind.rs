@@ -0,0 +1,454 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap as StdBTreeMap, BTreeSet as StdBTreeSet};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use btree_plus_store::{BTreeMap, BTreeSet, BTreeStore};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+thread_local! {
+    /// Number of [Tracked] values which haven't been dropped
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    /// Whether [Tracked] separators (clones) *panic* when dropped
+    static PANIC_ON_SEPARATOR_DROP: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Counts live instances, and can *panic* when dropped. Ordered by `value`.
+#[derive(Debug)]
+struct Tracked {
+    value: u32,
+    panic_on_drop: bool,
+    is_clone: bool,
+}
+
+impl Tracked {
+    fn with_flags(value: u32, panic_on_drop: bool, is_clone: bool) -> Self {
+        LIVE.with(|live| live.set(live.get() + 1));
+        Self {
+            value,
+            panic_on_drop,
+            is_clone,
+        }
+    }
+
+    fn new(value: u32) -> Self {
+        Self::with_flags(value, false, false)
+    }
+
+    fn panicking(value: u32) -> Self {
+        Self::with_flags(value, true, false)
+    }
+}
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        Self::with_flags(self.value, false, true)
+    }
+}
+
+impl PartialEq for Tracked {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for Tracked {}
+
+impl PartialOrd for Tracked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tracked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        LIVE.with(|live| live.set(live.get() - 1));
+        if self.panic_on_drop || (self.is_clone && PANIC_ON_SEPARATOR_DROP.with(Cell::get)) {
+            panic!("Tracked({}) dropped", self.value);
+        }
+    }
+}
+
+fn live() -> isize {
+    LIVE.with(Cell::get)
+}
+
+#[test]
+pub fn drop_while_unwinding() {
+    let store = BTreeStore::new();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut map = BTreeMap::new_in(&store);
+        for i in 0..1000 {
+            map.insert(Tracked::new(i), Tracked::new(i));
+        }
+        panic!("request failed");
+    }));
+    assert!(result.is_err());
+    assert_eq!(live(), 0, "the map leaked its entries");
+}
+
+#[test]
+pub fn panicking_destructor() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        let val = if i == 500 {
+            Tracked::panicking(i)
+        } else {
+            Tracked::new(i)
+        };
+        map.insert(Tracked::new(i), val);
+    }
+    assert!(catch_unwind(AssertUnwindSafe(|| drop(map))).is_err());
+    assert_eq!(live(), 0, "the map didn't drop the rest of its entries");
+}
+
+#[test]
+pub fn clear_with_panicking_destructor() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        map.insert(Tracked::new(i), Tracked::new(i));
+    }
+    map.insert(Tracked::new(1000), Tracked::panicking(1000));
+    assert!(catch_unwind(AssertUnwindSafe(|| map.clear())).is_err());
+    assert_eq!(live(), 0);
+    assert!(map.is_empty());
+    map.validate();
+
+    map.insert(Tracked::new(0), Tracked::new(0));
+    assert_eq!(map.len(), 1);
+    map.validate();
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+pub fn store_clear_drops_leaked_maps() {
+    let mut store = BTreeStore::new();
+    for len in [0, 1, 8, 9, 1000] {
+        let mut map = BTreeMap::new_in(&store);
+        for i in 0..len {
+            let val = if i == 5 {
+                Tracked::panicking(i)
+            } else {
+                Tracked::new(i)
+            };
+            map.insert(Tracked::new(i), val);
+        }
+        std::mem::forget(map);
+    }
+    assert!(catch_unwind(AssertUnwindSafe(|| store.clear())).is_err());
+    assert_eq!(live(), 0, "clear didn't drop every leaked entry");
+    assert_eq!(store.num_nodes(), 0);
+
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        map.insert(Tracked::new(i), Tracked::new(i));
+    }
+    map.validate();
+    std::mem::forget(map);
+    store.clear();
+    assert_eq!(live(), 0);
+}
+
+#[cfg(feature = "copyable")]
+#[test]
+pub fn store_clear_drops_copyable_maps() {
+    let mut store = BTreeStore::new();
+    let mut copies = Vec::new();
+    for len in [1, 9, 100] {
+        let map = btree_plus_store::copyable::BTreeMap::build(&store, |map| {
+            for i in 0..len {
+                map.insert(Tracked::new(i), Tracked::new(i));
+            }
+        });
+        // Copies share every node, so their entries must only be dropped once
+        copies.extend([map; 3]);
+    }
+    let mut leaked = BTreeMap::new_in(&store);
+    for i in 0..100 {
+        leaked.insert(Tracked::new(i), Tracked::new(i));
+    }
+    std::mem::forget(leaked);
+    drop(copies);
+
+    store.clear();
+    assert_eq!(live(), 0, "clear didn't drop every copied entry once");
+    assert_eq!(store.num_nodes(), 0);
+}
+
+#[test]
+pub fn panicking_separator_destructor() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    for i in 0..1000 {
+        map.insert(Tracked::new(i), i);
+    }
+
+    // Removals which replace or remove a leaf's separator panic, but after updating the map
+    PANIC_ON_SEPARATOR_DROP.with(|p| p.set(true));
+    let mut num_panics = 0;
+    for i in (0..1000).step_by(3) {
+        let removed = catch_unwind(AssertUnwindSafe(|| map.remove(&Tracked::new(i))));
+        num_panics += usize::from(removed.is_err());
+        map.validate();
+        assert!(!map.contains_key(&Tracked::new(i)));
+    }
+    PANIC_ON_SEPARATOR_DROP.with(|p| p.set(false));
+    assert!(num_panics > 0);
+    assert!(map
+        .keys()
+        .map(|k| k.value)
+        .eq((0..1000).filter(|i| i % 3 != 0)));
+
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+thread_local! {
+    /// Number of [Flaky] clones or comparisons until one *panic*s, if any
+    static CLONES_UNTIL_PANIC: Cell<Option<u32>> = const { Cell::new(None) };
+    static CMPS_UNTIL_PANIC: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Key whose `Clone` and `Ord` *panic* after a countdown
+#[derive(Debug)]
+struct Flaky(u32);
+
+/// Counts down, and *panic*s if it reaches 0
+fn count_down(countdown: &'static std::thread::LocalKey<Cell<Option<u32>>>, what: &str) {
+    countdown.with(|countdown| match countdown.get() {
+        None => {}
+        Some(0) => {
+            countdown.set(None);
+            panic!("{} panicked", what)
+        }
+        Some(n) => countdown.set(Some(n - 1)),
+    })
+}
+
+impl Clone for Flaky {
+    fn clone(&self) -> Self {
+        count_down(&CLONES_UNTIL_PANIC, "clone");
+        Self(self.0)
+    }
+}
+
+impl PartialEq for Flaky {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Flaky {}
+
+impl PartialOrd for Flaky {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Flaky {
+    fn cmp(&self, other: &Self) -> Ordering {
+        count_down(&CMPS_UNTIL_PANIC, "cmp");
+        self.0.cmp(&other.0)
+    }
+}
+
+fn set_countdowns(rng: &mut SmallRng) {
+    let mut countdown = || rng.gen_bool(0.5).then(|| rng.gen_range(0..20));
+    CLONES_UNTIL_PANIC.with(|c| c.set(countdown()));
+    CMPS_UNTIL_PANIC.with(|c| c.set(countdown()));
+}
+
+fn clear_countdowns() {
+    CLONES_UNTIL_PANIC.with(|c| c.set(None));
+    CMPS_UNTIL_PANIC.with(|c| c.set(None));
+}
+
+#[test]
+pub fn panicking_clone_and_ord() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let mut model = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    let mut num_panics = 0;
+    for i in 0..5000 {
+        let key = rng.gen_range(0..500);
+        set_countdowns(&mut rng);
+        let op = rng.gen_range(0..6);
+        let result = catch_unwind(AssertUnwindSafe(|| match op {
+            0 | 1 => map.insert(Flaky(key), i).map(|_| ()),
+            2 => map.remove(&Flaky(key)).map(|_| ()),
+            3 => map.pop_first().map(|_| ()),
+            4 => {
+                map.get_or_insert(Flaky(key), i);
+                None
+            }
+            _ => {
+                map.update(Flaky(key), |val| val.map_or(Some(i), |_| None));
+                None
+            }
+        }));
+        clear_countdowns();
+
+        match (result.is_ok(), op) {
+            (false, _) => num_panics += 1,
+            (true, 0 | 1) => drop(model.insert(key, i)),
+            (true, 2) => drop(model.remove(&key)),
+            (true, 3) => drop(model.pop_first()),
+            (true, 4) => drop(model.entry(key).or_insert(i)),
+            (true, _) => {
+                if model.remove(&key).is_none() {
+                    model.insert(key, i);
+                }
+            }
+        }
+        map.validate();
+        assert_eq!(map.len(), model.len());
+        assert!(
+            map.iter()
+                .map(|(k, v)| (k.0, *v))
+                .eq(model.iter().map(|(k, v)| (*k, *v))),
+            "map changed after a panic"
+        );
+    }
+    assert!(num_panics > 0);
+}
+
+#[test]
+pub fn update_panicking_clone_and_ord() {
+    let store = BTreeStore::new();
+    let mut map = BTreeMap::new_in(&store);
+    let mut model = StdBTreeMap::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    let mut num_panics = 0;
+    for i in 0..5000 {
+        let key = rng.gen_range(0..500);
+        set_countdowns(&mut rng);
+        let op = rng.gen_range(0..3);
+        let result = catch_unwind(AssertUnwindSafe(|| match op {
+            0 => drop(map.insert(Flaky(key), Tracked::new(i))),
+            // Removes the entry, or inserts it if it's missing
+            1 => map.update(Flaky(key), |val| match val {
+                None => Some(Tracked::new(i)),
+                Some(_) => None,
+            }),
+            _ => map.update(Flaky(key), |val| val.map(|_| Tracked::new(i))),
+        }));
+        clear_countdowns();
+
+        match (result.is_ok(), op) {
+            (false, _) => num_panics += 1,
+            (true, 0) => drop(model.insert(key, i)),
+            (true, 1) => {
+                if model.remove(&key).is_none() {
+                    model.insert(key, i);
+                }
+            }
+            (true, _) => {
+                if let Some(val) = model.get_mut(&key) {
+                    *val = i;
+                }
+            }
+        }
+        map.validate();
+        assert!(
+            map.iter()
+                .map(|(k, v)| (k.0, v.value))
+                .eq(model.iter().map(|(k, v)| (*k, *v))),
+            "map changed after a panic"
+        );
+        assert_eq!(live(), model.len() as isize, "a value was dropped twice or leaked");
+    }
+    assert!(num_panics > 0);
+    drop(map);
+    assert_eq!(live(), 0);
+}
+
+#[test]
+pub fn set_panicking_clone_and_ord() {
+    let store = BTreeStore::new();
+    let mut set = BTreeSet::new_in(&store);
+    let mut model = StdBTreeSet::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for _ in 0..5000 {
+        let value = rng.gen_range(0..500);
+        set_countdowns(&mut rng);
+        let op = rng.gen_range(0..4);
+        let result = catch_unwind(AssertUnwindSafe(|| match op {
+            0 | 1 => drop(set.insert(Flaky(value))),
+            2 => drop(set.remove(&Flaky(value))),
+            _ => drop(set.pop_last()),
+        }));
+        clear_countdowns();
+
+        if result.is_ok() {
+            match op {
+                0 | 1 => drop(model.insert(value)),
+                2 => drop(model.remove(&value)),
+                _ => drop(model.pop_last()),
+            }
+        }
+        set.validate();
+        assert!(set.iter().map(|v| v.0).eq(model.iter().copied()));
+    }
+}
+
+#[test]
+pub fn panicking_clone_in_bulk_load() {
+    let store = BTreeStore::new();
+    for clones in [0, 1, 10, 100] {
+        CLONES_UNTIL_PANIC.with(|c| c.set(Some(clones)));
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            BTreeMap::from_sorted_iter_in(&store, (0..5000).map(|i| (Flaky(i), Tracked::new(i))))
+        }));
+        clear_countdowns();
+        assert!(result.is_err());
+        assert_eq!(live(), 0, "bulk loading leaked entries");
+    }
+}
+
+#[test]
+pub fn get_or_insert_panicking_clone_and_ord() {
+    let store = BTreeStore::new();
+    for (clones, cmps) in (0..4)
+        .map(|n| (Some(n), None))
+        .chain((0..10).map(|n| (None, Some(n))))
+    {
+        // A full leaf, so inserting splits it and clones a separator
+        let mut map = BTreeMap::new_in(&store);
+        for i in 0..8 {
+            map.insert((Flaky(i * 2), Tracked::new(i * 2)), i * 2);
+        }
+        CLONES_UNTIL_PANIC.with(|c| c.set(clones));
+        CMPS_UNTIL_PANIC.with(|c| c.set(cmps));
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            *map.get_or_insert((Flaky(5), Tracked::new(5)), 5)
+        }));
+        clear_countdowns();
+
+        map.validate();
+        match result {
+            Ok(val) => {
+                assert_eq!(val, 5);
+                assert_eq!(map.len(), 9);
+            }
+            Err(_) => assert_eq!(map.len(), 8, "map changed after a panic"),
+        }
+        drop(map);
+        assert_eq!(live(), 0, "get_or_insert leaked a key");
+    }
+}
@@ -0,0 +1,153 @@
+use btree_plus_store::{BTreeStore, BTreeVec};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+#[test]
+pub fn insert_remove() {
+    let store = BTreeStore::summarized();
+    let mut vec = BTreeVec::new_in(&store);
+    let mut std_vec = Vec::new();
+
+    let mut rng = SmallRng::from_seed(*SEED);
+    for i in 0..10000 {
+        match rng.gen_range(0..10) {
+            0..=5 => {
+                let index = rng.gen_range(0..=std_vec.len());
+                vec.insert(index, i);
+                std_vec.insert(index, i);
+            }
+            6..=7 if !std_vec.is_empty() => {
+                let index = rng.gen_range(0..std_vec.len());
+                assert_eq!(vec.remove(index), std_vec.remove(index));
+            }
+            8 => {
+                vec.push(i);
+                std_vec.push(i);
+            }
+            _ => assert_eq!(vec.pop(), std_vec.pop()),
+        }
+        if i % 100 == 0 {
+            vec.validate();
+        }
+        assert_eq!(vec.len(), std_vec.len());
+        if !std_vec.is_empty() {
+            let index = rng.gen_range(0..std_vec.len());
+            assert_eq!(vec[index], std_vec[index]);
+        }
+    }
+    vec.validate();
+    assert!(vec.iter().eq(std_vec.iter()));
+    assert!(vec.iter().rev().eq(std_vec.iter().rev()));
+    assert_eq!(vec.get(std_vec.len()), None);
+
+    for _ in 0..200 {
+        let start = rng.gen_range(0..=std_vec.len());
+        let end = rng.gen_range(start..=std_vec.len());
+        assert!(vec.range(start..end).eq(std_vec[start..end].iter()));
+        assert_eq!(vec.range(start..end).len(), end - start);
+    }
+
+    for value in vec.iter_mut() {
+        *value *= 2;
+    }
+    for value in vec.range_mut(10..20) {
+        *value += 1;
+    }
+    for value in &mut std_vec {
+        *value *= 2;
+    }
+    for value in &mut std_vec[10..20] {
+        *value += 1;
+    }
+    assert!(vec.into_iter().eq(std_vec));
+}
+
+#[test]
+pub fn split_off_append() {
+    let store = BTreeStore::summarized();
+    let mut rng = SmallRng::from_seed(*SEED);
+    let mut vecs = vec![(BTreeVec::new_in(&store), Vec::new())];
+    let mut next = 0;
+
+    for _ in 0..500 {
+        let i = rng.gen_range(0..vecs.len());
+        match rng.gen_range(0..4) {
+            0 => {
+                let count = rng.gen_range(0..300);
+                let (vec, std_vec) = &mut vecs[i];
+                vec.extend(next..next + count);
+                std_vec.extend(next..next + count);
+                next += count;
+            }
+            1 if vecs.len() < 20 => {
+                let (vec, std_vec) = &mut vecs[i];
+                let at = rng.gen_range(0..=std_vec.len());
+                let split = (vec.split_off(at), std_vec.split_off(at));
+                vec.validate();
+                split.0.validate();
+                vecs.push(split);
+            }
+            _ if vecs.len() > 1 => {
+                let (mut other, mut std_other) = vecs.swap_remove(i);
+                let j = rng.gen_range(0..vecs.len());
+                let (vec, std_vec) = &mut vecs[j];
+                vec.append(&mut other);
+                std_vec.append(&mut std_other);
+                assert!(other.is_empty());
+                vec.validate();
+            }
+            _ => {}
+        }
+        for (vec, std_vec) in &vecs {
+            assert_eq!(vec.len(), std_vec.len());
+            assert!(vec.iter().eq(std_vec.iter()));
+        }
+    }
+    for (vec, std_vec) in &vecs {
+        vec.validate();
+        for (index, value) in std_vec.iter().enumerate() {
+            assert_eq!(vec.get(index), Some(value));
+        }
+    }
+}
+
+#[test]
+pub fn split_off_edges() {
+    let store = BTreeStore::summarized();
+    for len in [0usize, 1, 7, 8, 9, 40, 100, 1000] {
+        for at in [0, 1, len / 2, len.saturating_sub(1), len] {
+            if at > len {
+                continue;
+            }
+            let mut vec = BTreeVec::new_in(&store);
+            vec.extend(0..len);
+            let right = vec.split_off(at);
+            vec.validate();
+            right.validate();
+            assert!(vec.iter().copied().eq(0..at));
+            assert!(right.iter().copied().eq(at..len));
+
+            let mut right = right;
+            vec.append(&mut right);
+            vec.validate();
+            assert!(vec.iter().copied().eq(0..len));
+        }
+    }
+
+    let mut vec = BTreeVec::new_in(&store);
+    vec.extend(0..100);
+    vec.clear();
+    vec.push(1);
+    vec.validate();
+    assert_eq!(format!("{:?}", vec), "[1]");
+}
+
+#[test]
+#[should_panic(expected = "attempted to index up to maximum usize")]
+pub fn range_to_inclusive_max() {
+    let store = BTreeStore::summarized();
+    let mut vec = BTreeVec::new_in(&store);
+    vec.extend(0..10);
+    vec.range(..=usize::MAX);
+}